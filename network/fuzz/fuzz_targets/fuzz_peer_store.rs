@@ -3,7 +3,10 @@
 use libfuzzer_sys::fuzz_target;
 
 use ckb_network::{
-    multiaddr::MultiAddr, peer_store::types::BannedAddr, peer_store::PeerStore, Flags, PeerId,
+    multiaddr::MultiAddr,
+    peer_store::types::{BanReason, BannedAddr},
+    peer_store::PeerStore,
+    Flags, PeerId,
 };
 use ckb_network_fuzz::BufManager;
 
@@ -43,7 +46,7 @@ fn add_ban_addr(data: &mut BufManager, peer_store: &mut PeerStore) {
             address: network,
             ban_until: data.get(),
             created_at: data.get(),
-            ban_reason: String::new(),
+            ban_reason: BanReason::Manual(String::new()),
         };
         peer_store.mut_ban_list().ban(ban_addr);
     }
@@ -30,7 +30,7 @@ pub use crate::{
     },
     peer::{Peer, PeerIdentifyInfo},
     peer_registry::PeerRegistry,
-    peer_store::Score,
+    peer_store::{types::BanReason, Score},
     protocols::{
         identify::Flags, support_protocols::SupportProtocols, CKBProtocol, CKBProtocolContext,
         CKBProtocolHandler, PeerIndex,
@@ -2,7 +2,10 @@
 use crate::errors::{Error, P2PError};
 use crate::peer_registry::{ConnectionStatus, PeerRegistry};
 use crate::peer_store::{
-    types::{AddrInfo, BannedAddr},
+    types::{
+        multiaddr_to_ip_network, multiaddr_to_ip_networks, AddrInfo, BanReason, BannedAddr,
+        DnsResolver, SystemDnsResolver,
+    },
     PeerStore,
 };
 use crate::protocols::{
@@ -69,7 +72,7 @@ const DIAL_HANG_TIMEOUT: Duration = Duration::from_secs(300);
 /// The global shared state of the network module
 pub struct NetworkState {
     pub(crate) peer_registry: RwLock<PeerRegistry>,
-    pub(crate) peer_store: Mutex<PeerStore>,
+    pub(crate) peer_store: Arc<Mutex<PeerStore>>,
     /// Node listened addresses
     pub(crate) listened_addrs: RwLock<Vec<Multiaddr>>,
     dialing_addrs: RwLock<HashMap<PeerId, Instant>>,
@@ -86,6 +89,8 @@ pub struct NetworkState {
     /// fields: ProtocolId, Protocol Name, Supported Versions
     pub(crate) protocols: RwLock<Vec<(ProtocolId, String, Vec<String>)>>,
     pub(crate) required_flags: Flags,
+    /// Resolves the hostnames of the addrs of banned peers
+    dns_resolver: Arc<dyn DnsResolver + Send + Sync>,
 
     pub(crate) ckb2023: AtomicBool,
 }
@@ -114,9 +119,9 @@ impl NetworkState {
             })
             .collect();
         info!("Loading the peer store. This process may take a few seconds to complete.");
-        let peer_store = Mutex::new(PeerStore::load_from_dir_or_default(
+        let peer_store = Arc::new(Mutex::new(PeerStore::load_from_dir_or_default(
             config.peer_store_path(),
-        ));
+        )));
         let bootnodes = config.bootnodes();
 
         let peer_registry = PeerRegistry::new(
@@ -140,6 +145,7 @@ impl NetworkState {
             active: AtomicBool::new(true),
             protocols: RwLock::new(Vec::new()),
             required_flags: Flags::SYNC | Flags::DISCOVERY | Flags::RELAY,
+            dns_resolver: Arc::new(SystemDnsResolver),
             ckb2023: AtomicBool::new(false),
         })
     }
@@ -157,6 +163,13 @@ impl NetworkState {
         self
     }
 
+    /// Resolver of the hostnames of banned peers, whose IPs are banned with them
+    /// default with `SystemDnsResolver`
+    pub fn dns_resolver(mut self, resolver: Arc<dyn DnsResolver + Send + Sync>) -> Self {
+        self.dns_resolver = resolver;
+        self
+    }
+
     pub(crate) fn report_session(
         &self,
         p2p_control: &ServiceControl,
@@ -183,12 +196,43 @@ impl NetworkState {
         }
     }
 
+    /// Ban the IPs of an addr. The hostname of a dns addr is resolved on the blocking pool of the
+    /// runtime as the lookup blocks, the addr stops being dialed at once though.
+    pub(crate) fn ban_addr(&self, addr: Multiaddr, timeout_ms: u64, reason: BanReason) {
+        if let Some(network) = multiaddr_to_ip_network(&addr) {
+            self.peer_store
+                .lock()
+                .ban_addr_networks(&addr, vec![network], timeout_ms, reason);
+            return;
+        }
+        self.peer_store
+            .lock()
+            .ban_addr_networks(&addr, Vec::new(), timeout_ms, reason.clone());
+        let peer_store = Arc::clone(&self.peer_store);
+        let dns_resolver = Arc::clone(&self.dns_resolver);
+        let resolve = move || match multiaddr_to_ip_networks(&addr, dns_resolver.as_ref()) {
+            Some(networks) => peer_store
+                .lock()
+                .ban_addr_networks(&addr, networks, timeout_ms, reason),
+            None => warn!(
+                "Ban addr {:?}: its hostname can't be resolved, no IP is banned",
+                addr
+            ),
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(resolve);
+            }
+            Err(_) => resolve(),
+        }
+    }
+
     pub(crate) fn ban_session(
         &self,
         p2p_control: &ServiceControl,
         session_id: SessionId,
         duration: Duration,
-        reason: String,
+        reason: BanReason,
     ) {
         if let Some(addr) = self.with_peer_registry(|reg| {
             reg.get_peer(session_id)
@@ -206,8 +250,8 @@ impl NetworkState {
             }
             if let Some(peer) = self.with_peer_registry_mut(|reg| reg.remove_peer(session_id)) {
                 let message = format!("Ban for {} seconds, reason: {}", duration.as_secs(), reason);
-                self.peer_store.lock().ban_addr(
-                    &peer.connected_addr,
+                self.ban_addr(
+                    peer.connected_addr.clone(),
                     duration.as_millis() as u64,
                     reason,
                 );
@@ -598,7 +642,7 @@ impl ServiceHandle for EventHandler {
                     &context.control().clone().into(),
                     id,
                     Duration::from_secs(300),
-                    message,
+                    BanReason::ProtocolViolation(message),
                 );
             }
             ServiceError::SessionTimeout { session_context } => {
@@ -641,7 +685,9 @@ impl ServiceHandle for EventHandler {
                             &context.control().clone().into(),
                             id,
                             Duration::from_secs(300),
-                            format!("protocol {proto_id} panic when process peer message"),
+                            BanReason::ProtocolViolation(format!(
+                                "protocol {proto_id} panic when process peer message"
+                            )),
                         );
                     }
                     #[cfg(feature = "with_sentry")]
@@ -1253,8 +1299,8 @@ impl NetworkController {
     }
 
     /// Ban an ip
-    pub fn ban(&self, address: IpNetwork, ban_until: u64, ban_reason: String) {
-        self.disconnect_peers_in_ip_range(address, &ban_reason);
+    pub fn ban(&self, address: IpNetwork, ban_until: u64, ban_reason: BanReason) {
+        self.disconnect_peers_in_ip_range(address, &ban_reason.to_string());
         self.network_state
             .peer_store
             .lock()
@@ -1281,7 +1327,7 @@ impl NetworkController {
     }
 
     /// Ban an peer through peer index
-    pub fn ban_peer(&self, peer_index: PeerIndex, duration: Duration, reason: String) {
+    pub fn ban_peer(&self, peer_index: PeerIndex, duration: Duration, reason: BanReason) {
         self.network_state
             .ban_session(&self.p2p_control, peer_index, duration, reason);
     }
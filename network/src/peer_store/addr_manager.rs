@@ -1,5 +1,5 @@
 //! Address manager
-use crate::peer_store::types::AddrInfo;
+use crate::peer_store::{types::AddrInfo, Score};
 use p2p::{multiaddr::Multiaddr, utils::multiaddr_to_socketaddr};
 use rand::Rng;
 use std::collections::{HashMap, HashSet};
@@ -86,6 +86,14 @@ impl AddrManager {
         self.id_to_info.values()
     }
 
+    /// Return addresses whose score falls in `min..=max`
+    pub fn get_addrs_by_score_range(&self, min: Score, max: Score) -> Vec<AddrInfo> {
+        self.addrs_iter()
+            .filter(|addr_info| (min..=max).contains(&addr_info.score))
+            .cloned()
+            .collect()
+    }
+
     /// Remove an address by ip and port
     pub fn remove(&mut self, addr: &Multiaddr) -> Option<AddrInfo> {
         multiaddr_to_socketaddr(addr).and_then(|addr| {
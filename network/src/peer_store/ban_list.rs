@@ -1,5 +1,5 @@
 //! Ban list
-use crate::peer_store::types::{ip_to_network, BannedAddr};
+use crate::peer_store::types::{ip_to_network, ip_to_subnet, BannedAddr};
 use crate::peer_store::Multiaddr;
 use ckb_systemtime::unix_time_as_millis;
 use ipnetwork::IpNetwork;
@@ -30,8 +30,10 @@ impl BanList {
         }
     }
 
-    /// Ban address
-    pub fn ban(&mut self, banned_addr: BannedAddr) {
+    /// Ban address, the host bits of the network are cleared, e.g. banning `1.2.3.4/24` bans
+    /// `1.2.3.0/24`
+    pub fn ban(&mut self, mut banned_addr: BannedAddr) {
+        banned_addr.address = masked(&banned_addr.address);
         self.inner.insert(banned_addr.address, banned_addr);
         let (insert_count, _) = self.insert_count.overflowing_add(1);
         self.insert_count = insert_count;
@@ -42,7 +44,7 @@ impl BanList {
 
     /// Unban address
     pub fn unban_network(&mut self, ip_network: &IpNetwork) {
-        self.inner.remove(ip_network);
+        self.inner.remove(&masked(ip_network));
     }
 
     fn is_ip_banned_until(&self, ip: IpAddr, now_ms: u64) -> bool {
@@ -87,3 +89,7 @@ impl BanList {
         self.inner.len()
     }
 }
+
+fn masked(ip_network: &IpNetwork) -> IpNetwork {
+    ip_to_subnet(ip_network.ip(), ip_network.prefix()).expect("valid prefix")
+}
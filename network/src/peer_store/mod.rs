@@ -30,6 +30,18 @@ pub(crate) const ADDR_TRY_TIMEOUT_MS: u64 = 3 * 24 * 3600 * 1000;
 pub(crate) const DIAL_INTERVAL: u64 = 15 * 1000;
 const ADDR_MAX_RETRIES: u32 = 3;
 const ADDR_MAX_FAILURES: u32 = 10;
+/// Consider an addr is not connectable if its score is below this, between the default score
+/// and the ban score of `PeerScoreConfig`
+pub(crate) const TERRIBLE_SCORE: Score = 60;
+/// The base of the exponential backoff between failed dials of an addr, the first retry is
+/// allowed after `2 * ADDR_RETRY_BASE_MS`
+pub(crate) const ADDR_RETRY_BASE_MS: u64 = 30 * 1000;
+/// The ceiling of the backoff between failed dials of an addr
+pub(crate) const ADDR_RETRY_MAX_MS: u64 = 4 * 3600 * 1000;
+/// The neutral score of a peer, scores decay towards it over time
+pub(crate) const DEFAULT_SCORE: Score = 100;
+/// The time in which the distance of a peer's score to `DEFAULT_SCORE` halves
+pub(crate) const SCORE_HALF_LIFE_MS: u64 = 7 * 24 * 3600 * 1000;
 
 /// Alias score
 pub type Score = i32;
@@ -48,7 +60,7 @@ pub struct PeerScoreConfig {
 impl Default for PeerScoreConfig {
     fn default() -> Self {
         PeerScoreConfig {
-            default_score: 100,
+            default_score: DEFAULT_SCORE,
             ban_score: 40,
             ban_timeout_ms: 24 * 3600 * 1000, // 1 day
         }
@@ -4,7 +4,7 @@ use crate::{
         addr_manager::AddrManager,
         ban_list::BanList,
         types::{AddrInfo, BannedAddr},
-        PeerStore,
+        PeerStore, SCORE_HALF_LIFE_MS,
     },
 };
 use ckb_logger::{debug, error};
@@ -22,7 +22,11 @@ impl AddrManager {
     pub fn load<R: Read>(r: R) -> Result<Self, Error> {
         let addrs: Vec<AddrInfo> = serde_json::from_reader(r).map_err(PeerStoreError::Serde)?;
         let mut addr_manager = AddrManager::default();
-        addrs.into_iter().for_each(|addr| addr_manager.add(addr));
+        let now_ms = ckb_systemtime::unix_time_as_millis();
+        addrs.into_iter().for_each(|mut addr| {
+            addr.decay_score(now_ms, SCORE_HALF_LIFE_MS);
+            addr_manager.add(addr)
+        });
         Ok(addr_manager)
     }
 
@@ -137,6 +141,16 @@ impl PeerStore {
         move_file(tmp_ban_list, path.as_ref().join(DEFAULT_BAN_LIST_DB))?;
         Ok(())
     }
+
+    /// Dump all info to disk if it changed since the last dump, returns whether it was dumped
+    pub fn dump_to_dir_if_dirty<P: AsRef<Path>>(&mut self, path: P) -> Result<bool, Error> {
+        if !self.is_dirty() {
+            return Ok(false);
+        }
+        self.dump_to_dir(path)?;
+        self.clear_dirty();
+        Ok(true)
+    }
 }
 
 /// This function use `copy` then `remove_file` as a fallback when `rename` failed,
@@ -5,13 +5,13 @@ use crate::{
     peer_store::{
         addr_manager::AddrManager,
         ban_list::BanList,
-        types::{ip_to_network, AddrInfo, BannedAddr, PeerInfo},
+        types::{ip_to_network, ip_to_subnet, AddrInfo, BanReason, BannedAddr, PeerInfo},
         Behaviour, Multiaddr, PeerScoreConfig, ReportResult, Status, ADDR_COUNT_LIMIT,
         ADDR_TIMEOUT_MS, ADDR_TRY_TIMEOUT_MS, DIAL_INTERVAL,
     },
     Flags, PeerId, SessionType,
 };
-use ipnetwork::IpNetwork;
+use ipnetwork::{IpNetwork, IpNetworkError};
 use rand::prelude::IteratorRandom;
 use std::collections::{hash_map::Entry, HashMap};
 
@@ -25,6 +25,8 @@ pub struct PeerStore {
     ban_list: BanList,
     connected_peers: HashMap<PeerId, PeerInfo>,
     score_config: PeerScoreConfig,
+    /// Whether the persisted addresses or bans changed since the last dump
+    dirty: bool,
 }
 
 impl PeerStore {
@@ -35,6 +37,7 @@ impl PeerStore {
             ban_list,
             connected_peers: Default::default(),
             score_config: Default::default(),
+            dirty: false,
         }
     }
 
@@ -68,6 +71,7 @@ impl PeerStore {
         let score = self.score_config.default_score;
         self.addr_manager
             .add(AddrInfo::new(addr, 0, score, flags.bits()));
+        self.dirty = true;
         Ok(())
     }
 
@@ -88,6 +92,7 @@ impl PeerStore {
         addr_info.attempts_count = attempts_count;
 
         self.addr_manager.add(addr_info);
+        self.dirty = true;
         Ok(())
     }
 
@@ -103,6 +108,7 @@ impl PeerStore {
             score,
             flags.bits(),
         ));
+        self.dirty = true;
     }
 
     /// Update outbound peer last connected ms
@@ -111,7 +117,8 @@ impl PeerStore {
             return;
         }
         if let Some(info) = self.addr_manager.get_mut(&addr) {
-            info.last_connected_at_ms = ckb_systemtime::unix_time_as_millis()
+            info.last_connected_at_ms = ckb_systemtime::unix_time_as_millis();
+            self.dirty = true;
         }
     }
 
@@ -122,6 +129,7 @@ impl PeerStore {
 
     /// Get mut address manager
     pub fn mut_addr_manager(&mut self) -> &mut AddrManager {
+        self.dirty = true;
         &mut self.addr_manager
     }
 
@@ -130,11 +138,12 @@ impl PeerStore {
         if let Some(peer_addr) = self.addr_manager.get_mut(addr) {
             let score = peer_addr.score.saturating_add(behaviour.score());
             peer_addr.score = score;
+            self.dirty = true;
             if score < self.score_config.ban_score {
                 self.ban_addr(
                     addr,
                     self.score_config.ban_timeout_ms,
-                    format!("report behaviour {behaviour:?}"),
+                    BanReason::ProtocolViolation(format!("report behaviour {:?}", behaviour)),
                 );
                 return ReportResult::Banned;
             }
@@ -161,6 +170,7 @@ impl PeerStore {
         // Get info:
         // 1. Not already connected
         // 2. Connected within 3 days
+        // 3. Not backing off from failed dials
 
         let now_ms = ckb_systemtime::unix_time_as_millis();
         let peers = &self.connected_peers;
@@ -174,6 +184,7 @@ impl PeerStore {
                     && peer_addr.connected(|t| {
                         t > addr_expired_ms && t <= now_ms.saturating_sub(DIAL_INTERVAL)
                     })
+                    && peer_addr.should_retry(now_ms)
                     && required_flags_filter(
                         required_flags,
                         Flags::from_bits_truncate(peer_addr.flags),
@@ -187,7 +198,8 @@ impl PeerStore {
         // Get info:
         // 1. Not already connected
         // 2. Not already tried in a minute
-        // 3. Not connected within 3 days
+        // 3. Not backing off from failed dials
+        // 4. Not connected within 3 days
 
         let now_ms = ckb_systemtime::unix_time_as_millis();
         let addr_expired_ms = now_ms.saturating_sub(ADDR_TRY_TIMEOUT_MS);
@@ -198,6 +210,7 @@ impl PeerStore {
                     .map(|peer_id| !peers.contains_key(&peer_id))
                     .unwrap_or_default()
                     && !peer_addr.tried_in_last_minute(now_ms)
+                    && peer_addr.should_retry(now_ms)
                     && !peer_addr.connected(|t| t > addr_expired_ms)
             })
     }
@@ -218,15 +231,56 @@ impl PeerStore {
     }
 
     /// Ban an addr
-    pub(crate) fn ban_addr(&mut self, addr: &Multiaddr, timeout_ms: u64, ban_reason: String) {
+    pub(crate) fn ban_addr(&mut self, addr: &Multiaddr, timeout_ms: u64, ban_reason: BanReason) {
         if let Some(addr) = multiaddr_to_socketaddr(addr) {
             let network = ip_to_network(addr.ip());
             self.ban_network(network, timeout_ms, ban_reason)
         }
         self.addr_manager.remove(addr);
+        self.dirty = true;
     }
 
-    pub(crate) fn ban_network(&mut self, network: IpNetwork, timeout_ms: u64, ban_reason: String) {
+    /// Ban an addr by the IP networks it resolves to, see `multiaddr_to_ip_networks`. The
+    /// networks are resolved by the caller, as resolving a hostname blocks and must not
+    /// happen while the peer store is locked.
+    pub(crate) fn ban_addr_networks(
+        &mut self,
+        addr: &Multiaddr,
+        networks: Vec<IpNetwork>,
+        timeout_ms: u64,
+        ban_reason: BanReason,
+    ) {
+        for network in networks {
+            self.ban_network(network, timeout_ms, ban_reason.clone())
+        }
+        self.addr_manager.remove(addr);
+        self.dirty = true;
+    }
+
+    /// Ban the subnet of an addr, e.g. the /24 of an IPv4 addr, as abusive peers may rotate their
+    /// IPs within it
+    pub fn ban_addr_subnet(
+        &mut self,
+        addr: &Multiaddr,
+        prefix_len: u8,
+        timeout_ms: u64,
+        ban_reason: BanReason,
+    ) -> std::result::Result<(), IpNetworkError> {
+        if let Some(socket_addr) = multiaddr_to_socketaddr(addr) {
+            let network = ip_to_subnet(socket_addr.ip(), prefix_len)?;
+            self.ban_network(network, timeout_ms, ban_reason)
+        }
+        self.addr_manager.remove(addr);
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub(crate) fn ban_network(
+        &mut self,
+        network: IpNetwork,
+        timeout_ms: u64,
+        ban_reason: BanReason,
+    ) {
         let now_ms = ckb_systemtime::unix_time_as_millis();
         let ban_addr = BannedAddr {
             address: network,
@@ -249,12 +303,23 @@ impl PeerStore {
 
     /// Get mut ban list
     pub fn mut_ban_list(&mut self) -> &mut BanList {
+        self.dirty = true;
         &mut self.ban_list
     }
 
     /// Clear ban list
     pub fn clear_ban_list(&mut self) {
         std::mem::take(&mut self.ban_list);
+        self.dirty = true;
+    }
+
+    /// Whether the addresses or bans changed since the peer store was loaded or last dumped
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub(crate) fn clear_dirty(&mut self) {
+        self.dirty = false;
     }
 
     /// Check and try delete addrs if reach limit
@@ -1,12 +1,18 @@
 //! Type used on peer store
 use crate::{
-    peer_store::{Score, SessionType, ADDR_MAX_FAILURES, ADDR_MAX_RETRIES, ADDR_TIMEOUT_MS},
+    peer_store::{
+        Score, SessionType, ADDR_MAX_FAILURES, ADDR_MAX_RETRIES, ADDR_RETRY_BASE_MS,
+        ADDR_RETRY_MAX_MS, ADDR_TIMEOUT_MS, DEFAULT_SCORE, TERRIBLE_SCORE,
+    },
     Flags,
 };
-use ipnetwork::IpNetwork;
+use ipnetwork::{IpNetwork, IpNetworkError};
 use p2p::multiaddr::{Multiaddr, Protocol};
 use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
+use std::{
+    fmt,
+    net::{IpAddr, ToSocketAddrs},
+};
 
 /// Peer info
 #[derive(Debug, Clone)]
@@ -52,6 +58,13 @@ pub struct AddrInfo {
     /// Flags
     #[serde(default = "default_flags")]
     pub flags: u64,
+    /// The time before which the addr should not be dialed again, backs off exponentially with
+    /// the attempts count
+    #[serde(default)]
+    pub next_retry_at_ms: u64,
+    /// Last time the score decayed
+    #[serde(default)]
+    pub score_decayed_at_ms: u64,
 }
 
 fn default_flags() -> u64 {
@@ -69,6 +82,8 @@ impl AddrInfo {
             attempts_count: 0,
             random_id_pos: 0,
             flags,
+            next_retry_at_ms: 0,
+            score_decayed_at_ms: 0,
         }
     }
 
@@ -88,6 +103,10 @@ impl AddrInfo {
         if self.tried_in_last_minute(now_ms) {
             return true;
         }
+        // consider addr is not connectable if it misbehaves, even if it connects recently
+        if self.score < TERRIBLE_SCORE {
+            return false;
+        }
         // we give up if never connect to this addr
         if self.last_connected_at_ms == 0 && self.attempts_count >= ADDR_MAX_RETRIES {
             return false;
@@ -101,10 +120,16 @@ impl AddrInfo {
         true
     }
 
+    /// Whether the backoff since the last failed dial has elapsed
+    pub fn should_retry(&self, now_ms: u64) -> bool {
+        now_ms >= self.next_retry_at_ms
+    }
+
     /// Try dail count
     pub fn mark_tried(&mut self, tried_at_ms: u64) {
         self.last_tried_at_ms = tried_at_ms;
         self.attempts_count = self.attempts_count.saturating_add(1);
+        self.next_retry_at_ms = tried_at_ms.saturating_add(retry_delay_ms(self.attempts_count));
     }
 
     /// Mark last connected time
@@ -112,6 +137,29 @@ impl AddrInfo {
         self.last_connected_at_ms = connected_at_ms;
         // reset attempts
         self.attempts_count = 0;
+        self.next_retry_at_ms = 0;
+    }
+
+    /// Moves the score towards `DEFAULT_SCORE`, halving the distance every `half_life_ms`
+    /// elapsed since the addr was last connected or tried, or since the score last decayed.
+    ///
+    /// The score of an addr never connected nor tried is kept.
+    pub fn decay_score(&mut self, now_ms: u64, half_life_ms: u64) {
+        let since_ms = self
+            .last_connected_at_ms
+            .max(self.last_tried_at_ms)
+            .max(self.score_decayed_at_ms);
+        if since_ms == 0 || half_life_ms == 0 {
+            return;
+        }
+        let elapsed_ms = now_ms.saturating_sub(since_ms);
+        let distance = f64::from(self.score) - f64::from(DEFAULT_SCORE);
+        let decayed = distance * 0.5f64.powf(elapsed_ms as f64 / half_life_ms as f64);
+        let score = DEFAULT_SCORE.saturating_add(decayed.round() as Score);
+        if score != self.score {
+            self.score = score;
+            self.score_decayed_at_ms = now_ms;
+        }
     }
 
     /// Change address flags
@@ -120,6 +168,15 @@ impl AddrInfo {
     }
 }
 
+/// The backoff before dialing an addr again after `attempts_count` failed dials,
+/// `ADDR_RETRY_BASE_MS * 2^attempts_count` capped at `ADDR_RETRY_MAX_MS`
+fn retry_delay_ms(attempts_count: u32) -> u64 {
+    2u64.checked_pow(attempts_count)
+        .map(|factor| ADDR_RETRY_BASE_MS.saturating_mul(factor))
+        .unwrap_or(u64::MAX)
+        .min(ADDR_RETRY_MAX_MS)
+}
+
 /// Banned addr info
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct BannedAddr {
@@ -128,11 +185,94 @@ pub struct BannedAddr {
     /// Ban until time
     pub ban_until: u64,
     /// Ban reason
-    pub ban_reason: String,
+    pub ban_reason: BanReason,
     /// Ban time
     pub created_at: u64,
 }
 
+/// Ban reason
+///
+/// Each reason carries the detail given where the peer is banned. It is persisted as its
+/// display string, `<reason>: <detail>`; strings of previous versions which match no other
+/// reason are loaded as `Manual`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum BanReason {
+    /// Sent an invalid block
+    InvalidBlock(String),
+    /// Sent an invalid transaction
+    InvalidTransaction(String),
+    /// Violated a protocol, e.g. sent a malformed message
+    ProtocolViolation(String),
+    /// Failed to respond in time
+    Timeout(String),
+    /// Any other reason, e.g. given by the `set_ban` RPC
+    Manual(String),
+}
+
+/// Builds a ban reason of one kind from its detail
+type BanReasonKind = fn(String) -> BanReason;
+
+impl BanReason {
+    const INVALID_BLOCK: &'static str = "invalid block";
+    const INVALID_TRANSACTION: &'static str = "invalid transaction";
+    const PROTOCOL_VIOLATION: &'static str = "protocol violation";
+    const TIMEOUT: &'static str = "timeout";
+
+    /// The prefixes of the display strings of the reasons other than `Manual`
+    const KINDS: [(&'static str, BanReasonKind); 4] = [
+        (Self::INVALID_BLOCK, BanReason::InvalidBlock),
+        (Self::INVALID_TRANSACTION, BanReason::InvalidTransaction),
+        (Self::PROTOCOL_VIOLATION, BanReason::ProtocolViolation),
+        (Self::TIMEOUT, BanReason::Timeout),
+    ];
+}
+
+impl fmt::Display for BanReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, detail) = match self {
+            BanReason::InvalidBlock(detail) => (Self::INVALID_BLOCK, detail),
+            BanReason::InvalidTransaction(detail) => (Self::INVALID_TRANSACTION, detail),
+            BanReason::ProtocolViolation(detail) => (Self::PROTOCOL_VIOLATION, detail),
+            BanReason::Timeout(detail) => (Self::TIMEOUT, detail),
+            BanReason::Manual(reason) => return f.write_str(reason),
+        };
+        if detail.is_empty() {
+            f.write_str(kind)
+        } else {
+            write!(f, "{}: {}", kind, detail)
+        }
+    }
+}
+
+impl From<String> for BanReason {
+    fn from(reason: String) -> Self {
+        for (kind, ban_reason) in Self::KINDS {
+            if let Some(detail) = reason.strip_prefix(kind) {
+                if detail.is_empty() {
+                    return ban_reason(String::new());
+                }
+                if let Some(detail) = detail.strip_prefix(": ") {
+                    return ban_reason(detail.to_owned());
+                }
+            }
+        }
+        BanReason::Manual(reason)
+    }
+}
+
+impl From<&str> for BanReason {
+    fn from(reason: &str) -> Self {
+        reason.to_owned().into()
+    }
+}
+
+impl From<BanReason> for String {
+    fn from(reason: BanReason) -> Self {
+        reason.to_string()
+    }
+}
+
 /// Convert multiaddr to IpNetwork
 pub fn multiaddr_to_ip_network(multiaddr: &Multiaddr) -> Option<IpNetwork> {
     for addr_component in multiaddr {
@@ -145,6 +285,55 @@ pub fn multiaddr_to_ip_network(multiaddr: &Multiaddr) -> Option<IpNetwork> {
     None
 }
 
+/// Resolves the hostnames of dns multiaddrs
+pub trait DnsResolver {
+    /// Returns the IPs of the host, empty if it can't be resolved
+    fn resolve(&self, host: &str) -> Vec<IpAddr>;
+}
+
+/// Resolves hostnames with the resolver of the system, the lookup blocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemDnsResolver;
+
+impl DnsResolver for SystemDnsResolver {
+    fn resolve(&self, host: &str) -> Vec<IpAddr> {
+        (host, 0)
+            .to_socket_addrs()
+            .map(|addrs| addrs.map(|addr| addr.ip()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Convert multiaddr to IpNetworks, the hostname of a dns4 or dns6 multiaddr is resolved to
+/// all its IPs of the corresponding family.
+///
+/// Returns `None` if the multiaddr has neither IP nor hostname, or if the hostname can't be
+/// resolved.
+pub fn multiaddr_to_ip_networks<R: DnsResolver + ?Sized>(
+    multiaddr: &Multiaddr,
+    resolver: &R,
+) -> Option<Vec<IpNetwork>> {
+    let resolve = |host: &str, family: fn(&IpAddr) -> bool| {
+        let networks: Vec<_> = resolver
+            .resolve(host)
+            .into_iter()
+            .filter(family)
+            .map(ip_to_network)
+            .collect();
+        (!networks.is_empty()).then_some(networks)
+    };
+    for addr_component in multiaddr {
+        match addr_component {
+            Protocol::Ip4(ipv4) => return Some(vec![IpNetwork::V4(ipv4.into())]),
+            Protocol::Ip6(ipv6) => return Some(vec![IpNetwork::V6(ipv6.into())]),
+            Protocol::Dns4(host) => return resolve(&host, IpAddr::is_ipv4),
+            Protocol::Dns6(host) => return resolve(&host, IpAddr::is_ipv6),
+            _ => (),
+        }
+    }
+    None
+}
+
 /// Convert IpAddr to IpNetwork
 pub fn ip_to_network(ip: IpAddr) -> IpNetwork {
     match ip {
@@ -152,3 +341,10 @@ pub fn ip_to_network(ip: IpAddr) -> IpNetwork {
         IpAddr::V6(ipv6) => IpNetwork::V6(ipv6.into()),
     }
 }
+
+/// Convert IpAddr to the IpNetwork of its subnet with the prefix length, e.g. `1.2.3.0/24` for
+/// `1.2.3.4` and 24
+pub fn ip_to_subnet(ip: IpAddr, prefix_len: u8) -> Result<IpNetwork, IpNetworkError> {
+    let network = IpNetwork::new(ip, prefix_len)?;
+    IpNetwork::new(network.network(), prefix_len)
+}
@@ -17,7 +17,9 @@ use p2p::{
 
 mod protocol;
 
-use crate::{peer_store::required_flags_filter, NetworkState, PeerIdentifyInfo, SupportProtocols};
+use crate::{
+    peer_store::required_flags_filter, BanReason, NetworkState, PeerIdentifyInfo, SupportProtocols,
+};
 use ckb_types::{packed, prelude::*};
 
 use protocol::IdentifyMessage;
@@ -407,7 +409,9 @@ impl Callback for IdentifyCallback {
                     &context.control().clone().into(),
                     context.session.id,
                     BAN_ON_NOT_SAME_NET,
-                    "The nodes are not on the same network".to_string(),
+                    BanReason::ProtocolViolation(
+                        "The nodes are not on the same network".to_string(),
+                    ),
                 );
                 MisbehaveResult::Disconnect
             }
@@ -35,7 +35,7 @@ pub type BoxedFutureTask = Pin<Box<dyn Future<Output = ()> + 'static + Send>>;
 use crate::{
     compress::{compress, decompress},
     network::{async_disconnect_with_message, disconnect_with_message},
-    Behaviour, Error, NetworkState, Peer, ProtocolVersion, SupportProtocols,
+    BanReason, Behaviour, Error, NetworkState, Peer, ProtocolVersion, SupportProtocols,
 };
 
 /// Abstract protocol context
@@ -119,7 +119,7 @@ pub trait CKBProtocolContext: Send {
     /// Report peer behavior
     fn report_peer(&self, peer_index: PeerIndex, behaviour: Behaviour);
     /// Ban peer
-    fn ban_peer(&self, peer_index: PeerIndex, duration: Duration, reason: String);
+    fn ban_peer(&self, peer_index: PeerIndex, duration: Duration, reason: BanReason);
     /// current protocol id
     fn protocol_id(&self) -> ProtocolId;
     /// Raw tentacle controller
@@ -620,7 +620,7 @@ impl CKBProtocolContext for DefaultCKBProtocolContext {
         self.network_state
             .report_session(&self.p2p_control, peer_index, behaviour);
     }
-    fn ban_peer(&self, peer_index: PeerIndex, duration: Duration, reason: String) {
+    fn ban_peer(&self, peer_index: PeerIndex, duration: Duration, reason: BanReason) {
         self.network_state
             .ban_session(&self.p2p_control, peer_index, duration, reason);
     }
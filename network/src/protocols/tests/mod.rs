@@ -7,7 +7,7 @@ use super::{
 };
 
 use crate::{
-    network::EventHandler, services::protocol_type_checker::ProtocolTypeCheckerService,
+    network::EventHandler, services::protocol_type_checker::ProtocolTypeCheckerService, BanReason,
     NetworkState, PeerIdentifyInfo, SupportProtocols,
 };
 
@@ -98,7 +98,7 @@ impl Node {
                 &self.control,
                 id,
                 Duration::from_secs(20),
-                Default::default(),
+                BanReason::Manual(String::new()),
             );
         }
     }
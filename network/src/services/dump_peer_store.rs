@@ -28,10 +28,11 @@ impl DumpPeerStoreService {
     fn dump_peer_store(&self) {
         let path = self.network_state.config.peer_store_path();
         self.network_state.with_peer_store_mut(|peer_store| {
-            if let Err(err) = peer_store.dump_to_dir(&path) {
-                warn!("Dump peer store error, path: {:?} error: {}", path, err);
-            } else {
-                debug!("Dump peer store to {:?}", path);
+            // mutations between two dumps are batched into a single write
+            match peer_store.dump_to_dir_if_dirty(&path) {
+                Ok(true) => debug!("Dump peer store to {:?}", path),
+                Ok(false) => debug!("Peer store unchanged, skip dumping"),
+                Err(err) => warn!("Dump peer store error, path: {:?} error: {}", path, err),
             }
         });
     }
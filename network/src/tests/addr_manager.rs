@@ -1,6 +1,6 @@
 use crate::{
     multiaddr::Multiaddr,
-    peer_store::{addr_manager::AddrManager, types::AddrInfo},
+    peer_store::{addr_manager::AddrManager, types::AddrInfo, DEFAULT_SCORE, TERRIBLE_SCORE},
     PeerId,
 };
 use proptest::prelude::*;
@@ -17,7 +17,7 @@ proptest! {
             AddrInfo::new(
                 addr,
                 0,
-                0,
+                100,
                 0
             )
         }
@@ -40,3 +40,54 @@ proptest! {
         assert_eq!(addrs.len(), count);
     }
 }
+
+#[test]
+fn test_get_addrs_by_score_range() {
+    let mut addr_manager: AddrManager = Default::default();
+    for (i, score) in [-50, -10, 0, 10, 50, 100].into_iter().enumerate() {
+        let ip = Ipv4Addr::from(((225 << 24) + i + 1) as u32);
+        let addr: Multiaddr = format!("/ip4/{}/tcp/42/p2p/{}", ip, PeerId::random().to_base58())
+            .parse()
+            .unwrap();
+        addr_manager.add(AddrInfo::new(addr, 0, score, 0));
+    }
+
+    let mut scores: Vec<_> = addr_manager
+        .get_addrs_by_score_range(-10, 50)
+        .into_iter()
+        .map(|addr_info| addr_info.score)
+        .collect();
+    scores.sort_unstable();
+    assert_eq!(scores, vec![-10, 0, 10, 50]);
+
+    assert!(addr_manager.get_addrs_by_score_range(60, 90).is_empty());
+    assert!(addr_manager.get_addrs_by_score_range(50, -10).is_empty());
+}
+
+#[test]
+fn test_fetch_random_excludes_terrible_score() {
+    let mut addr_manager: AddrManager = Default::default();
+    let scores = [
+        TERRIBLE_SCORE - 10,
+        TERRIBLE_SCORE - 1,
+        TERRIBLE_SCORE,
+        DEFAULT_SCORE,
+    ];
+    for (i, score) in scores.into_iter().enumerate() {
+        let ip = Ipv4Addr::from(((225 << 24) + i + 1) as u32);
+        let addr: Multiaddr = format!("/ip4/{}/tcp/42/p2p/{}", ip, PeerId::random().to_base58())
+            .parse()
+            .unwrap();
+        addr_manager.add(AddrInfo::new(addr, 0, score, 0));
+    }
+
+    let mut fetched_scores: Vec<_> = addr_manager
+        .fetch_random(scores.len(), |_| true)
+        .into_iter()
+        .map(|addr_info| addr_info.score)
+        .collect();
+    fetched_scores.sort_unstable();
+    assert_eq!(fetched_scores, vec![TERRIBLE_SCORE, DEFAULT_SCORE]);
+    // they are still kept by the addr manager
+    assert_eq!(addr_manager.count(), scores.len());
+}
@@ -3,12 +3,20 @@ use crate::{
     extract_peer_id,
     multiaddr::Multiaddr,
     peer_store::{
-        ban_list::CLEAR_INTERVAL_COUNTER, types::multiaddr_to_ip_network, PeerStore, Status,
-        ADDR_COUNT_LIMIT, ADDR_TRY_TIMEOUT_MS,
+        ban_list::CLEAR_INTERVAL_COUNTER,
+        types::{multiaddr_to_ip_network, multiaddr_to_ip_networks, AddrInfo, DnsResolver},
+        PeerStore, Status, ADDR_COUNT_LIMIT, ADDR_RETRY_BASE_MS, ADDR_RETRY_MAX_MS,
+        ADDR_TRY_TIMEOUT_MS, DEFAULT_SCORE, TERRIBLE_SCORE,
     },
-    Behaviour, Flags, PeerId, SessionType,
+    Behaviour, Flags, NetworkState, PeerId, SessionType,
+};
+use ckb_app_config::NetworkConfig;
+use ipnetwork::IpNetwork;
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::Arc,
 };
-use std::collections::HashSet;
 
 #[test]
 fn test_add_connected_peer() {
@@ -71,6 +79,56 @@ fn test_report() {
     assert!(peer_store.addr_manager().get(&addr).is_none())
 }
 
+#[test]
+fn test_ban_addr_subnet() {
+    let mut peer_store: PeerStore = Default::default();
+    let addr: Multiaddr = format!("/ip4/1.2.3.4/tcp/42/p2p/{}", PeerId::random().to_base58())
+        .parse()
+        .unwrap();
+    peer_store
+        .add_addr(addr.clone(), Flags::COMPATIBILITY)
+        .unwrap();
+    peer_store
+        .ban_addr_subnet(&addr, 24, 10_000, "no reason".into())
+        .unwrap();
+    assert!(peer_store.addr_manager().get(&addr).is_none());
+
+    let banned_addrs = peer_store.ban_list().get_banned_addrs();
+    assert_eq!(banned_addrs.len(), 1);
+    assert_eq!(banned_addrs[0].address, "1.2.3.0/24".parse().unwrap());
+    assert!(peer_store
+        .ban_list()
+        .is_ip_banned(&"1.2.3.200".parse().unwrap()));
+    assert!(!peer_store
+        .ban_list()
+        .is_ip_banned(&"1.2.4.1".parse().unwrap()));
+    assert!(peer_store.is_addr_banned(&"/ip4/1.2.3.200/tcp/42".parse().unwrap()));
+    assert!(!peer_store.is_addr_banned(&"/ip4/1.2.4.1/tcp/42".parse().unwrap()));
+
+    // the subnet can be unbanned by any of its addresses
+    peer_store
+        .mut_ban_list()
+        .unban_network(&"1.2.3.4/24".parse().unwrap());
+    assert!(!peer_store
+        .ban_list()
+        .is_ip_banned(&"1.2.3.200".parse().unwrap()));
+
+    let addr_v6: Multiaddr = "/ip6/2001:db8:1:2:3::4/tcp/42".parse().unwrap();
+    peer_store
+        .ban_addr_subnet(&addr_v6, 64, 10_000, "no reason".into())
+        .unwrap();
+    assert!(peer_store
+        .ban_list()
+        .is_ip_banned(&"2001:db8:1:2:ffff::1".parse().unwrap()));
+    assert!(!peer_store
+        .ban_list()
+        .is_ip_banned(&"2001:db8:1:3::1".parse().unwrap()));
+
+    assert!(peer_store
+        .ban_addr_subnet(&addr, 33, 10_000, "no reason".into())
+        .is_err());
+}
+
 #[test]
 fn test_update_status() {
     let mut peer_store: PeerStore = Default::default();
@@ -288,6 +346,133 @@ fn test_fetch_addrs_to_feeler() {
     assert!(peer_store.fetch_addrs_to_feeler(1).is_empty());
 }
 
+#[test]
+fn test_addr_retry_backoff() {
+    let now = 1_000_000;
+    let mut paddr = AddrInfo::new(random_addr(), 0, 100, Flags::COMPATIBILITY.bits());
+    assert!(paddr.should_retry(now));
+
+    // the delay doubles with every failed dial
+    let mut last_delay = 0;
+    for attempts in 1..=5 {
+        paddr.mark_tried(now);
+        let delay = paddr.next_retry_at_ms - now;
+        assert_eq!(delay, ADDR_RETRY_BASE_MS << attempts);
+        assert!(delay > last_delay);
+        assert!(!paddr.should_retry(now + delay - 1));
+        assert!(paddr.should_retry(now + delay));
+        last_delay = delay;
+    }
+
+    // and stops growing at the ceiling
+    for _ in 0..100 {
+        paddr.mark_tried(now);
+    }
+    assert_eq!(paddr.next_retry_at_ms - now, ADDR_RETRY_MAX_MS);
+    assert!(!paddr.should_retry(now + ADDR_RETRY_MAX_MS - 1));
+
+    // a successful connection resets the backoff
+    paddr.mark_connected(now);
+    assert!(paddr.should_retry(now));
+    paddr.mark_tried(now);
+    assert_eq!(paddr.next_retry_at_ms - now, ADDR_RETRY_BASE_MS * 2);
+}
+
+#[test]
+fn test_fetch_addrs_to_feeler_backoff() {
+    let _faketime_guard = ckb_systemtime::faketime();
+    _faketime_guard.set_faketime(1_000_000);
+
+    let mut peer_store: PeerStore = Default::default();
+    let addr = random_addr();
+    peer_store
+        .add_addr(addr.clone(), Flags::COMPATIBILITY)
+        .unwrap();
+    for _ in 0..2 {
+        peer_store
+            .mut_addr_manager()
+            .get_mut(&addr)
+            .unwrap()
+            .mark_tried(1_000_000);
+    }
+
+    // tried more than a minute ago, but still backing off from 2 failed dials
+    _faketime_guard.set_faketime(1_000_000 + (ADDR_RETRY_BASE_MS << 2) - 1);
+    assert!(peer_store.fetch_addrs_to_feeler(1).is_empty());
+    _faketime_guard.set_faketime(1_000_000 + (ADDR_RETRY_BASE_MS << 2));
+    assert_eq!(peer_store.fetch_addrs_to_feeler(1).len(), 1);
+}
+
+#[test]
+fn test_terrible_score() {
+    let now = 10 * 60 * 1000;
+
+    // a misbehaving peer is not connectable even if it connected recently
+    let mut low_score_addr = AddrInfo::new(
+        random_addr(),
+        now - 1000,
+        TERRIBLE_SCORE - 1,
+        Flags::COMPATIBILITY.bits(),
+    );
+    assert!(!low_score_addr.is_connectable(now));
+    // but it is kept while it is being tried
+    low_score_addr.mark_tried(now);
+    assert!(low_score_addr.is_connectable(now));
+
+    let high_score_addr = AddrInfo::new(
+        random_addr(),
+        now - 1000,
+        TERRIBLE_SCORE,
+        Flags::COMPATIBILITY.bits(),
+    );
+    assert!(high_score_addr.is_connectable(now));
+}
+
+#[test]
+fn test_decay_score() {
+    let half_life = 1000;
+    let now = 1_000_000;
+
+    // a high score relaxes towards the default score
+    let mut high_score_addr = AddrInfo::new(
+        random_addr(),
+        now,
+        DEFAULT_SCORE + 100,
+        Flags::COMPATIBILITY.bits(),
+    );
+    high_score_addr.decay_score(now, half_life);
+    assert_eq!(high_score_addr.score, DEFAULT_SCORE + 100);
+    high_score_addr.decay_score(now + half_life, half_life);
+    assert_eq!(high_score_addr.score, DEFAULT_SCORE + 50);
+    high_score_addr.decay_score(now + 20 * half_life, half_life);
+    assert_eq!(high_score_addr.score, DEFAULT_SCORE);
+
+    // a penalized score recovers, the time already decayed is not counted twice
+    let mut penalized_addr = AddrInfo::new(
+        random_addr(),
+        0,
+        DEFAULT_SCORE - 40,
+        Flags::COMPATIBILITY.bits(),
+    );
+    penalized_addr.mark_tried(now);
+    penalized_addr.decay_score(now + half_life, half_life);
+    assert_eq!(penalized_addr.score, DEFAULT_SCORE - 20);
+    penalized_addr.decay_score(now + half_life, half_life);
+    assert_eq!(penalized_addr.score, DEFAULT_SCORE - 20);
+    penalized_addr.decay_score(now + 2 * half_life, half_life);
+    assert_eq!(penalized_addr.score, DEFAULT_SCORE - 10);
+
+    // unknown addrs keep their score
+    let mut unknown_addr = AddrInfo::new(
+        random_addr(),
+        0,
+        DEFAULT_SCORE - 40,
+        Flags::COMPATIBILITY.bits(),
+    );
+    unknown_addr.decay_score(now, half_life);
+    assert_eq!(unknown_addr.score, DEFAULT_SCORE - 40);
+}
+
 #[test]
 fn test_fetch_random_addrs() {
     let mut peer_store: PeerStore = Default::default();
@@ -588,3 +773,101 @@ fn test_addr_unique() {
 
     assert_eq!(peer_store.addr_manager().addrs_iter().count(), 2);
 }
+
+struct MockDnsResolver(HashMap<&'static str, Vec<IpAddr>>);
+
+impl DnsResolver for MockDnsResolver {
+    fn resolve(&self, host: &str) -> Vec<IpAddr> {
+        self.0.get(host).cloned().unwrap_or_default()
+    }
+}
+
+#[test]
+fn test_multiaddr_to_ip_networks() {
+    let resolver = MockDnsResolver(HashMap::from([
+        ("single.example.com", vec!["1.2.3.4".parse().unwrap()]),
+        (
+            "multi.example.com",
+            vec![
+                "1.2.3.4".parse().unwrap(),
+                "::1".parse().unwrap(),
+                "5.6.7.8".parse().unwrap(),
+            ],
+        ),
+    ]));
+    let networks = |addr: &str| multiaddr_to_ip_networks(&addr.parse().unwrap(), &resolver);
+    let network = |ip: &str| IpNetwork::from(ip.parse::<IpAddr>().unwrap());
+
+    assert_eq!(
+        networks("/ip4/127.0.0.1/tcp/42"),
+        Some(vec![network("127.0.0.1")])
+    );
+    assert_eq!(
+        networks("/dns4/single.example.com/tcp/42"),
+        Some(vec![network("1.2.3.4")])
+    );
+    // only the IPs of the family of the dns protocol
+    assert_eq!(
+        networks("/dns4/multi.example.com/tcp/42"),
+        Some(vec![network("1.2.3.4"), network("5.6.7.8")])
+    );
+    assert_eq!(
+        networks("/dns6/multi.example.com/tcp/42"),
+        Some(vec![network("::1")])
+    );
+    assert_eq!(networks("/dns6/single.example.com/tcp/42"), None);
+    assert_eq!(networks("/dns4/unknown.example.com/tcp/42"), None);
+    // the single IP function still ignores hostnames
+    assert_eq!(
+        multiaddr_to_ip_network(&"/dns4/single.example.com/tcp/42".parse().unwrap()),
+        None
+    );
+}
+
+#[test]
+fn test_ban_addr_networks() {
+    let resolver: Arc<dyn DnsResolver + Send + Sync> =
+        Arc::new(MockDnsResolver(HashMap::from([(
+            "multi.example.com",
+            vec!["1.2.3.4".parse().unwrap(), "5.6.7.8".parse().unwrap()],
+        )])));
+    let mut peer_store: PeerStore = Default::default();
+    let addr: Multiaddr = "/dns4/multi.example.com/tcp/42".parse().unwrap();
+    let networks = multiaddr_to_ip_networks(&addr, resolver.as_ref()).unwrap();
+    peer_store.ban_addr_networks(&addr, networks, 10_000, "no reason".into());
+
+    // every IP the hostname resolves to is banned
+    for banned in ["/ip4/1.2.3.4/tcp/42", "/ip4/5.6.7.8/tcp/43"] {
+        assert!(peer_store.is_addr_banned(&banned.parse().unwrap()));
+    }
+    assert!(!peer_store.is_addr_banned(&"/ip4/1.2.3.5/tcp/42".parse().unwrap()));
+}
+
+#[test]
+fn test_ban_unresolvable_addr() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let config = NetworkConfig {
+        path: tmp_dir.path().to_path_buf(),
+        ..Default::default()
+    };
+    let network_state = NetworkState::from_config(config)
+        .unwrap()
+        .dns_resolver(Arc::new(MockDnsResolver(HashMap::new())));
+    let addr: Multiaddr = format!(
+        "/dns4/unknown.example.com/tcp/42/p2p/{}",
+        PeerId::random().to_base58()
+    )
+    .parse()
+    .unwrap();
+    network_state.with_peer_store_mut(|peer_store| {
+        peer_store.add_outbound_addr(addr.clone(), Flags::COMPATIBILITY)
+    });
+
+    network_state.ban_addr(addr, 10_000, "no reason".into());
+
+    // the addr is no longer dialed even though no IP could be banned
+    network_state.with_peer_store_mut(|peer_store| {
+        assert_eq!(peer_store.addr_manager().addrs_iter().count(), 0);
+        assert!(peer_store.ban_list().get_banned_addrs().is_empty());
+    });
+}
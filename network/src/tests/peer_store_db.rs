@@ -1,10 +1,10 @@
 use crate::{
     multiaddr::Multiaddr,
     peer_store::{
-        types::{multiaddr_to_ip_network, AddrInfo, BannedAddr},
-        PeerStore,
+        types::{multiaddr_to_ip_network, AddrInfo, BanReason, BannedAddr},
+        PeerStore, DEFAULT_SCORE, SCORE_HALF_LIFE_MS,
     },
-    PeerId,
+    Flags, PeerId,
 };
 
 use std::fs::File;
@@ -90,6 +90,67 @@ fn test_peer_store_persistent() {
     );
 }
 
+#[test]
+fn test_peer_store_load_decays_scores() {
+    let now_ms = ckb_systemtime::unix_time_as_millis();
+    let mut peer_store = PeerStore::default();
+    let addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/42/p2p/{}", PeerId::random().to_base58())
+        .parse()
+        .unwrap();
+    peer_store.mut_addr_manager().add(AddrInfo::new(
+        addr.clone(),
+        now_ms - 2 * SCORE_HALF_LIFE_MS,
+        DEFAULT_SCORE - 40,
+        0,
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    peer_store.dump_to_dir(dir.path()).unwrap();
+    let mut peer_store2 = PeerStore::load_from_dir_or_default(dir.path());
+    let addr_info = peer_store2.mut_addr_manager().get_mut(&addr).unwrap();
+    assert_eq!(addr_info.score, DEFAULT_SCORE - 10);
+    assert!(addr_info.score_decayed_at_ms >= now_ms);
+}
+
+#[test]
+fn test_ban_reason_serde() {
+    let banned_addr = |ban_reason| BannedAddr {
+        address: "127.0.0.1/32".parse().unwrap(),
+        ban_until: 10_000,
+        ban_reason,
+        created_at: 0,
+    };
+    for ban_reason in [
+        BanReason::InvalidBlock("BlockIsInvalid(401)".to_owned()),
+        BanReason::InvalidTransaction(String::new()),
+        BanReason::ProtocolViolation("send us a malformed message".to_owned()),
+        BanReason::Timeout(String::new()),
+        BanReason::Manual("send us a malformed message".to_owned()),
+        BanReason::Manual(String::new()),
+    ] {
+        let json = serde_json::to_string(&banned_addr(ban_reason.clone())).unwrap();
+        assert!(json.contains(&format!(r#""ban_reason":"{ban_reason}""#)));
+        let loaded: BannedAddr = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, banned_addr(ban_reason));
+    }
+
+    // reasons stored by previous versions
+    let json = r#"{"address":"127.0.0.1/32","ban_until":10000,"ban_reason":"report behaviour TestBad","created_at":0}"#;
+    let loaded: BannedAddr = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        loaded.ban_reason,
+        BanReason::Manual("report behaviour TestBad".to_owned())
+    );
+    let json =
+        r#"{"address":"127.0.0.1/32","ban_until":10000,"ban_reason":"timeout","created_at":0}"#;
+    let loaded: BannedAddr = serde_json::from_str(json).unwrap();
+    assert_eq!(loaded.ban_reason, BanReason::Timeout(String::new()));
+    let json =
+        r#"{"address":"127.0.0.1/32","ban_until":10000,"ban_reason":"timeouts","created_at":0}"#;
+    let loaded: BannedAddr = serde_json::from_str(json).unwrap();
+    assert_eq!(loaded.ban_reason, BanReason::Manual("timeouts".to_owned()));
+}
+
 #[test]
 fn test_peer_store_load_from_dir_should_not_panic() {
     // should return an empty store when dir does not exist
@@ -186,3 +247,40 @@ fn test_peer_store_dump_with_broken_tmp_file_should_be_ok() {
     assert_eq!(1, peer_store.ban_list().count());
     assert_eq!(3, peer_store.addr_manager().count());
 }
+
+#[test]
+fn test_peer_store_dump_only_when_dirty() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut peer_store = PeerStore::load_from_dir_or_default(dir.path());
+    assert!(!peer_store.is_dirty());
+
+    // rapid mutations between two periodic dumps are batched into one write
+    for port in 0..1000u16 {
+        let addr: Multiaddr = format!(
+            "/ip4/127.0.0.1/tcp/{}/p2p/{}",
+            port,
+            PeerId::random().to_base58()
+        )
+        .parse()
+        .unwrap();
+        peer_store.add_addr(addr, Flags::all()).unwrap();
+    }
+    let writes = (0..10)
+        .filter(|_| peer_store.dump_to_dir_if_dirty(dir.path()).unwrap())
+        .count();
+    assert_eq!(1, writes);
+    assert!(!peer_store.is_dirty());
+    assert_eq!(
+        1000,
+        PeerStore::load_from_dir_or_default(dir.path())
+            .addr_manager()
+            .count()
+    );
+
+    // a later mutation is written by the next dump
+    peer_store.mut_ban_list().unban_network(
+        &multiaddr_to_ip_network(&"/ip4/127.0.0.1/tcp/42".parse().unwrap()).unwrap(),
+    );
+    assert!(peer_store.dump_to_dir_if_dirty(dir.path()).unwrap());
+    assert!(!peer_store.dump_to_dir_if_dirty(dir.path()).unwrap());
+}
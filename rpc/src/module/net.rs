@@ -5,7 +5,9 @@ use ckb_jsonrpc_types::{
     BannedAddr, LocalNode, LocalNodeProtocol, NodeAddress, PeerSyncState, RemoteNode,
     RemoteNodeProtocol, SyncState, Timestamp,
 };
-use ckb_network::{extract_peer_id, multiaddr::Multiaddr, NetworkController};
+use ckb_network::{
+    extract_peer_id, multiaddr::Multiaddr, peer_store::types::BanReason, NetworkController,
+};
 use ckb_sync::SyncShared;
 use ckb_systemtime::unix_time_as_millis;
 use ckb_types::prelude::{Pack, Unpack};
@@ -675,7 +677,7 @@ impl NetRpc for NetRpcImpl {
             .map(|banned| BannedAddr {
                 address: banned.address.to_string(),
                 ban_until: banned.ban_until.into(),
-                ban_reason: banned.ban_reason,
+                ban_reason: banned.ban_reason.to_string(),
                 created_at: banned.created_at.into(),
             })
             .collect())
@@ -710,8 +712,11 @@ impl NetRpc for NetRpcImpl {
                             .unwrap_or_else(|| DEFAULT_BAN_DURATION.into())
                             .value()
                 };
-                self.network_controller
-                    .ban(ip_network, ban_until, reason.unwrap_or_default());
+                self.network_controller.ban(
+                    ip_network,
+                    ban_until,
+                    BanReason::Manual(reason.unwrap_or_default()),
+                );
                 Ok(())
             }
             "delete" => {
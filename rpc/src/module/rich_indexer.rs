@@ -2,9 +2,10 @@ use crate::error::RPCError;
 use async_trait::async_trait;
 use ckb_jsonrpc_types::{
     IndexerCell, IndexerCellsCapacity, IndexerOrder, IndexerPagination, IndexerSearchKey,
-    IndexerTip, IndexerTx, JsonBytes, Uint32,
+    IndexerSyncStatus, IndexerTip, IndexerTx, JsonBytes, Uint32,
 };
 use ckb_rich_indexer::AsyncRichIndexerHandle;
+use ckb_shared::shared::Shared;
 use jsonrpc_core::Result;
 use jsonrpc_utils::rpc;
 
@@ -24,6 +25,16 @@ pub trait RichIndexerRpc {
     #[rpc(name = "get_indexer_tip")]
     async fn get_indexer_tip(&self) -> Result<Option<IndexerTip>>;
 
+    /// Returns the indexed tip and how many blocks the rich-indexer is behind the node tip.
+    ///
+    /// ## Returns
+    ///   * indexer_tip - indexed tip, `null` if no block has been indexed yet
+    ///       - block_hash - indexed tip block hash
+    ///       - block_number - indexed tip block number
+    ///   * lag - the number of blocks the rich-indexer is behind the node tip
+    #[rpc(name = "get_sync_status")]
+    async fn get_sync_status(&self) -> Result<IndexerSyncStatus>;
+
     /// Returns the live cells collection by the lock or type script.
     ///
     /// The difference from the original CKB Indexer is that the `script_search_mode` parameter accepts the `partial` enumeration value. This implies that a partial search can be conducted on the `args` of the `script`.
@@ -160,11 +171,12 @@ pub trait RichIndexerRpc {
 #[derive(Clone)]
 pub(crate) struct RichIndexerRpcImpl {
     pub(crate) handle: AsyncRichIndexerHandle,
+    pub(crate) shared: Shared,
 }
 
 impl RichIndexerRpcImpl {
-    pub fn new(handle: AsyncRichIndexerHandle) -> Self {
-        RichIndexerRpcImpl { handle }
+    pub fn new(handle: AsyncRichIndexerHandle, shared: Shared) -> Self {
+        RichIndexerRpcImpl { handle, shared }
     }
 }
 
@@ -177,6 +189,14 @@ impl RichIndexerRpc for RichIndexerRpcImpl {
             .map_err(|e| RPCError::custom(RPCError::Indexer, e))
     }
 
+    async fn get_sync_status(&self) -> Result<IndexerSyncStatus> {
+        let node_tip_number = self.shared.snapshot().tip_number();
+        self.handle
+            .get_sync_status(node_tip_number.into())
+            .await
+            .map_err(|e| RPCError::custom(RPCError::Indexer, e))
+    }
+
     async fn get_cells(
         &self,
         search_key: IndexerSearchKey,
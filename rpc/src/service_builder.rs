@@ -238,7 +238,7 @@ impl<'a> ServiceBuilder<'a> {
             }
 
             let rich_indexer_handle = rich_indexer.async_handle();
-            let rich_indexer_methods = RichIndexerRpcImpl::new(rich_indexer_handle);
+            let rich_indexer_methods = RichIndexerRpcImpl::new(rich_indexer_handle, shared.clone());
             self = set_rpc_module_methods!(
                 self,
                 "RichIndexer",
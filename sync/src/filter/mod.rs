@@ -11,7 +11,8 @@ use crate::utils::{metric_ckb_message_bytes, MetricDirection};
 use ckb_constant::sync::BAD_MESSAGE_BAN_TIME;
 use ckb_logger::{debug_target, error_target, info_target, warn_target};
 use ckb_network::{
-    async_trait, bytes::Bytes, CKBProtocolContext, CKBProtocolHandler, PeerIndex, SupportProtocols,
+    async_trait, bytes::Bytes, BanReason, CKBProtocolContext, CKBProtocolHandler, PeerIndex,
+    SupportProtocols,
 };
 use ckb_types::{packed, prelude::*};
 use std::sync::Arc;
@@ -88,7 +89,7 @@ impl BlockFilter {
                 ban_time,
                 status
             );
-            nc.ban_peer(peer, ban_time, status.to_string());
+            nc.ban_peer(peer, ban_time, status.ban_reason());
         } else if status.should_warn() {
             warn_target!(
                 crate::LOG_TARGET_RELAY,
@@ -130,7 +131,7 @@ impl CKBProtocolHandler for BlockFilter {
                 nc.ban_peer(
                     peer_index,
                     BAD_MESSAGE_BAN_TIME,
-                    String::from("send us a malformed message"),
+                    BanReason::ProtocolViolation(String::from("send us a malformed message")),
                 );
                 return;
             }
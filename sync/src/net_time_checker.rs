@@ -2,7 +2,7 @@ use crate::utils::send_message_to;
 use ckb_constant::sync::BAD_MESSAGE_BAN_TIME;
 use ckb_logger::{debug, info, warn};
 use ckb_network::async_trait;
-use ckb_network::{bytes::Bytes, CKBProtocolContext, CKBProtocolHandler, PeerIndex};
+use ckb_network::{bytes::Bytes, BanReason, CKBProtocolContext, CKBProtocolHandler, PeerIndex};
 use ckb_types::{packed, prelude::*};
 use ckb_util::RwLock;
 use std::collections::VecDeque;
@@ -146,7 +146,7 @@ impl CKBProtocolHandler for NetTimeProtocol {
                 nc.ban_peer(
                     peer_index,
                     BAD_MESSAGE_BAN_TIME,
-                    String::from("send us a malformed message"),
+                    BanReason::ProtocolViolation(String::from("send us a malformed message")),
                 );
                 return;
             }
@@ -31,7 +31,7 @@ use ckb_logger::{
     debug, debug_target, error, error_target, info_target, trace_target, warn_target,
 };
 use ckb_network::{
-    async_trait, bytes::Bytes, tokio, CKBProtocolContext, CKBProtocolHandler, PeerIndex,
+    async_trait, bytes::Bytes, tokio, BanReason, CKBProtocolContext, CKBProtocolHandler, PeerIndex,
     SupportProtocols, TargetSession,
 };
 use ckb_shared::block_status::BlockStatus;
@@ -218,7 +218,7 @@ impl Relayer {
                 ban_time,
                 status
             );
-            nc.ban_peer(peer, ban_time, status.to_string());
+            nc.ban_peer(peer, ban_time, status.ban_reason());
         } else if status.should_warn() {
             warn_target!(
                 crate::LOG_TARGET_RELAY,
@@ -861,10 +861,10 @@ impl CKBProtocolHandler for Relayer {
                         nc.ban_peer(
                             peer_index,
                             BAD_MESSAGE_BAN_TIME,
-                            String::from(
+                            BanReason::ProtocolViolation(String::from(
                                 "send us a malformed message: \
                                  too many fields in CompactBlock",
-                            ),
+                            )),
                         );
                         return;
                     } else {
@@ -883,10 +883,10 @@ impl CKBProtocolHandler for Relayer {
                             nc.ban_peer(
                                 peer_index,
                                 BAD_MESSAGE_BAN_TIME,
-                                String::from(
+                                BanReason::ProtocolViolation(String::from(
                                     "send us a malformed message \
                                      too many fields",
-                                ),
+                                )),
                             );
                             return;
                         }
@@ -902,7 +902,7 @@ impl CKBProtocolHandler for Relayer {
                 nc.ban_peer(
                     peer_index,
                     BAD_MESSAGE_BAN_TIME,
-                    String::from("send us a malformed message"),
+                    BanReason::ProtocolViolation(String::from("send us a malformed message")),
                 );
                 return;
             }
@@ -5,7 +5,7 @@ use ckb_chain_spec::consensus::{build_genesis_epoch_ext, ConsensusBuilder};
 use ckb_dao::DaoCalculator;
 use ckb_dao_utils::genesis_dao_data;
 use ckb_network::{
-    async_trait, bytes::Bytes as P2pBytes, Behaviour, CKBProtocolContext, Error, Flags,
+    async_trait, bytes::Bytes as P2pBytes, BanReason, Behaviour, CKBProtocolContext, Error, Flags,
     NetworkController, NetworkService, NetworkState, Peer, PeerIndex, ProtocolId, SupportProtocols,
     TargetSession,
 };
@@ -451,7 +451,7 @@ impl CKBProtocolContext for MockProtocolContext {
     fn report_peer(&self, _peer_index: PeerIndex, _behaviour: Behaviour) {
         unimplemented!();
     }
-    fn ban_peer(&self, _peer_index: PeerIndex, _duration: Duration, _reason: String) {
+    fn ban_peer(&self, _peer_index: PeerIndex, _duration: Duration, _reason: BanReason) {
         unimplemented!();
     }
     fn protocol_id(&self) -> ProtocolId {
@@ -1,7 +1,7 @@
 use crate::relayer::Relayer;
 use crate::Status;
 use ckb_logger::error;
-use ckb_network::{CKBProtocolContext, PeerIndex};
+use ckb_network::{BanReason, CKBProtocolContext, PeerIndex};
 use ckb_types::{
     core::{Cycle, TransactionView},
     packed,
@@ -73,7 +73,9 @@ impl<'a> TransactionsProcess<'a> {
             self.nc.ban_peer(
                 self.peer,
                 DEFAULT_BAN_TIME,
-                String::from("relay declared cycles greater than max_block_cycles"),
+                BanReason::InvalidTransaction(String::from(
+                    "relay declared cycles greater than max_block_cycles",
+                )),
             );
             return Status::ok();
         }
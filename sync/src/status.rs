@@ -1,4 +1,5 @@
 use ckb_constant::sync::{BAD_MESSAGE_BAN_TIME, SYNC_USELESS_BAN_TIME};
+use ckb_network::BanReason;
 use std::fmt::{self, Display, Formatter};
 use std::time::Duration;
 
@@ -171,6 +172,19 @@ impl Status {
         }
     }
 
+    /// The reason to ban session with, see `should_ban`
+    pub fn ban_reason(&self) -> BanReason {
+        let detail = self.to_string();
+        match self.code {
+            StatusCode::BlockIsInvalid
+            | StatusCode::CompactBlockHasInvalidHeader
+            | StatusCode::CompactBlockHasInvalidUncle
+            | StatusCode::CompactBlockHasUnmatchedTransactionRootWithReconstructedBlock
+            | StatusCode::HeadersIsInvalid => BanReason::InvalidBlock(detail),
+            _ => BanReason::ProtocolViolation(detail),
+        }
+    }
+
     /// Whether should output a warning log
     pub fn should_warn(&self) -> bool {
         self.code as u16 >= 500
@@ -35,7 +35,7 @@ use ckb_constant::sync::{
 use ckb_logger::{debug, error, info, trace, warn};
 use ckb_metrics::HistogramTimer;
 use ckb_network::{
-    async_trait, bytes::Bytes, tokio, CKBProtocolContext, CKBProtocolHandler, PeerIndex,
+    async_trait, bytes::Bytes, tokio, BanReason, CKBProtocolContext, CKBProtocolHandler, PeerIndex,
     ServiceControl, SupportProtocols,
 };
 use ckb_shared::types::HeaderIndexView;
@@ -839,10 +839,10 @@ impl CKBProtocolHandler for Synchronizer {
                         nc.ban_peer(
                             peer_index,
                             BAD_MESSAGE_BAN_TIME,
-                            String::from(
+                            BanReason::ProtocolViolation(String::from(
                                 "send us a malformed message: \
                                  too many fields in SendBlock",
-                            ),
+                            )),
                         );
                         return;
                     } else {
@@ -860,10 +860,10 @@ impl CKBProtocolHandler for Synchronizer {
                             nc.ban_peer(
                                 peer_index,
                                 BAD_MESSAGE_BAN_TIME,
-                                String::from(
+                                BanReason::ProtocolViolation(String::from(
                                     "send us a malformed message: \
                                      too many fields",
-                                ),
+                                )),
                             );
                             return;
                         }
@@ -875,7 +875,7 @@ impl CKBProtocolHandler for Synchronizer {
                 nc.ban_peer(
                     peer_index,
                     BAD_MESSAGE_BAN_TIME,
-                    String::from("send us a malformed message"),
+                    BanReason::ProtocolViolation(String::from("send us a malformed message")),
                 );
                 return;
             }
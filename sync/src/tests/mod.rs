@@ -1,8 +1,8 @@
 use ckb_channel::{bounded, Receiver, Select, Sender};
 use ckb_network::async_trait;
 use ckb_network::{
-    bytes::Bytes, Behaviour, CKBProtocolContext, CKBProtocolHandler, Peer, PeerIndex, ProtocolId,
-    TargetSession,
+    bytes::Bytes, BanReason, Behaviour, CKBProtocolContext, CKBProtocolHandler, Peer, PeerIndex,
+    ProtocolId, TargetSession,
 };
 use ckb_util::RwLock;
 use futures::{executor::block_on, future::Future};
@@ -15,6 +15,7 @@ use std::time::Duration;
 mod block_status;
 mod inflight_blocks;
 mod net_time_checker;
+mod status;
 mod sync_shared;
 
 mod synchronizer;
@@ -389,7 +390,7 @@ impl CKBProtocolContext for TestNetworkContext {
             .collect::<Vec<_>>()
     }
     fn report_peer(&self, _peer_index: PeerIndex, _behaviour: Behaviour) {}
-    fn ban_peer(&self, _peer_index: PeerIndex, _duration: Duration, _reason: String) {}
+    fn ban_peer(&self, _peer_index: PeerIndex, _duration: Duration, _reason: BanReason) {}
     // Other methods
     fn protocol_id(&self) -> ProtocolId {
         self.protocol
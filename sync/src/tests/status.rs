@@ -0,0 +1,23 @@
+use ckb_network::BanReason;
+
+use crate::StatusCode;
+
+#[test]
+fn test_ban_reason() {
+    assert_eq!(
+        StatusCode::BlockIsInvalid.with_context("bad").ban_reason(),
+        BanReason::InvalidBlock("BlockIsInvalid(401): bad".to_owned())
+    );
+    assert_eq!(
+        StatusCode::HeadersIsInvalid
+            .with_context("bad")
+            .ban_reason(),
+        BanReason::InvalidBlock("HeadersIsInvalid(415): bad".to_owned())
+    );
+    assert_eq!(
+        StatusCode::ProtocolMessageIsMalformed
+            .with_context("bad")
+            .ban_reason(),
+        BanReason::ProtocolViolation("ProtocolMessageIsMalformed(400): bad".to_owned())
+    );
+}
@@ -4,8 +4,8 @@ use ckb_constant::sync::{CHAIN_SYNC_TIMEOUT, EVICTION_HEADERS_RESPONSE_TIME, MAX
 use ckb_dao::DaoCalculator;
 use ckb_error::InternalErrorKind;
 use ckb_network::{
-    async_trait, bytes::Bytes, Behaviour, CKBProtocolContext, Peer, PeerId, PeerIndex, ProtocolId,
-    SessionType, TargetSession,
+    async_trait, bytes::Bytes, BanReason, Behaviour, CKBProtocolContext, Peer, PeerId, PeerIndex,
+    ProtocolId, SessionType, TargetSession,
 };
 use ckb_reward_calculator::RewardCalculator;
 use ckb_shared::types::HeaderIndex;
@@ -570,7 +570,7 @@ impl CKBProtocolContext for DummyNetworkContext {
         unimplemented!();
     }
     fn report_peer(&self, _peer_index: PeerIndex, _behaviour: Behaviour) {}
-    fn ban_peer(&self, _peer_index: PeerIndex, _duration: Duration, _reason: String) {}
+    fn ban_peer(&self, _peer_index: PeerIndex, _duration: Duration, _reason: BanReason) {}
     // Other methods
     fn protocol_id(&self) -> ProtocolId {
         ProtocolId::new(1)
@@ -2000,7 +2000,7 @@ pub(crate) fn post_sync_process(
             "Receive {} from {}. Ban {:?} for {}",
             item_name, peer, ban_time, status
         );
-        nc.ban_peer(peer, ban_time, status.to_string());
+        nc.ban_peer(peer, ban_time, status.ban_reason());
     } else if status.should_warn() {
         warn!("Receive {} from {}, {}", item_name, peer, status);
     } else if !status.is_ok() {
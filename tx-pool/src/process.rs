@@ -15,7 +15,7 @@ use ckb_error::{AnyError, InternalErrorKind};
 use ckb_jsonrpc_types::BlockTemplate;
 use ckb_logger::Level::Trace;
 use ckb_logger::{debug, error, info, log_enabled_target, trace_target};
-use ckb_network::PeerIndex;
+use ckb_network::{BanReason, PeerIndex};
 use ckb_script::ChunkCommand;
 use ckb_snapshot::Snapshot;
 use ckb_types::core::error::OutPointError;
@@ -687,7 +687,11 @@ impl TxPoolService {
                 )
             },
         );
-        self.network.ban_peer(peer, DEFAULT_BAN_TIME, reason);
+        self.network.ban_peer(
+            peer,
+            DEFAULT_BAN_TIME,
+            BanReason::InvalidTransaction(reason),
+        );
     }
 
     pub(crate) async fn _process_tx(
@@ -38,6 +38,9 @@ pub struct IndexerConfig {
     /// limit of indexer reqeust
     #[serde(default)]
     pub request_limit: Option<usize>,
+    /// The number of blocks fetched concurrently ahead of the indexer, default 1
+    #[serde(default = "default_block_fetch_parallelism")]
+    pub block_fetch_parallelism: usize,
     /// Rich indexer config options
     #[serde(default)]
     pub rich_indexer: RichIndexerConfig,
@@ -47,6 +50,10 @@ const fn default_poll_interval() -> u64 {
     2
 }
 
+const fn default_block_fetch_parallelism() -> usize {
+    1
+}
+
 impl Default for IndexerConfig {
     fn default() -> Self {
         IndexerConfig {
@@ -60,6 +67,7 @@ impl Default for IndexerConfig {
             db_keep_log_file_num: None,
             init_tip_hash: None,
             request_limit: None,
+            block_fetch_parallelism: default_block_fetch_parallelism(),
             rich_indexer: RichIndexerConfig::default(),
         }
     }
@@ -116,6 +124,9 @@ pub struct IndexerSyncConfig {
     /// Maximal db info log files to be kept.
     #[serde(default)]
     pub db_keep_log_file_num: Option<NonZeroUsize>,
+    /// The number of blocks fetched concurrently ahead of the indexer
+    #[serde(default = "default_block_fetch_parallelism")]
+    pub block_fetch_parallelism: usize,
 }
 
 impl From<&IndexerConfig> for IndexerSyncConfig {
@@ -125,6 +136,7 @@ impl From<&IndexerConfig> for IndexerSyncConfig {
             poll_interval: config.poll_interval,
             index_tx_pool: config.index_tx_pool,
             db_keep_log_file_num: config.db_keep_log_file_num,
+            block_fetch_parallelism: config.block_fetch_parallelism,
         }
     }
 }
@@ -23,7 +23,10 @@ pub use network::{
 };
 pub use network_alert::Config as NetworkAlertConfig;
 pub use notify::Config as NotifyConfig;
-pub use rich_indexer::{DBDriver, RichIndexerConfig};
+pub use rich_indexer::{
+    CustomCellField, CustomCellFieldKind, CustomCellSchema, DBDriver, OversizedTransactions,
+    RichIndexerConfig, ZeroCapacityCells,
+};
 pub use rpc::{Config as RpcConfig, Module as RpcModule};
 pub use store::Config as StoreConfig;
 pub use tx_pool::{BlockAssemblerConfig, TxPoolConfig};
@@ -1,8 +1,10 @@
+use ckb_types::H256;
 use serde::{Deserialize, Serialize};
 use std::{default::Default, path::PathBuf};
 
 const PGSQL: &str = "postgres://";
 const SQLITE: &str = "sqlite://";
+const MYSQL: &str = "mysql://";
 
 /// Rich indexer database type.
 #[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Copy)]
@@ -13,6 +15,10 @@ pub enum DBDriver {
     Sqlite,
     /// Postgres config options.
     Postgres,
+    /// MySQL config options, only the bulk insert helpers of the rich-indexer support it so
+    /// far.
+    #[serde(rename = "mysql")]
+    MySQL,
 }
 
 impl ToString for DBDriver {
@@ -20,10 +26,88 @@ impl ToString for DBDriver {
         match self {
             DBDriver::Postgres => PGSQL.to_string(),
             DBDriver::Sqlite => SQLITE.to_string(),
+            DBDriver::MySQL => MYSQL.to_string(),
         }
     }
 }
 
+/// How the rich indexer treats outputs whose capacity is zero.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ZeroCapacityCells {
+    /// Index zero-capacity outputs like any other cell.
+    #[default]
+    Store,
+    /// Log and skip zero-capacity outputs.
+    Skip,
+    /// Fail to index a block which contains zero-capacity outputs.
+    Reject,
+}
+
+/// How the rich indexer treats transactions with more inputs or outputs than the
+/// configured caps.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizedTransactions {
+    /// Log and index oversized transactions like any other transaction.
+    #[default]
+    Warn,
+    /// Fail to index a block which contains an oversized transaction.
+    Reject,
+}
+
+/// The kind of a fixed-size field in custom cell data.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomCellFieldKind {
+    /// Little-endian `u8`.
+    Uint8,
+    /// Little-endian `u16`.
+    Uint16,
+    /// Little-endian `u32`.
+    Uint32,
+    /// Little-endian `u64`.
+    Uint64,
+    /// Little-endian `u128`.
+    Uint128,
+    /// 32 raw bytes.
+    Byte32,
+}
+
+impl CustomCellFieldKind {
+    /// The size of the field in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            CustomCellFieldKind::Uint8 => 1,
+            CustomCellFieldKind::Uint16 => 2,
+            CustomCellFieldKind::Uint32 => 4,
+            CustomCellFieldKind::Uint64 => 8,
+            CustomCellFieldKind::Uint128 => 16,
+            CustomCellFieldKind::Byte32 => 32,
+        }
+    }
+}
+
+/// A fixed-size field of custom cell data.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomCellField {
+    /// The field name.
+    pub name: String,
+    /// The field kind.
+    pub kind: CustomCellFieldKind,
+    /// The byte offset of the field in the cell data.
+    pub offset: usize,
+}
+
+/// The data schema of cells whose type script has the given code hash.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomCellSchema {
+    /// The type script code hash.
+    pub code_hash: H256,
+    /// The fields extracted from the cell data.
+    pub fields: Vec<CustomCellField>,
+}
+
 /// Rich indexer config options.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RichIndexerConfig {
@@ -49,6 +133,54 @@ pub struct RichIndexerConfig {
     /// The database password.
     #[serde(default = "default_db_password")]
     pub db_password: String,
+    /// How to treat outputs whose capacity is zero, default `store`.
+    #[serde(default)]
+    pub zero_capacity_cells: ZeroCapacityCells,
+    /// Data schemas of custom cells whose fields are extracted into the `custom_cell_field` table.
+    #[serde(default)]
+    pub custom_cell_schemas: Vec<CustomCellSchema>,
+    /// Cell data larger than this number of bytes is stored compressed, disabled by default.
+    ///
+    /// The data filters of queries decompress the candidate cells stored compressed, which
+    /// makes filtering large cells by data slower.
+    #[serde(default)]
+    pub data_compression_threshold: Option<usize>,
+    /// Only keep the live cell set, spent cells and the transactions left without cells are
+    /// deleted once they are deep enough not to be rolled back, disabled by default.
+    #[serde(default)]
+    pub pruned_mode: bool,
+    /// Outputs whose lock or type script has one of these code hashes are not indexed.
+    #[serde(default)]
+    pub ignored_code_hashes: Vec<H256>,
+    /// Length of the indexed prefix of cell data kept for prefix and exact data filters,
+    /// disabled by default.
+    #[serde(default)]
+    pub data_prefix_len: Option<usize>,
+    /// Create the database, tables and run migrations and their backfills on startup, enabled
+    /// by default. When disabled, startup fails unless the schema is already up to date, and
+    /// pending backfills are only reported.
+    #[serde(default = "default_auto_migrate")]
+    pub auto_migrate: bool,
+    /// Expected number of indexed outputs, which enables an in-memory bloom filter of their
+    /// out points sized for it, so that the inputs spending unknown cells skip the database
+    /// lookups. Disabled by default.
+    #[serde(default)]
+    pub out_point_filter_capacity: Option<usize>,
+    /// Maximum number of inputs of a transaction, above any transaction fitting in a block
+    /// by default.
+    #[serde(default = "default_max_tx_cells")]
+    pub max_tx_inputs: usize,
+    /// Maximum number of outputs of a transaction, above any transaction fitting in a block
+    /// by default.
+    #[serde(default = "default_max_tx_cells")]
+    pub max_tx_outputs: usize,
+    /// How to treat transactions exceeding `max_tx_inputs` or `max_tx_outputs`, default `warn`.
+    #[serde(default)]
+    pub oversized_transactions: OversizedTransactions,
+    /// Store the witnesses of transactions, enabled by default. The number of witnesses and
+    /// their total size are recorded either way.
+    #[serde(default = "default_index_witnesses")]
+    pub index_witnesses: bool,
 }
 
 impl Default for RichIndexerConfig {
@@ -61,6 +193,18 @@ impl Default for RichIndexerConfig {
             db_port: default_db_port(),
             db_user: default_db_user(),
             db_password: default_db_password(),
+            zero_capacity_cells: ZeroCapacityCells::default(),
+            custom_cell_schemas: Vec::new(),
+            data_compression_threshold: None,
+            pruned_mode: false,
+            ignored_code_hashes: Vec::new(),
+            data_prefix_len: None,
+            auto_migrate: default_auto_migrate(),
+            out_point_filter_capacity: None,
+            max_tx_inputs: default_max_tx_cells(),
+            max_tx_outputs: default_max_tx_cells(),
+            oversized_transactions: OversizedTransactions::default(),
+            index_witnesses: default_index_witnesses(),
         }
     }
 }
@@ -73,6 +217,20 @@ fn default_db_host() -> String {
     "127.0.0.1".to_string()
 }
 
+fn default_auto_migrate() -> bool {
+    true
+}
+
+fn default_index_witnesses() -> bool {
+    true
+}
+
+/// A block is at most 597,000 bytes, which leaves room for about 13,500 inputs or
+/// fewer outputs in a single transaction.
+fn default_max_tx_cells() -> usize {
+    20_000
+}
+
 fn default_db_port() -> u16 {
     8532
 }
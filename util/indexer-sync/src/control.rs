@@ -0,0 +1,142 @@
+//! Pausing and resuming of the sync loop.
+
+use ckb_stop_handler::has_received_stop_signal;
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// How often a paused sync loop checks for the exit signal.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The state of the sync loop as seen by a [`SyncControl`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncState {
+    /// Blocks are being indexed.
+    Running,
+    /// A pause is requested, the sync loop stops once the block being written is committed.
+    Pausing,
+    /// The sync loop holds before the next block until it is resumed.
+    Paused,
+}
+
+#[derive(Default)]
+struct Inner {
+    pause_requested: bool,
+    parked: bool,
+}
+
+/// Control handle of the sync loop, for pausing the writer during DB maintenance.
+///
+/// A pause takes effect between two blocks, the block being appended or rolled back is always
+/// committed first. The handle can be cloned and shared with other threads.
+#[derive(Clone, Default)]
+pub struct SyncControl {
+    inner: Arc<(Mutex<Inner>, Condvar)>,
+}
+
+impl SyncControl {
+    /// Requests the sync loop to stop after the current block.
+    pub fn pause(&self) {
+        let (lock, _) = &*self.inner;
+        lock.lock().expect("sync control lock").pause_requested = true;
+    }
+
+    /// Lets a paused sync loop continue.
+    pub fn resume(&self) {
+        let (lock, cvar) = &*self.inner;
+        lock.lock().expect("sync control lock").pause_requested = false;
+        cvar.notify_all();
+    }
+
+    /// Returns the state of the sync loop.
+    pub fn state(&self) -> SyncState {
+        let (lock, _) = &*self.inner;
+        let inner = lock.lock().expect("sync control lock");
+        match (inner.pause_requested, inner.parked) {
+            (false, _) => SyncState::Running,
+            (true, false) => SyncState::Pausing,
+            (true, true) => SyncState::Paused,
+        }
+    }
+
+    /// Blocks the sync loop while a pause is requested.
+    ///
+    /// Returns `false` if the exit signal is received while waiting.
+    pub(crate) fn wait_while_paused(&self) -> bool {
+        let (lock, cvar) = &*self.inner;
+        let mut inner = lock.lock().expect("sync control lock");
+        while inner.pause_requested {
+            if has_received_stop_signal() {
+                inner.parked = false;
+                return false;
+            }
+            inner.parked = true;
+            inner = cvar
+                .wait_timeout(inner, STOP_CHECK_INTERVAL)
+                .expect("sync control lock")
+                .0;
+        }
+        inner.parked = false;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::thread;
+    use std::time::Instant;
+
+    fn wait_for_state(control: &SyncControl, state: SyncState) {
+        let started_at = Instant::now();
+        while control.state() != state {
+            assert!(
+                started_at.elapsed() < Duration::from_secs(5),
+                "timeout waiting for {state:?}"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn pause_and_resume_sync() {
+        const BLOCKS: u64 = 200;
+
+        let control = SyncControl::default();
+        let written = Arc::new(AtomicU64::new(0));
+        let sync_loop = {
+            let control = control.clone();
+            let written = Arc::clone(&written);
+            thread::spawn(move || {
+                for _ in 0..BLOCKS {
+                    if !control.wait_while_paused() {
+                        break;
+                    }
+                    // the block write
+                    thread::sleep(Duration::from_millis(1));
+                    written.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+
+        assert_eq!(SyncState::Running, control.state());
+        while written.load(Ordering::SeqCst) == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        control.pause();
+        wait_for_state(&control, SyncState::Paused);
+
+        let written_at_pause = written.load(Ordering::SeqCst);
+        assert!(written_at_pause < BLOCKS);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(written_at_pause, written.load(Ordering::SeqCst));
+        assert_eq!(SyncState::Paused, control.state());
+
+        control.resume();
+        assert_eq!(SyncState::Running, control.state());
+        sync_loop.join().unwrap();
+        assert_eq!(BLOCKS, written.load(Ordering::SeqCst));
+    }
+}
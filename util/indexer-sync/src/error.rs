@@ -10,6 +10,10 @@ pub enum Error {
     /// Invalid params error
     #[error("Invalid params {0}")]
     Params(String),
+    /// The connection to the underlying DB was lost, the operation can be retried once it
+    /// is reestablished
+    #[error("Db connection error {0}")]
+    Connection(String),
 }
 
 impl Error {
@@ -20,6 +24,11 @@ impl Error {
     {
         Error::Params(s.into())
     }
+
+    /// Whether the error is caused by a lost connection to the underlying DB.
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(self, Error::Connection(_))
+    }
 }
 
 impl From<rocksdb::Error> for Error {
@@ -1,15 +1,22 @@
 //! The built-in synchronization service in CKB can provide block synchronization services for indexers.
 
+pub(crate) mod control;
 pub(crate) mod custom_filters;
 pub(crate) mod error;
 pub(crate) mod pool;
+pub(crate) mod prefetch;
+pub(crate) mod reconnect;
 pub(crate) mod store;
 
+pub use crate::control::{SyncControl, SyncState};
 pub use crate::custom_filters::CustomFilters;
 pub use crate::error::Error;
 pub use crate::pool::{Pool, PoolService};
 pub use crate::store::SecondaryDB;
 
+use crate::prefetch::BlockPrefetcher;
+use crate::reconnect::{retry_on_connection_lost, Backoff};
+
 use ckb_app_config::{DBConfig, IndexerSyncConfig};
 use ckb_async_runtime::{
     tokio::{self, time},
@@ -52,6 +59,9 @@ pub trait IndexerSync {
     fn get_identity(&self) -> &str;
     /// Set init tip
     fn set_init_tip(&self, init_tip_number: u64, init_tip_hash: &H256);
+    /// Reports how many blocks the indexer is behind the node tip, after each block appended
+    /// or rolled back by the sync loop. Does nothing by default.
+    fn report_sync_lag(&self, _lag: BlockNumber) {}
 }
 
 /// Construct new secondary db instance
@@ -80,8 +90,10 @@ pub struct IndexerSyncService {
     secondary_db: SecondaryDB,
     pool_service: PoolService,
     poll_interval: Duration,
+    block_fetch_parallelism: usize,
     async_handle: Handle,
     init_tip_hash: Option<H256>,
+    control: SyncControl,
 }
 
 impl IndexerSyncService {
@@ -97,8 +109,10 @@ impl IndexerSyncService {
             secondary_db,
             pool_service,
             poll_interval: Duration::from_secs(config.poll_interval),
+            block_fetch_parallelism: config.block_fetch_parallelism,
             async_handle,
             init_tip_hash,
+            control: SyncControl::default(),
         }
     }
 
@@ -144,15 +158,21 @@ impl IndexerSyncService {
         if let Err(e) = self.secondary_db.try_catch_up_with_primary() {
             error!("secondary_db try_catch_up_with_primary error {}", e);
         }
+        let mut prefetcher = None;
         loop {
             if has_received_stop_signal() {
                 info!("try_loop_sync received exit signal, exit now");
                 break;
             }
+            if !self.control.wait_while_paused() {
+                info!("try_loop_sync received exit signal while paused, exit now");
+                break;
+            }
 
-            match indexer.tip() {
+            let identity = indexer.get_identity();
+            match retry_on_connection_lost(identity, Backoff::default(), || indexer.tip()) {
                 Ok(Some((tip_number, tip_hash))) => {
-                    match self.get_block_by_number(tip_number + 1) {
+                    match self.next_block(tip_number + 1, &mut prefetcher) {
                         Some(block) => {
                             if block.parent_hash() == tip_hash {
                                 info!(
@@ -161,8 +181,14 @@ impl IndexerSyncService {
                                     block.number(),
                                     block.hash()
                                 );
-                                if let Err(e) = indexer.append(&block) {
-                                    error!("Failed to append block: {}. Will attempt to retry.", e);
+                                match retry_on_connection_lost(identity, Backoff::default(), || {
+                                    indexer.append(&block)
+                                }) {
+                                    Ok(()) => self.report_sync_lag(&indexer, block.number()),
+                                    Err(e) => error!(
+                                        "Failed to append block: {}. Will attempt to retry.",
+                                        e
+                                    ),
                                 }
                             } else {
                                 info!(
@@ -171,7 +197,20 @@ impl IndexerSyncService {
                                     tip_number,
                                     tip_hash
                                 );
-                                indexer.rollback().expect("rollback block should be OK");
+                                match retry_on_connection_lost(identity, Backoff::default(), || {
+                                    indexer.rollback()
+                                }) {
+                                    Err(e) if e.is_connection_lost() => {
+                                        error!("Failed to rollback block: {}", e);
+                                    }
+                                    result => {
+                                        result.expect("rollback block should be OK");
+                                        self.report_sync_lag(
+                                            &indexer,
+                                            tip_number.saturating_sub(1),
+                                        );
+                                    }
+                                }
                             }
                         }
                         None => {
@@ -181,8 +220,13 @@ impl IndexerSyncService {
                 }
                 Ok(None) => match self.get_block_by_number(0) {
                     Some(block) => {
-                        if let Err(e) = indexer.append(&block) {
-                            error!("Failed to append block: {}. Will attempt to retry.", e);
+                        match retry_on_connection_lost(identity, Backoff::default(), || {
+                            indexer.append(&block)
+                        }) {
+                            Ok(()) => self.report_sync_lag(&indexer, block.number()),
+                            Err(e) => {
+                                error!("Failed to append block: {}. Will attempt to retry.", e)
+                            }
                         }
                     }
                     None => {
@@ -197,6 +241,12 @@ impl IndexerSyncService {
         }
     }
 
+    fn report_sync_lag<I: IndexerSync>(&self, indexer: &I, indexer_tip_number: BlockNumber) {
+        if let Some(header) = self.secondary_db.get_tip_header() {
+            indexer.report_sync_lag(header.number().saturating_sub(indexer_tip_number));
+        }
+    }
+
     /// Processes that handle block cell and expect to be spawned to run in tokio runtime
     pub fn spawn_poll<I>(
         &self,
@@ -259,6 +309,11 @@ impl IndexerSyncService {
         });
     }
 
+    /// Returns the handle for pausing and resuming the sync loop
+    pub fn control(&self) -> SyncControl {
+        self.control.clone()
+    }
+
     /// Get index data based on transaction pool synchronization
     pub fn pool(&self) -> Option<Arc<RwLock<Pool>>> {
         self.pool_service.pool()
@@ -294,6 +349,28 @@ impl IndexerSyncService {
             .index_tx_pool(notify_controller, check_index_tx_pool_ready);
     }
 
+    /// Gets the next block to index, the blocks are read ahead by the prefetcher when the
+    /// fetch parallelism is above 1. The prefetcher restarts from the requested number after
+    /// a rollback or a failed append.
+    fn next_block(
+        &self,
+        block_number: u64,
+        prefetcher: &mut Option<BlockPrefetcher>,
+    ) -> Option<core::BlockView> {
+        if self.block_fetch_parallelism <= 1 {
+            return self.get_block_by_number(block_number);
+        }
+        if prefetcher.as_ref().map(BlockPrefetcher::next_number) != Some(block_number) {
+            let service = self.clone();
+            *prefetcher = Some(BlockPrefetcher::new(
+                block_number,
+                self.block_fetch_parallelism,
+                move |number| service.get_block_by_number(number),
+            ));
+        }
+        prefetcher.as_mut().and_then(BlockPrefetcher::next_block)
+    }
+
     fn get_block_by_number(&self, block_number: u64) -> Option<core::BlockView> {
         let block_hash = self.secondary_db.get_block_hash(block_number)?;
         self.secondary_db.get_block(&block_hash)
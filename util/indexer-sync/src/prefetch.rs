@@ -0,0 +1,162 @@
+//! Read-ahead of the blocks to be indexed.
+
+use ckb_types::core::{BlockNumber, BlockView};
+
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// The number of blocks each fetch worker may run ahead of the indexer.
+const PREFETCH_QUEUE_SIZE: usize = 4;
+
+/// Fetches consecutive blocks ahead of the indexer with a number of worker threads, so that
+/// fetching the next blocks overlaps with writing the current one.
+///
+/// Worker `i` fetches the blocks `start + i`, `start + i + n`, ... into its own bounded queue,
+/// and the queues are consumed round-robin so that the blocks are returned in order.
+pub(crate) struct BlockPrefetcher {
+    start: BlockNumber,
+    next_number: BlockNumber,
+    receivers: Vec<Receiver<Option<BlockView>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl BlockPrefetcher {
+    pub(crate) fn new<F>(start: BlockNumber, parallelism: usize, fetch: F) -> Self
+    where
+        F: Fn(BlockNumber) -> Option<BlockView> + Send + Sync + 'static,
+    {
+        let parallelism = parallelism.max(1);
+        let fetch = Arc::new(fetch);
+        let (receivers, workers) = (0..parallelism)
+            .map(|i| {
+                let (sender, receiver) = sync_channel(PREFETCH_QUEUE_SIZE);
+                let fetch = Arc::clone(&fetch);
+                let worker = thread::Builder::new()
+                    .name(format!("IndexerPrefetch-{i}"))
+                    .spawn(move || {
+                        let mut number = start + i as u64;
+                        loop {
+                            let block = fetch(number);
+                            let end = block.is_none();
+                            // the prefetcher is dropped or the end of the chain is reached
+                            if sender.send(block).is_err() || end {
+                                break;
+                            }
+                            number += parallelism as u64;
+                        }
+                    })
+                    .expect("spawn block prefetch worker");
+                (receiver, worker)
+            })
+            .unzip();
+        BlockPrefetcher {
+            start,
+            next_number: start,
+            receivers,
+            workers,
+        }
+    }
+
+    /// The number of the block returned by the next call of `next_block`.
+    pub(crate) fn next_number(&self) -> BlockNumber {
+        self.next_number
+    }
+
+    /// Returns the next block in order, or `None` once a block is missing.
+    pub(crate) fn next_block(&mut self) -> Option<BlockView> {
+        let index = ((self.next_number - self.start) % self.receivers.len() as u64) as usize;
+        let block = self.receivers[index].recv().ok().flatten()?;
+        self.next_number += 1;
+        Some(block)
+    }
+}
+
+impl Drop for BlockPrefetcher {
+    fn drop(&mut self) {
+        // closing the queues stops the workers blocked on a full queue
+        self.receivers.clear();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ckb_types::{
+        core::{EpochNumberWithFraction, HeaderBuilder},
+        prelude::*,
+    };
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    fn block(number: BlockNumber) -> BlockView {
+        BlockView::new_advanced_builder()
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn prefetch_overlaps_with_writes() {
+        const BLOCKS: u64 = 16;
+        const FETCH_TIME: Duration = Duration::from_millis(20);
+        const WRITE_TIME: Duration = Duration::from_millis(20);
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let fetch = {
+            let in_flight = Arc::clone(&in_flight);
+            let max_in_flight = Arc::clone(&max_in_flight);
+            move |number: BlockNumber| {
+                if number >= BLOCKS {
+                    return None;
+                }
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                thread::sleep(FETCH_TIME);
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Some(block(number))
+            }
+        };
+
+        let started_at = Instant::now();
+        let mut prefetcher = BlockPrefetcher::new(0, 4, fetch);
+        let mut numbers = Vec::new();
+        while let Some(block) = prefetcher.next_block() {
+            // the single writer
+            thread::sleep(WRITE_TIME);
+            numbers.push(block.number());
+        }
+        let elapsed = started_at.elapsed();
+
+        assert_eq!((0..BLOCKS).collect::<Vec<_>>(), numbers);
+        assert_eq!(BLOCKS, prefetcher.next_number());
+        // fetches overlap each other and the writes, instead of alternating with the writes
+        assert!(max_in_flight.load(Ordering::SeqCst) > 1);
+        assert!(elapsed < (FETCH_TIME + WRITE_TIME) * BLOCKS as u32);
+    }
+
+    #[test]
+    fn drop_prefetcher_stops_workers() {
+        let mut prefetcher = BlockPrefetcher::new(10, 2, |number| Some(block(number)));
+        assert_eq!(
+            Some(10),
+            prefetcher.next_block().map(|block| block.number())
+        );
+        assert_eq!(
+            Some(11),
+            prefetcher.next_block().map(|block| block.number())
+        );
+        // the workers fetching an endless chain are blocked on their full queues
+        drop(prefetcher);
+    }
+}
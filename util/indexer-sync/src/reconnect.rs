@@ -0,0 +1,127 @@
+//! Retrying of indexer operations interrupted by a lost DB connection.
+
+use crate::Error;
+
+use ckb_logger::{info, warn};
+use ckb_stop_handler::has_received_stop_signal;
+
+use std::thread::sleep;
+use std::time::Duration;
+
+/// The delay before the first retry after the DB connection was lost.
+pub(crate) const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+/// The upper bound of the delay between retries.
+pub(crate) const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Exponential backoff between reconnection attempts.
+pub(crate) struct Backoff {
+    next_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Backoff {
+    pub(crate) fn new(initial_delay: Duration, max_delay: Duration) -> Self {
+        Backoff {
+            next_delay: initial_delay,
+            max_delay,
+        }
+    }
+
+    /// Returns the delay to wait before the next attempt, doubling it for the following one.
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let delay = self.next_delay;
+        self.next_delay = (delay * 2).min(self.max_delay);
+        delay
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::new(INITIAL_RECONNECT_DELAY, MAX_RECONNECT_DELAY)
+    }
+}
+
+/// Runs `op` until it succeeds or fails with an error other than a lost connection.
+///
+/// Each attempt is expected to start from a clean DB transaction, so a block whose indexing
+/// was interrupted is indexed again from scratch once the DB is reachable. The last error is
+/// returned if the stop signal is received while waiting for the DB.
+pub(crate) fn retry_on_connection_lost<T, F>(
+    identity: &str,
+    mut backoff: Backoff,
+    mut op: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+{
+    let mut attempts = 0;
+    loop {
+        match op() {
+            Err(e) if e.is_connection_lost() && !has_received_stop_signal() => {
+                let delay = backoff.next_delay();
+                warn!(
+                    "{} lost the DB connection: {}, retrying in {:?}",
+                    identity, e, delay
+                );
+                attempts += 1;
+                sleep(delay);
+            }
+            result => {
+                if result.is_ok() && attempts > 0 {
+                    info!(
+                        "{} resumed after {} reconnection attempts",
+                        identity, attempts
+                    );
+                }
+                return result;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    fn quick_backoff() -> Backoff {
+        Backoff::new(Duration::from_millis(1), Duration::from_millis(4))
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let mut backoff = quick_backoff();
+        let delays = (0..4).map(|_| backoff.next_delay()).collect::<Vec<_>>();
+        assert_eq!(
+            vec![1, 2, 4, 4],
+            delays.iter().map(Duration::as_millis).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn resumes_after_connection_recovers() {
+        // the connection is dropped for the first two attempts, then comes back
+        let attempts = Cell::new(0);
+        let result = retry_on_connection_lost("test", quick_backoff(), || {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() <= 2 {
+                Err(Error::Connection("connection reset by peer".to_string()))
+            } else {
+                Ok(attempts.get())
+            }
+        });
+        assert_eq!(3, result.unwrap());
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let attempts = Cell::new(0);
+        let result: Result<(), _> = retry_on_connection_lost("test", quick_backoff(), || {
+            attempts.set(attempts.get() + 1);
+            Err(Error::DB("constraint violation".to_string()))
+        });
+        assert!(matches!(result, Err(Error::DB(_))));
+        assert_eq!(1, attempts.get());
+    }
+}
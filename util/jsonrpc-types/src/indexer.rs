@@ -12,6 +12,15 @@ pub struct IndexerTip {
     pub block_number: BlockNumber,
 }
 
+/// Indexer sync status
+#[derive(Serialize, JsonSchema)]
+pub struct IndexerSyncStatus {
+    /// indexed tip, `null` if no block has been indexed yet
+    pub indexer_tip: Option<IndexerTip>,
+    /// the number of blocks the indexer is behind the node tip
+    pub lag: BlockNumber,
+}
+
 /// Live cell
 #[derive(Serialize, JsonSchema)]
 pub struct IndexerCell {
@@ -170,6 +179,17 @@ pub enum IndexerOrder {
     Asc,
 }
 
+/// Cells order key Id | Creation
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexerCellsOrderBy {
+    /// Order by the indexed id of the cell
+    #[default]
+    Id,
+    /// Order by the creation of the cell: block number, transaction index and output index
+    Creation,
+}
+
 /// Cells capacity
 #[derive(Serialize, JsonSchema)]
 pub struct IndexerCellsCapacity {
@@ -252,6 +272,17 @@ pub struct IndexerTxWithCells {
     pub cells: Vec<(IndexerCellType, Uint32)>,
 }
 
+/// Transaction committed in a block
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexerTxInfo {
+    /// transaction hash
+    pub tx_hash: H256,
+    /// the number of the transaction committed in the block
+    pub block_number: BlockNumber,
+    /// the position index of the transaction committed in the block
+    pub tx_index: Uint32,
+}
+
 /// Cell type
 #[derive(Serialize, Clone, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
@@ -261,3 +292,89 @@ pub enum IndexerCellType {
     /// Output
     Output,
 }
+
+/// Cell event, the creation or the consumption of a cell in a block
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexerCellEvent {
+    /// reference to the created or consumed cell via transaction hash and output index
+    pub out_point: OutPoint,
+    /// the number of the block in which the event happened
+    pub block_number: BlockNumber,
+    /// the position index of the transaction committed in the block
+    pub tx_index: Uint32,
+    /// the hash of the transaction which created or consumed the cell
+    pub tx_hash: H256,
+    /// io type, `output` for a created cell and `input` for a consumed cell
+    pub io_type: IndexerCellType,
+    /// the position index of the cell in the transaction inputs or outputs
+    pub io_index: Uint32,
+}
+
+/// The first and the last block in which a lock script was active
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexerAddressActivity {
+    /// the number of the first block in which a cell was created under the lock
+    pub first_block: BlockNumber,
+    /// the number of the last block in which a cell under the lock was created or consumed
+    pub last_block: BlockNumber,
+}
+
+/// Spending status of a transaction output
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexerOutputSpendingStatus {
+    /// the index of the output in the transaction
+    pub output_index: Uint32,
+    /// whether the output has been consumed
+    pub spent: bool,
+    /// the hash of the transaction consuming the output, if it is indexed
+    pub spent_by_tx: Option<H256>,
+}
+
+/// Number of live cells of a lock per type script code hash
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexerAssetType {
+    /// the code hash of the type script, null for cells without a type script
+    pub type_code_hash: Option<H256>,
+    /// the number of live cells
+    pub cell_count: Uint64,
+}
+
+/// Position of a transaction in its block
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexerTxPosition {
+    /// the hash of the block containing the transaction
+    pub block_hash: H256,
+    /// the index of the transaction in the block
+    pub tx_index: Uint32,
+    /// the number of transactions in the block, null for blocks indexed before it was stored
+    pub tx_count: Option<Uint32>,
+}
+
+/// Indexed cell along with its spending status
+#[derive(Serialize, JsonSchema)]
+pub struct IndexerCellWithStatus {
+    /// the cell
+    pub cell: IndexerCell,
+    /// whether the cell has been consumed
+    pub spent: bool,
+    /// the hash of the transaction consuming the cell, if it is indexed
+    pub spent_by_tx: Option<H256>,
+}
+
+/// Light-client commitment parsed from a block extension
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexerBlockCommitment {
+    /// the chain root MMR hash in the first 32 bytes of the extension
+    pub chain_root: H256,
+    /// the remaining bytes of the extension
+    pub extra_data: JsonBytes,
+}
+
+/// Live cell count of a lock script code hash
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexerLockUsage {
+    /// lock script code hash
+    pub code_hash: H256,
+    /// the number of live cells locked by scripts with this code hash
+    pub live_cell_count: Uint64,
+}
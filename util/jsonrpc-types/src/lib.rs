@@ -54,9 +54,12 @@ pub use self::subscription::Topic;
 pub use self::uints::{Uint128, Uint32, Uint64};
 pub use ckb_types::core::RationalU256;
 pub use indexer::{
-    IndexerCell, IndexerCellType, IndexerCellsCapacity, IndexerOrder, IndexerPagination,
-    IndexerRange, IndexerScriptType, IndexerSearchKey, IndexerSearchKeyFilter, IndexerSearchMode,
-    IndexerTip, IndexerTx, IndexerTxWithCell, IndexerTxWithCells,
+    IndexerAddressActivity, IndexerAssetType, IndexerBlockCommitment, IndexerCell,
+    IndexerCellEvent, IndexerCellType, IndexerCellWithStatus, IndexerCellsCapacity,
+    IndexerCellsOrderBy, IndexerLockUsage, IndexerOrder, IndexerOutputSpendingStatus,
+    IndexerPagination, IndexerRange, IndexerScriptType, IndexerSearchKey, IndexerSearchKeyFilter,
+    IndexerSearchMode, IndexerSyncStatus, IndexerTip, IndexerTx, IndexerTxInfo, IndexerTxPosition,
+    IndexerTxWithCell, IndexerTxWithCells,
 };
 pub use primitive::{
     AsEpochNumberWithFraction, BlockNumber, Capacity, Cycle, EpochNumber, EpochNumberWithFraction,
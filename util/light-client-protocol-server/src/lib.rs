@@ -5,7 +5,9 @@
 use std::sync::Arc;
 
 use ckb_logger::{debug, error, info, trace, warn};
-use ckb_network::{async_trait, bytes::Bytes, CKBProtocolContext, CKBProtocolHandler, PeerIndex};
+use ckb_network::{
+    async_trait, bytes::Bytes, BanReason, CKBProtocolContext, CKBProtocolHandler, PeerIndex,
+};
 use ckb_shared::Shared;
 use ckb_store::ChainStore;
 use ckb_types::{core, packed, prelude::*};
@@ -70,7 +72,7 @@ impl CKBProtocolHandler for LightClientProtocol {
                 nc.ban_peer(
                     peer,
                     constant::BAD_MESSAGE_BAN_TIME,
-                    String::from("send us a malformed message"),
+                    BanReason::ProtocolViolation(String::from("send us a malformed message")),
                 );
                 return;
             }
@@ -83,7 +85,7 @@ impl CKBProtocolHandler for LightClientProtocol {
                 "process {} from {}; ban {:?} since result is {}",
                 item_name, peer, ban_time, status
             );
-            nc.ban_peer(peer, ban_time, status.to_string());
+            nc.ban_peer(peer, ban_time, status.ban_reason());
         } else if status.should_warn() {
             warn!("process {} from {}; result is {}", item_name, peer, status);
         } else if !status.is_ok() {
@@ -1,5 +1,7 @@
 use std::{fmt, time::Duration};
 
+use ckb_network::BanReason;
+
 use crate::constant;
 
 /// StatusCodes indicate whether a specific operation has been successfully completed.
@@ -101,6 +103,17 @@ impl Status {
         }
     }
 
+    /// Returns the reason to ban the session with, see `should_ban`.
+    pub fn ban_reason(&self) -> BanReason {
+        let detail = self.to_string();
+        match self.code {
+            StatusCode::InvalidLastBlock
+            | StatusCode::InvalidUnconfirmedBlock
+            | StatusCode::InvaildDifficultyBoundary => BanReason::InvalidBlock(detail),
+            _ => BanReason::ProtocolViolation(detail),
+        }
+    }
+
     /// Whether a warning log should be output.
     pub fn should_warn(&self) -> bool {
         let code = self.code as u16;
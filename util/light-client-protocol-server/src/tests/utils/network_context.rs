@@ -6,14 +6,14 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use ckb_network::{
-    async_trait, bytes::Bytes as P2pBytes, Behaviour, CKBProtocolContext, Error, Peer, PeerIndex,
-    ProtocolId, SupportProtocols, TargetSession,
+    async_trait, bytes::Bytes as P2pBytes, BanReason, Behaviour, CKBProtocolContext, Error, Peer,
+    PeerIndex, ProtocolId, SupportProtocols, TargetSession,
 };
 
 struct MockProtocolContext {
     protocol: SupportProtocols,
     sent_messages: RefCell<Vec<(ProtocolId, PeerIndex, P2pBytes)>>,
-    banned_peers: RefCell<Vec<(PeerIndex, Duration, String)>>,
+    banned_peers: RefCell<Vec<(PeerIndex, Duration, BanReason)>>,
     connected_peers: RefCell<HashSet<PeerIndex>>,
 }
 
@@ -47,11 +47,11 @@ impl MockNetworkContext {
         &self.inner.sent_messages
     }
 
-    pub(crate) fn banned_peers(&self) -> &RefCell<Vec<(PeerIndex, Duration, String)>> {
+    pub(crate) fn banned_peers(&self) -> &RefCell<Vec<(PeerIndex, Duration, BanReason)>> {
         &self.inner.banned_peers
     }
 
-    pub(crate) fn has_banned(&self, target: PeerIndex) -> Option<(Duration, String)> {
+    pub(crate) fn has_banned(&self, target: PeerIndex) -> Option<(Duration, BanReason)> {
         self.banned_peers()
             .borrow()
             .iter()
@@ -199,7 +199,7 @@ impl CKBProtocolContext for MockProtocolContext {
     fn report_peer(&self, _peer_index: PeerIndex, _behaviour: Behaviour) {
         unimplemented!();
     }
-    fn ban_peer(&self, peer_index: PeerIndex, duration: Duration, reason: String) {
+    fn ban_peer(&self, peer_index: PeerIndex, duration: Duration, reason: BanReason) {
         self.banned_peers
             .borrow_mut()
             .push((peer_index, duration, reason));
@@ -124,6 +124,8 @@ pub struct Metrics {
     pub ckb_network_ban_peer: IntCounter,
     pub ckb_inflight_blocks_count: IntGauge,
     pub ckb_inflight_timeout_count: IntCounter,
+    /// Gauge for the number of blocks the rich-indexer is behind the node tip
+    pub ckb_rich_indexer_sync_lag: IntGauge,
 }
 
 static METRICS: once_cell::sync::Lazy<Metrics> = once_cell::sync::Lazy::new(|| {
@@ -309,6 +311,11 @@ static METRICS: once_cell::sync::Lazy<Metrics> = once_cell::sync::Lazy::new(|| {
             "ckb_inflight_timeout_count",
             "The CKB inflight timeout count"
     ).unwrap(),
+    ckb_rich_indexer_sync_lag: register_int_gauge!(
+        "ckb_rich_indexer_sync_lag",
+        "The number of blocks the CKB rich-indexer is behind the node tip"
+    )
+            .unwrap(),
     }
 });
 
@@ -12,7 +12,8 @@ use crate::BAD_MESSAGE_BAN_TIME;
 use ckb_app_config::NetworkAlertConfig;
 use ckb_logger::{debug, info, trace};
 use ckb_network::{
-    async_trait, bytes::Bytes, CKBProtocolContext, CKBProtocolHandler, PeerIndex, TargetSession,
+    async_trait, bytes::Bytes, BanReason, CKBProtocolContext, CKBProtocolHandler, PeerIndex,
+    TargetSession,
 };
 use ckb_notify::NotifyController;
 use ckb_types::{packed, prelude::*};
@@ -126,7 +127,9 @@ impl CKBProtocolHandler for AlertRelayer {
                     nc.ban_peer(
                         peer_index,
                         BAD_MESSAGE_BAN_TIME,
-                        String::from("send us a malformed message: not utf-8 string"),
+                        BanReason::ProtocolViolation(String::from(
+                            "send us a malformed message: not utf-8 string",
+                        )),
                     );
                     return;
                 }
@@ -136,7 +139,7 @@ impl CKBProtocolHandler for AlertRelayer {
                 nc.ban_peer(
                     peer_index,
                     BAD_MESSAGE_BAN_TIME,
-                    String::from("send us a malformed message"),
+                    BanReason::ProtocolViolation(String::from("send us a malformed message")),
                 );
                 return;
             }
@@ -156,7 +159,9 @@ impl CKBProtocolHandler for AlertRelayer {
             nc.ban_peer(
                 peer_index,
                 BAD_MESSAGE_BAN_TIME,
-                String::from("send us an alert with invalid signatures"),
+                BanReason::ProtocolViolation(String::from(
+                    "send us an alert with invalid signatures",
+                )),
             );
             return;
         }
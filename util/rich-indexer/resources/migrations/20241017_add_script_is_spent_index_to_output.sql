@@ -0,0 +1,4 @@
+-- 20241017_add_script_is_spent_index_to_output.sql
+
+CREATE INDEX IF NOT EXISTS "idx_output_table_lock_script_id_is_spent" ON "output" ("lock_script_id", "is_spent");
+CREATE INDEX IF NOT EXISTS "idx_output_table_type_script_id_is_spent" ON "output" ("type_script_id", "is_spent");
@@ -0,0 +1,8 @@
+-- 20241018_add_custom_cell_field_table.sql
+
+CREATE TABLE IF NOT EXISTS custom_cell_field(
+    output_id BIGINT NOT NULL,
+    field_name TEXT NOT NULL,
+    field_value TEXT NOT NULL,
+    PRIMARY KEY(output_id, field_name)
+);
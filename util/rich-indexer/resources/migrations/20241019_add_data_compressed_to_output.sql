@@ -0,0 +1,4 @@
+-- 20241019_add_data_compressed_to_output.sql
+
+ALTER TABLE output
+ADD COLUMN data_compressed INTEGER DEFAULT 0;
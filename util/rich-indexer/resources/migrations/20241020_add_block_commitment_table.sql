@@ -0,0 +1,7 @@
+-- 20241020_add_block_commitment_table.sql
+
+CREATE TABLE IF NOT EXISTS block_commitment(
+    block_id BIGINT PRIMARY KEY,
+    chain_root BYTEA NOT NULL,
+    extra_data BYTEA NOT NULL
+);
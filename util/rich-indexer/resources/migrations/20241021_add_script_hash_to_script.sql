@@ -0,0 +1,6 @@
+-- 20241021_add_script_hash_to_script.sql
+
+ALTER TABLE script
+ADD COLUMN script_hash BYTEA;
+
+CREATE INDEX IF NOT EXISTS idx_script_script_hash ON script(script_hash);
@@ -0,0 +1,6 @@
+-- 20241022_add_data_prefix_to_output.sql
+
+ALTER TABLE output
+ADD COLUMN data_prefix BYTEA;
+
+CREATE INDEX IF NOT EXISTS idx_output_data_prefix ON output(data_prefix);
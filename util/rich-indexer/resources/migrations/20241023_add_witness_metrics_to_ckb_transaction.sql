@@ -0,0 +1,7 @@
+-- 20241023_add_witness_metrics_to_ckb_transaction.sql
+
+ALTER TABLE ckb_transaction
+ADD COLUMN witness_count INTEGER;
+
+ALTER TABLE ckb_transaction
+ADD COLUMN witnesses_total_bytes BIGINT;
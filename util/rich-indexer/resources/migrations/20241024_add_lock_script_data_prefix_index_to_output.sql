@@ -0,0 +1,3 @@
+-- 20241024_add_lock_script_data_prefix_index_to_output.sql
+
+CREATE INDEX IF NOT EXISTS "idx_output_table_lock_script_id_data_prefix" ON "output" ("lock_script_id", "data_prefix");
@@ -0,0 +1,4 @@
+-- 20241025_add_transaction_count_to_block.sql
+
+ALTER TABLE block
+ADD COLUMN transaction_count BIGINT;
@@ -0,0 +1,8 @@
+-- 20241026_add_is_cellbase_to_ckb_transaction.sql
+
+ALTER TABLE ckb_transaction
+ADD COLUMN is_cellbase INTEGER DEFAULT 0;
+
+UPDATE ckb_transaction
+SET is_cellbase = 1
+WHERE tx_index = 0;
@@ -0,0 +1,6 @@
+-- 20241027_add_data_hash_to_output.sql
+
+ALTER TABLE output
+ADD COLUMN data_hash BYTEA;
+
+CREATE INDEX IF NOT EXISTS idx_output_data_hash ON output(data_hash);
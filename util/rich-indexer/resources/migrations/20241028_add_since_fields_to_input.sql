@@ -0,0 +1,12 @@
+-- 20241028_add_since_fields_to_input.sql
+
+ALTER TABLE input
+ADD COLUMN since_flag SMALLINT;
+
+ALTER TABLE input
+ADD COLUMN since_type SMALLINT;
+
+ALTER TABLE input
+ADD COLUMN since_value BIGINT;
+
+CREATE INDEX IF NOT EXISTS idx_input_since_flag_type ON input(since_flag, since_type);
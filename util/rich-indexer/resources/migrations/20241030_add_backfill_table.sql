@@ -0,0 +1,5 @@
+-- 20241030_add_backfill_table.sql
+
+CREATE TABLE IF NOT EXISTS backfill(
+    name TEXT PRIMARY KEY
+);
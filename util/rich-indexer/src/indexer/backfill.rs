@@ -0,0 +1,162 @@
+//! One-off backfills of the columns which rows indexed before their introduction lack.
+//!
+//! Completed backfills are recorded in the `backfill` table, so that each one runs once rather
+//! than scanning the tables on every startup.
+
+use super::{
+    db_error, fill_data_prefixes, fill_missing_data_hashes, fill_missing_script_hashes,
+    fill_missing_since_fields,
+};
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use sqlx::Row;
+
+use std::collections::HashSet;
+
+/// A backfill of a column for the rows indexed before it was introduced or configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Backfill {
+    /// Fills in `script.script_hash`.
+    ScriptHash,
+    /// Fills in `output.data_prefix` with the configured prefix length.
+    DataPrefix(usize),
+    /// Fills in `output.data_hash`.
+    DataHash,
+    /// Fills in the decoded since fields of `input`. Inputs whose since doesn't decode keep
+    /// them NULL, which is why this can't be told from the data alone.
+    SinceFields,
+}
+
+impl Backfill {
+    /// Returns the name the backfill is recorded under once completed.
+    pub(crate) fn name(&self) -> String {
+        match self {
+            Backfill::ScriptHash => "script_hash".to_string(),
+            Backfill::DataPrefix(len) => format!("data_prefix_{}", len),
+            Backfill::DataHash => "data_hash".to_string(),
+            Backfill::SinceFields => "since_fields".to_string(),
+        }
+    }
+
+    async fn run(&self, store: &SQLXPool) -> Result<(), Error> {
+        match self {
+            Backfill::ScriptHash => fill_missing_script_hashes(store).await,
+            Backfill::DataPrefix(_) => fill_data_prefixes(store).await,
+            Backfill::DataHash => fill_missing_data_hashes(store).await,
+            Backfill::SinceFields => fill_missing_since_fields(store).await,
+        }
+    }
+}
+
+/// Returns the backfills required by the database and its configuration which have not been
+/// completed yet.
+pub(crate) async fn pending_backfills(store: &SQLXPool) -> Result<Vec<Backfill>, Error> {
+    let completed: HashSet<String> = store
+        .fetch_all(SQLXPool::new_query("SELECT name FROM backfill"))
+        .await
+        .map_err(db_error)?
+        .iter()
+        .map(|row| row.get::<String, _>("name"))
+        .collect();
+
+    let mut pending: Vec<Backfill> = [
+        Backfill::ScriptHash,
+        Backfill::DataHash,
+        Backfill::SinceFields,
+    ]
+    .into_iter()
+    .filter(|backfill| !completed.contains(&backfill.name()))
+    .collect();
+    if let Some(len) = store.data_prefix_len {
+        let backfill = Backfill::DataPrefix(len);
+        if !completed.contains(&backfill.name()) {
+            pending.push(backfill);
+        }
+    }
+    Ok(pending)
+}
+
+/// Loads whether the prefixes of the configured length are complete, the `data_prefix` column
+/// only narrows queries once they are, see `SQLXPool::queryable_data_prefix_len`.
+pub(crate) async fn load_data_prefix_complete(store: &SQLXPool) -> Result<(), Error> {
+    let complete_len = match store.data_prefix_len {
+        Some(len) => store
+            .fetch_optional(
+                SQLXPool::new_query("SELECT name FROM backfill WHERE name = $1")
+                    .bind(Backfill::DataPrefix(len).name()),
+            )
+            .await
+            .map_err(db_error)?
+            .map(|_| len),
+        None => None,
+    };
+    *store
+        .complete_data_prefix_len
+        .write()
+        .expect("acquire lock") = complete_len;
+    Ok(())
+}
+
+/// Forgets the completed prefixes of another length than the configured one. The outputs
+/// indexed from now on lack them, so they are filled in again once configured again.
+async fn forget_stale_data_prefixes(store: &SQLXPool) -> Result<(), Error> {
+    let name = store
+        .data_prefix_len
+        .map(|len| Backfill::DataPrefix(len).name())
+        .unwrap_or_default();
+    SQLXPool::new_query("DELETE FROM backfill WHERE name LIKE 'data_prefix_%' AND name <> $1")
+        .bind(name)
+        .execute(store.get_pool().map_err(db_error)?)
+        .await
+        .map(|_| ())
+        .map_err(db_error)
+}
+
+/// Runs the pending backfills, each one is recorded as soon as it completes so that an
+/// interrupted startup resumes with the remaining ones.
+pub(crate) async fn run_pending_backfills(store: &SQLXPool) -> Result<(), Error> {
+    for backfill in pending_backfills(store).await? {
+        log::info!("Running rich-indexer backfill {}...", backfill.name());
+        backfill.run(store).await?;
+
+        let mut tx = store.transaction().await.map_err(db_error)?;
+        if let Backfill::DataPrefix(_) = backfill {
+            // only the prefixes of the configured length are kept
+            SQLXPool::new_query("DELETE FROM backfill WHERE name LIKE 'data_prefix_%'")
+                .execute(tx.as_mut())
+                .await
+                .map_err(db_error)?;
+        }
+        SQLXPool::new_query("INSERT INTO backfill (name) VALUES ($1)")
+            .bind(backfill.name())
+            .execute(tx.as_mut())
+            .await
+            .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+        log::info!("Rich-indexer backfill {} is done.", backfill.name());
+    }
+    load_data_prefix_complete(store).await
+}
+
+/// Runs the pending backfills on startup when `auto_migrate` is enabled. Otherwise the database
+/// is left untouched, and the pending backfills are only reported.
+pub(crate) async fn backfill_on_startup(store: &SQLXPool, auto_migrate: bool) -> Result<(), Error> {
+    forget_stale_data_prefixes(store).await?;
+    if auto_migrate {
+        return run_pending_backfills(store).await;
+    }
+    let pending = pending_backfills(store).await?;
+    if !pending.is_empty() {
+        log::warn!(
+            "Rich-indexer backfills {} are pending, the columns they fill in stay incomplete \
+            until the indexer is started with auto_migrate",
+            pending
+                .iter()
+                .map(Backfill::name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    load_data_prefix_complete(store).await
+}
@@ -1,14 +1,18 @@
 #![allow(clippy::needless_borrow)]
 
-use super::to_fixed_array;
+use super::{
+    code_hash_from_db, compress_data, db_error, decompress_data, to_fixed_array, SinceLock,
+};
 use crate::store::SQLXPool;
 
+use ckb_app_config::{CustomCellFieldKind, CustomCellSchema, DBDriver};
 use ckb_indexer_sync::Error;
 use ckb_types::{
     bytes::Bytes,
     core::{BlockView, TransactionView},
-    packed::{Byte, CellInput, CellOutput, OutPoint, ScriptBuilder},
+    packed::{Byte, CellInput, CellOutput, OutPoint, Script, ScriptBuilder},
     prelude::*,
+    H256,
 };
 use sql_builder::SqlBuilder;
 use sqlx::{
@@ -17,14 +21,12 @@ use sqlx::{
     Row, Transaction,
 };
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::slice;
 
-// Note that every database has a practical limit on the number of bind parameters you can add to a single query.
-// This varies by database.
-// https://docs.rs/sqlx/0.6.3/sqlx/struct.QueryBuilder.html#note-database-specific-limits
-// BATCH_SIZE_THRESHOLD represents the number of rows that can be bound in an insert sql execution.
-// The number of columns in each row multiplied by this BATCH_SIZE_THRESHOLD yields the total number of bound parameters,
-// which should be within the above limits.
+// BATCH_SIZE_THRESHOLD represents the number of out points that are looked up or updated in a
+// single sql execution. Bulk inserts are instead sized by the bind parameter limit of the
+// backend, and capped at this number of rows on SQLite, see `insert_batch_size`.
 pub(crate) const BATCH_SIZE_THRESHOLD: usize = 1_000;
 
 type OutputCellRow = (
@@ -33,13 +35,25 @@ type OutputCellRow = (
     (Vec<u8>, i16, Vec<u8>),
     Option<(Vec<u8>, i16, Vec<u8>)>,
     Vec<u8>,
+    bool,
+    Option<Vec<u8>>,
+    Vec<u8>,
 );
 
-enum FieldValue {
+type CustomCellFieldRow = (i32, String, String);
+
+/// `(output_id, since, input_index, (since_flag, since_type, since_value))`, the decoded since
+/// fields are `None` for inputs without a since lock.
+type InputRow = (i64, Vec<u8>, i32, Option<(i16, i16, i64)>);
+
+pub(crate) enum FieldValue {
     Binary(Vec<u8>),
+    Text(String),
     BigInt(i64),
     Int(i32),
     NoneBigInt,
+    NoneBinary,
+    NoneSmallInt,
     SmallInt(i16),
 }
 
@@ -50,9 +64,12 @@ impl FieldValue {
     ) -> Query<'a, Any, AnyArguments<'a>> {
         match self {
             FieldValue::Binary(value) => query.bind(value),
+            FieldValue::Text(value) => query.bind(value),
             FieldValue::BigInt(value) => query.bind(value),
             FieldValue::Int(value) => query.bind(value),
             FieldValue::NoneBigInt => query.bind(Option::<i64>::None),
+            FieldValue::NoneBinary => query.bind(Option::<Vec<u8>>::None),
+            FieldValue::NoneSmallInt => query.bind(Option::<i16>::None),
             FieldValue::SmallInt(value) => query.bind(value),
         }
     }
@@ -64,6 +81,12 @@ impl From<Vec<u8>> for FieldValue {
     }
 }
 
+impl From<String> for FieldValue {
+    fn from(value: String) -> Self {
+        FieldValue::Text(value)
+    }
+}
+
 impl From<i64> for FieldValue {
     fn from(value: i64) -> Self {
         FieldValue::BigInt(value)
@@ -91,6 +114,7 @@ pub(crate) async fn append_block(
     let block_id = insert_block_table(block_view, tx).await?;
     insert_block_proposals(block_id, block_view, tx).await?;
     bulk_insert_block_association_uncle_table(block_id, &uncle_id_list, tx).await?;
+    insert_block_commitment(block_id, block_view, tx).await?;
     Ok(block_id)
 }
 
@@ -103,15 +127,17 @@ pub(crate) async fn insert_uncle_blocks(
         .into_iter()
         .map(|uncle| {
             let uncle_block_header = uncle.header();
+            // keep the uncle header as is, building would recompute its roots and hash from
+            // the transactions, which an uncle doesn't carry
             BlockView::new_advanced_builder()
                 .header(uncle_block_header)
                 .proposals(uncle.data().proposals())
-                .build()
+                .build_unchecked()
         })
         .collect::<Vec<_>>();
     let uncle_block_rows: Vec<Vec<FieldValue>> = uncle_blocks
         .iter()
-        .map(block_view_to_field_values)
+        .map(|uncle| block_view_to_field_values(uncle, None))
         .collect();
     let uncle_id_list = bulk_insert_block_table(&uncle_block_rows, tx).await?;
     insert_blocks_proposals(&uncle_id_list, &uncle_blocks, tx).await?;
@@ -122,7 +148,7 @@ async fn insert_block_table(
     block_view: &BlockView,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<i64, Error> {
-    let block_row = block_view_to_field_values(block_view);
+    let block_row = block_view_to_field_values(block_view, Some(block_view.transactions().len()));
     bulk_insert_block_table(&[block_row], tx)
         .await
         .map(|ids| ids[0])
@@ -164,20 +190,37 @@ async fn insert_block_proposals(
     bulk_insert_block_association_proposal_table(&block_association_proposal_rows, tx).await
 }
 
+/// Inserts the transaction row, along with the number of its witnesses and the sum of
+/// their raw lengths. Both are NULL for transactions indexed before they were added.
+///
+/// The witnesses themselves are left NULL unless `index_witnesses` is set.
 pub(crate) async fn insert_transaction_table(
     block_id: i64,
     tx_index: usize,
     tx_view: &TransactionView,
+    index_witnesses: bool,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<i64, Error> {
+    let witnesses_total_bytes: usize = tx_view
+        .witnesses()
+        .into_iter()
+        .map(|witness| witness.raw_data().len())
+        .sum();
     let tx_row = vec![
         tx_view.hash().raw_data().to_vec().into(),
         tx_view.version().to_be_bytes().to_vec().into(),
         (tx_view.inputs().len() as i32).into(),
         (tx_view.outputs().len() as i32).into(),
-        tx_view.witnesses().as_bytes().to_vec().into(),
+        if index_witnesses {
+            tx_view.witnesses().as_bytes().to_vec().into()
+        } else {
+            FieldValue::NoneBinary
+        },
+        (tx_view.witnesses().len() as i32).into(),
+        (witnesses_total_bytes as i64).into(),
         block_id.into(),
         (tx_index as i32).into(),
+        (is_cellbase(tx_index) as i32).into(),
     ];
     bulk_insert_and_return_ids(
         "ckb_transaction",
@@ -187,8 +230,11 @@ pub(crate) async fn insert_transaction_table(
             "input_count",
             "output_count",
             "witnesses",
+            "witness_count",
+            "witnesses_total_bytes",
             "block_id",
             "tx_index",
+            "is_cellbase",
         ],
         &[tx_row],
         tx,
@@ -210,6 +256,45 @@ pub(crate) async fn bulk_insert_blocks_simple(
         &["block_hash", "block_number"],
         &simple_block_rows,
         None,
+        None,
+        tx,
+    )
+    .await
+}
+
+/// Extensions of at least 32 and at most 96 bytes follow the layout of RFC 0044, which
+/// commits to the chain root MMR hash in the first 32 bytes.
+const CHAIN_ROOT_EXTENSION_LEN: std::ops::RangeInclusive<usize> = 32..=96;
+
+/// Parses the light-client commitment of the block extension, returns the chain root and
+/// the remaining extra data, or `None` if the extension doesn't match the known layout.
+pub(crate) fn parse_block_commitment(extension: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    if !CHAIN_ROOT_EXTENSION_LEN.contains(&extension.len()) {
+        return None;
+    }
+    let (chain_root, extra_data) = extension.split_at(32);
+    Some((chain_root.to_vec(), extra_data.to_vec()))
+}
+
+async fn insert_block_commitment(
+    block_id: i64,
+    block_view: &BlockView,
+    tx: &mut Transaction<'_, Any>,
+) -> Result<(), Error> {
+    let commitment = block_view
+        .data()
+        .extension()
+        .and_then(|extension| parse_block_commitment(&extension.raw_data()));
+    let (chain_root, extra_data) = match commitment {
+        Some(commitment) => commitment,
+        None => return Ok(()),
+    };
+    bulk_insert(
+        "block_commitment",
+        &["block_id", "chain_root", "extra_data"],
+        &[vec![block_id.into(), chain_root.into(), extra_data.into()]],
+        None,
+        None,
         tx,
     )
     .await
@@ -235,6 +320,7 @@ async fn bulk_insert_block_table(
             "proposals_hash",
             "extra_hash",
             "extension",
+            "transaction_count",
         ],
         block_rows,
         tx,
@@ -251,6 +337,7 @@ async fn bulk_insert_block_association_proposal_table(
         &["block_id", "proposal"],
         &block_association_proposal_rows,
         None,
+        None,
         tx,
     )
     .await
@@ -270,6 +357,7 @@ async fn bulk_insert_block_association_uncle_table(
         &["block_id", "uncle_id"],
         &block_association_uncle_rows,
         None,
+        None,
         tx,
     )
     .await
@@ -280,25 +368,51 @@ pub(crate) async fn bulk_insert_output_table(
     output_cell_rows: Vec<OutputCellRow>,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<(), Error> {
-    let mut new_rows: Vec<Vec<FieldValue>> = Vec::new();
-    for row in output_cell_rows {
-        let type_script_id = if let Some(type_script) = &row.3 {
-            query_script_id(&type_script.0, type_script.1, &type_script.2, tx).await?
-        } else {
-            None
-        };
-        let new_row = vec![
-            tx_id.into(),
-            row.0.into(),
-            row.1.into(),
-            query_script_id(&row.2 .0, row.2 .1, &row.2 .2, tx)
-                .await?
-                .map_or(FieldValue::NoneBigInt, FieldValue::BigInt),
-            type_script_id.map_or(FieldValue::NoneBigInt, FieldValue::BigInt),
-            row.4.into(),
-        ];
-        new_rows.push(new_row);
+    let mut script_hashes = Vec::with_capacity(output_cell_rows.len());
+    for row in &output_cell_rows {
+        let lock_script_hash = calc_script_hash(&row.2 .0, row.2 .1, &row.2 .2)?;
+        let type_script_hash = row
+            .3
+            .as_ref()
+            .map(|type_script| calc_script_hash(&type_script.0, type_script.1, &type_script.2))
+            .transpose()?;
+        script_hashes.push((lock_script_hash, type_script_hash));
     }
+    let distinct_script_hashes: Vec<Vec<u8>> = script_hashes
+        .iter()
+        .flat_map(|(lock_script_hash, type_script_hash)| {
+            std::iter::once(lock_script_hash).chain(type_script_hash)
+        })
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let script_ids = query_script_ids(&distinct_script_hashes, tx).await?;
+    let script_id = |script_hash: &Vec<u8>| {
+        script_ids
+            .get(script_hash)
+            .map_or(FieldValue::NoneBigInt, |id| FieldValue::BigInt(*id))
+    };
+
+    let new_rows: Vec<Vec<FieldValue>> = output_cell_rows
+        .into_iter()
+        .zip(&script_hashes)
+        .map(|(row, (lock_script_hash, type_script_hash))| {
+            vec![
+                tx_id.into(),
+                row.0.into(),
+                row.1.into(),
+                script_id(lock_script_hash),
+                type_script_hash
+                    .as_ref()
+                    .map_or(FieldValue::NoneBigInt, script_id),
+                row.4.into(),
+                (row.5 as i32).into(),
+                row.6.map_or(FieldValue::NoneBinary, FieldValue::Binary),
+                row.7.into(),
+            ]
+        })
+        .collect();
     bulk_insert(
         "output",
         &[
@@ -308,9 +422,53 @@ pub(crate) async fn bulk_insert_output_table(
             "lock_script_id",
             "type_script_id",
             "data",
+            "data_compressed",
+            "data_prefix",
+            "data_hash",
         ],
         &new_rows,
         None,
+        None,
+        tx,
+    )
+    .await
+}
+
+pub(crate) async fn bulk_insert_custom_cell_field_table(
+    tx_id: i64,
+    custom_cell_field_rows: Vec<CustomCellFieldRow>,
+    tx: &mut Transaction<'_, Any>,
+) -> Result<(), Error> {
+    if custom_cell_field_rows.is_empty() {
+        return Ok(());
+    }
+    let output_ids: HashMap<i32, i64> = SQLXPool::new_query(
+        r#"
+        SELECT id, output_index FROM output
+        WHERE tx_id = $1
+        "#,
+    )
+    .bind(tx_id)
+    .fetch_all(tx.as_mut())
+    .await
+    .map_err(db_error)?
+    .into_iter()
+    .map(|row| (row.get("output_index"), row.get("id")))
+    .collect();
+    let rows = custom_cell_field_rows
+        .into_iter()
+        .filter_map(|(output_index, name, value)| {
+            output_ids
+                .get(&output_index)
+                .map(|output_id| vec![(*output_id).into(), name.into(), value.into()])
+        })
+        .collect::<Vec<Vec<FieldValue>>>();
+    bulk_insert(
+        "custom_cell_field",
+        &["output_id", "field_name", "field_value"],
+        &rows,
+        None,
+        None,
         tx,
     )
     .await
@@ -318,18 +476,49 @@ pub(crate) async fn bulk_insert_output_table(
 
 pub(crate) async fn bulk_insert_input_table(
     tx_id: i64,
-    input_rows: Vec<(i64, Vec<u8>, i32)>,
+    input_rows: Vec<InputRow>,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<(), Error> {
     let input_rows = input_rows
         .into_iter()
-        .map(|row| vec![row.0.into(), row.1.into(), tx_id.into(), row.2.into()])
+        .map(|row| {
+            let (since_flag, since_type, since_value) = match row.3 {
+                Some((flag, since_type, value)) => (
+                    FieldValue::SmallInt(flag),
+                    FieldValue::SmallInt(since_type),
+                    FieldValue::BigInt(value),
+                ),
+                None => (
+                    FieldValue::NoneSmallInt,
+                    FieldValue::NoneSmallInt,
+                    FieldValue::NoneBigInt,
+                ),
+            };
+            vec![
+                row.0.into(),
+                row.1.into(),
+                tx_id.into(),
+                row.2.into(),
+                since_flag,
+                since_type,
+                since_value,
+            ]
+        })
         .collect::<Vec<Vec<FieldValue>>>();
     bulk_insert(
         "input",
-        &["output_id", "since", "consumed_tx_id", "input_index"],
+        &[
+            "output_id",
+            "since",
+            "consumed_tx_id",
+            "input_index",
+            "since_flag",
+            "since_type",
+            "since_value",
+        ],
         &input_rows,
         None,
+        None,
         tx,
     )
     .await
@@ -342,18 +531,273 @@ pub(crate) async fn bulk_insert_script_table(
     // let script_rows = script_set.iter().collect::<Vec<_>>();
     let script_rows = script_set
         .into_iter()
-        .map(|(code_hash, hash_type, args)| vec![code_hash.into(), hash_type.into(), args.into()])
-        .collect::<Vec<_>>();
+        .map(|(code_hash, hash_type, args)| {
+            let script_hash = calc_script_hash(&code_hash, hash_type, &args)?;
+            Ok(vec![
+                code_hash.into(),
+                hash_type.into(),
+                args.into(),
+                script_hash.into(),
+            ])
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
     bulk_insert(
         "script",
-        &["code_hash", "hash_type", "args"],
+        &["code_hash", "hash_type", "args", "script_hash"],
         &script_rows,
         Some(&["code_hash", "hash_type", "args"]),
+        None,
         tx,
     )
     .await
 }
 
+/// Computes the hash of a script stored as `(code_hash, hash_type, args)` in the script table.
+pub(crate) fn calc_script_hash(
+    code_hash: &[u8],
+    hash_type: i16,
+    args: &[u8],
+) -> Result<Vec<u8>, Error> {
+    Ok(ScriptBuilder::default()
+        .code_hash(code_hash_from_db(code_hash)?.pack())
+        .hash_type(Byte::new(hash_type as u8))
+        .args(args.pack())
+        .build()
+        .calc_script_hash()
+        .raw_data()
+        .to_vec())
+}
+
+/// Fills in the hash of scripts indexed before the `script_hash` column was introduced.
+pub(crate) async fn fill_missing_script_hashes(store: &SQLXPool) -> Result<(), Error> {
+    let rows = store
+        .fetch_all(SQLXPool::new_query(
+            r#"
+            SELECT id, code_hash, hash_type, args
+            FROM script
+            WHERE script_hash IS NULL
+            "#,
+        ))
+        .await
+        .map_err(db_error)?;
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = store.transaction().await.map_err(db_error)?;
+    for row in &rows {
+        let id: i64 = row.get("id");
+        let code_hash: Vec<u8> = row.get("code_hash");
+        let hash_type: i16 = row.get("hash_type");
+        let args: Option<Vec<u8>> = row.get("args");
+        let script_hash = calc_script_hash(&code_hash, hash_type, &args.unwrap_or_default())?;
+        SQLXPool::new_query("UPDATE script SET script_hash = $1 WHERE id = $2")
+            .bind(script_hash)
+            .bind(id)
+            .execute(tx.as_mut())
+            .await
+            .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)
+}
+
+/// Brings the `data_prefix` column in line with the configured prefix length, filling in
+/// outputs indexed while it was disabled or with a different length, `BATCH_SIZE_THRESHOLD`
+/// outputs per transaction. The prefix of compressed data is taken from the original data.
+pub(crate) async fn fill_data_prefixes(store: &SQLXPool) -> Result<(), Error> {
+    let len = match store.data_prefix_len {
+        Some(len) => len,
+        None => return Ok(()),
+    };
+    let mut last_id = -1;
+    loop {
+        let rows = store
+            .fetch_all(
+                SQLXPool::new_query(
+                    r#"
+                    SELECT id, data, data_compressed, data_prefix
+                    FROM output
+                    WHERE id > $1
+                    ORDER BY id
+                    LIMIT $2
+                    "#,
+                )
+                .bind(last_id)
+                .bind(BATCH_SIZE_THRESHOLD as i64),
+            )
+            .await
+            .map_err(db_error)?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = store.transaction().await.map_err(db_error)?;
+        for row in &rows {
+            last_id = row.get::<i64, _>("id");
+            let data = decompress_data(
+                row.get::<Option<Vec<u8>>, _>("data").unwrap_or_default(),
+                is_data_compressed(row),
+            )?;
+            let data_prefix = &data[..data.len().min(len)];
+            if row.get::<Option<Vec<u8>>, _>("data_prefix").as_deref() == Some(data_prefix) {
+                continue;
+            }
+            SQLXPool::new_query("UPDATE output SET data_prefix = $1 WHERE id = $2")
+                .bind(data_prefix.to_vec())
+                .bind(last_id)
+                .execute(tx.as_mut())
+                .await
+                .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)?;
+    }
+}
+
+/// Fills in the data hash of outputs indexed before the `data_hash` column was introduced,
+/// `BATCH_SIZE_THRESHOLD` outputs per transaction.
+pub(crate) async fn fill_missing_data_hashes(store: &SQLXPool) -> Result<(), Error> {
+    loop {
+        let rows = store
+            .fetch_all(
+                SQLXPool::new_query(
+                    r#"
+                    SELECT id, data, data_compressed
+                    FROM output
+                    WHERE data_hash IS NULL
+                    ORDER BY id
+                    LIMIT $1
+                    "#,
+                )
+                .bind(BATCH_SIZE_THRESHOLD as i64),
+            )
+            .await
+            .map_err(db_error)?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = store.transaction().await.map_err(db_error)?;
+        for row in &rows {
+            let data = decompress_data(
+                row.get::<Option<Vec<u8>>, _>("data").unwrap_or_default(),
+                is_data_compressed(row),
+            )?;
+            SQLXPool::new_query("UPDATE output SET data_hash = $1 WHERE id = $2")
+                .bind(CellOutput::calc_data_hash(&data).raw_data().to_vec())
+                .bind(row.get::<i64, _>("id"))
+                .execute(tx.as_mut())
+                .await
+                .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)?;
+    }
+}
+
+/// Fills in the decoded since fields of inputs indexed before they were introduced,
+/// `BATCH_SIZE_THRESHOLD` inputs per transaction. Inputs without a since lock keep them NULL,
+/// so this is run once as a backfill rather than until no NULL is left.
+pub(crate) async fn fill_missing_since_fields(store: &SQLXPool) -> Result<(), Error> {
+    let mut last_output_id = -1;
+    loop {
+        let rows = store
+            .fetch_all(
+                SQLXPool::new_query(
+                    r#"
+                    SELECT output_id, since
+                    FROM input
+                    WHERE since_flag IS NULL AND since <> $1 AND output_id > $2
+                    ORDER BY output_id
+                    LIMIT $3
+                    "#,
+                )
+                .bind(0u64.to_be_bytes().to_vec())
+                .bind(last_output_id)
+                .bind(BATCH_SIZE_THRESHOLD as i64),
+            )
+            .await
+            .map_err(db_error)?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = store.transaction().await.map_err(db_error)?;
+        for row in &rows {
+            last_output_id = row.get::<i64, _>("output_id");
+            let since = to_fixed_array::<8>(&row.get::<Vec<u8>, _>("since"));
+            let Some(since_lock) = SinceLock::decode(u64::from_be_bytes(since)) else {
+                continue;
+            };
+            let (since_flag, since_type, since_value) = since_lock.to_columns();
+            SQLXPool::new_query(
+                r#"
+                UPDATE input
+                SET since_flag = $1, since_type = $2, since_value = $3
+                WHERE output_id = $4
+                "#,
+            )
+            .bind(since_flag)
+            .bind(since_type)
+            .bind(since_value)
+            .bind(last_output_id)
+            .execute(tx.as_mut())
+            .await
+            .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)?;
+    }
+}
+
+/// Number of output ids covered by each transaction of `reconcile_spent_flags`.
+pub(crate) const RECONCILE_SPENT_FLAGS_CHUNK_SIZE: i64 = 100_000;
+
+/// Corrects the `is_spent` flag of outputs which disagrees with the input table, as left by
+/// versions which failed to maintain it. The outputs are processed in ranges of `chunk_size`
+/// ids, each in its own transaction, and the number of corrected outputs is returned.
+pub(crate) async fn reconcile_spent_flags(store: &SQLXPool, chunk_size: i64) -> Result<u64, Error> {
+    let max_id = store
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COALESCE(MAX(id), 0) AS max_id FROM output",
+        ))
+        .await
+        .map_err(db_error)?
+        .get::<i64, _>("max_id");
+    let mut corrected = 0;
+    let mut start = 0;
+    while start <= max_id {
+        let end = start.saturating_add(chunk_size.max(1));
+        let mut tx = store.transaction().await.map_err(db_error)?;
+        corrected += SQLXPool::new_query(
+            r#"
+            UPDATE output
+            SET is_spent = CASE
+                WHEN EXISTS (SELECT 1 FROM input WHERE input.output_id = output.id) THEN 1
+                ELSE 0
+            END
+            WHERE id >= $1 AND id < $2
+            AND (is_spent IS NULL OR is_spent <> CASE
+                WHEN EXISTS (SELECT 1 FROM input WHERE input.output_id = output.id) THEN 1
+                ELSE 0
+            END)
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .execute(tx.as_mut())
+        .await
+        .map_err(db_error)?
+        .rows_affected();
+        tx.commit().await.map_err(db_error)?;
+        log::info!(
+            "reconciled spent flags of outputs up to id {} of {}, {} corrected",
+            end.min(max_id),
+            max_id,
+            corrected
+        );
+        start = end;
+    }
+    Ok(corrected)
+}
+
 pub(crate) async fn bulk_insert_tx_association_header_dep_table(
     tx_id: i64,
     tx_view: &TransactionView,
@@ -370,6 +814,7 @@ pub(crate) async fn bulk_insert_tx_association_header_dep_table(
         &["tx_id", "block_id"],
         &tx_association_header_dep_rows,
         None,
+        None,
         tx,
     )
     .await
@@ -380,9 +825,14 @@ pub(crate) async fn bulk_insert_tx_association_cell_dep_table(
     tx_view: &TransactionView,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<(), Error> {
+    let out_points: Vec<OutPoint> = tx_view
+        .cell_deps_iter()
+        .map(|cell_dep| cell_dep.out_point())
+        .collect();
+    let output_ids = query_output_ids(&out_points, tx).await?;
     let mut tx_association_cell_dep_rows = Vec::new();
     for cell_dep in tx_view.cell_deps_iter() {
-        if let Some(output_id) = query_output_id(&cell_dep.out_point(), tx).await? {
+        if let Some(&output_id) = output_ids.get(&cell_dep.out_point()) {
             tx_association_cell_dep_rows.push(vec![
                 tx_id.into(),
                 output_id.into(),
@@ -395,35 +845,92 @@ pub(crate) async fn bulk_insert_tx_association_cell_dep_table(
         &["tx_id", "output_id", "dep_type"],
         &tx_association_cell_dep_rows,
         None,
+        None,
         tx,
     )
     .await
 }
 
+/// Marks the cell of `out_point` as spent, returns whether it is indexed.
+#[allow(dead_code)]
 pub(crate) async fn spend_cell(
     out_point: &OutPoint,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<bool, Error> {
-    let output_tx_hash = out_point.tx_hash().raw_data().to_vec();
-    let output_index: u32 = out_point.index().unpack();
+    bulk_spend_cells(slice::from_ref(out_point), tx)
+        .await
+        .map(|updated_rows| updated_rows > 0)
+}
 
-    let updated_rows = sqlx::query(
-        r#"
-            UPDATE output
-            SET is_spent = 1
-            WHERE
-                tx_id = (SELECT ckb_transaction.id FROM ckb_transaction WHERE tx_hash = $1)
-                AND output_index = $2
-        "#,
-    )
-    .bind(output_tx_hash)
-    .bind(output_index as i32)
-    .execute(tx.as_mut())
-    .await
-    .map_err(|err| Error::DB(err.to_string()))?
-    .rows_affected();
+/// Marks the cells of `out_points` as spent, in chunks of `BATCH_SIZE_THRESHOLD` out
+/// points per statement. Returns the number of cells updated, out points which are not
+/// indexed are not counted.
+pub(crate) async fn bulk_spend_cells(
+    out_points: &[OutPoint],
+    tx: &mut Transaction<'_, Any>,
+) -> Result<u64, Error> {
+    let mut updated_rows = 0;
+    for chunk in out_points.chunks(BATCH_SIZE_THRESHOLD) {
+        // resolve the transaction ids first, so that the update matches the
+        // (tx_id, output_index) pairs of the output table directly
+        let tx_hashes: Vec<Vec<u8>> = chunk
+            .iter()
+            .map(|out_point| out_point.tx_hash().raw_data().to_vec())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let placeholders = (1..=tx_hashes.len())
+            .map(|i| format!("${}", i))
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            "SELECT id, tx_hash FROM ckb_transaction WHERE tx_hash IN ({})",
+            placeholders
+        );
+        let mut query = SQLXPool::new_query(&sql);
+        for tx_hash in &tx_hashes {
+            query = query.bind(tx_hash.as_slice());
+        }
+        let tx_ids: HashMap<Vec<u8>, i64> = query
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(|row| (row.get::<Vec<u8>, _>("tx_hash"), row.get::<i64, _>("id")))
+            .collect();
 
-    Ok(updated_rows > 0)
+        // out points of transactions which are not indexed can't be spent
+        let pairs: Vec<(i64, i32)> = chunk
+            .iter()
+            .filter_map(|out_point| {
+                let output_index: u32 = out_point.index().unpack();
+                tx_ids
+                    .get(out_point.tx_hash().raw_data().as_ref())
+                    .map(|tx_id| (*tx_id, output_index as i32))
+            })
+            .collect();
+        if pairs.is_empty() {
+            continue;
+        }
+        let placeholders = (0..pairs.len())
+            .map(|i| format!("(${}, ${})", i * 2 + 1, i * 2 + 2))
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            "UPDATE output SET is_spent = 1 WHERE (tx_id, output_index) IN ({})",
+            placeholders
+        );
+        let mut query = SQLXPool::new_query(&sql);
+        for (tx_id, output_index) in pairs {
+            query = query.bind(tx_id).bind(output_index);
+        }
+        updated_rows += query
+            .execute(tx.as_mut())
+            .await
+            .map_err(db_error)?
+            .rows_affected();
+    }
+    Ok(updated_rows)
 }
 
 pub(crate) async fn query_output_cell(
@@ -439,6 +946,7 @@ pub(crate) async fn query_output_cell(
             output.id,
             output.capacity,
             output.data,
+            output.data_compressed,
             lock_script.code_hash AS lock_code_hash,
             lock_script.hash_type AS lock_hash_type,
             lock_script.args AS lock_args,
@@ -460,58 +968,106 @@ pub(crate) async fn query_output_cell(
     .bind(output_index as i32)
     .fetch_optional(tx.as_mut())
     .await
-    .map_err(|err| Error::DB(err.to_string()))?;
+    .map_err(db_error)?;
 
-    Ok(build_cell_output(row))
+    build_cell_output(row)
 }
 
-pub(crate) async fn query_output_id(
-    out_point: &OutPoint,
+/// Looks up the ids of the outputs of `out_points`, in chunks of `BATCH_SIZE_THRESHOLD` out
+/// points per statement. Out points which are not indexed, such as the input of a cellbase,
+/// are left out of the map.
+pub(crate) async fn query_output_ids(
+    out_points: &[OutPoint],
     tx: &mut Transaction<'_, Any>,
-) -> Result<Option<i64>, Error> {
-    let output_tx_hash = out_point.tx_hash().raw_data().to_vec();
-    let output_index: u32 = out_point.index().unpack();
-
-    sqlx::query(
-        r#"
-        SELECT output.id
-        FROM
-            output
-        WHERE
-            output.tx_id = (SELECT ckb_transaction.id FROM ckb_transaction WHERE tx_hash = $1)
-            AND output.output_index = $2
-        "#,
-    )
-    .bind(output_tx_hash)
-    .bind(output_index as i32)
-    .fetch_optional(tx.as_mut())
-    .await
-    .map_err(|err| Error::DB(err.to_string()))
-    .map(|row| row.map(|row| row.get::<i64, _>("id")))
+) -> Result<HashMap<OutPoint, i64>, Error> {
+    let mut output_ids = HashMap::new();
+    for chunk in out_points.chunks(BATCH_SIZE_THRESHOLD) {
+        let mut keys = HashMap::new();
+        let placeholders = chunk
+            .iter()
+            .enumerate()
+            .map(|(i, out_point)| {
+                let output_index: u32 = out_point.index().unpack();
+                keys.insert(
+                    (out_point.tx_hash().raw_data().to_vec(), output_index as i32),
+                    out_point,
+                );
+                format!("(${}, {})", i + 1, output_index)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            r#"
+            SELECT output.id, ckb_transaction.tx_hash, output.output_index
+            FROM output
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            WHERE (ckb_transaction.tx_hash, output.output_index) IN ({})
+            "#,
+            placeholders
+        );
+        let mut query = SQLXPool::new_query(&sql);
+        for out_point in chunk {
+            query = query.bind(out_point.tx_hash().raw_data().to_vec());
+        }
+        for row in query.fetch_all(tx.as_mut()).await.map_err(db_error)? {
+            let key = (
+                row.get::<Vec<u8>, _>("tx_hash"),
+                row.get::<i32, _>("output_index"),
+            );
+            if let Some(out_point) = keys.get(&key) {
+                output_ids.insert((*out_point).clone(), row.get::<i64, _>("id"));
+            }
+        }
+    }
+    Ok(output_ids)
 }
 
+/// Looks up the id of a script by its hash, which is computed as `Script::calc_script_hash`
+/// does, see `query_script_ids`.
+#[allow(dead_code)]
 pub(crate) async fn query_script_id(
-    code_hash: &[u8],
-    hash_type: i16,
-    args: &[u8],
+    script: &Script,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<Option<i64>, Error> {
-    sqlx::query(
-        r#"
-        SELECT id
-        FROM
-            script
-        WHERE
-            code_hash = $1 AND hash_type = $2 AND args = $3
-        "#,
-    )
-    .bind(code_hash)
-    .bind(hash_type)
-    .bind(args)
-    .fetch_optional(tx.as_mut())
-    .await
-    .map_err(|err| Error::DB(err.to_string()))
-    .map(|row| row.map(|row| row.get::<i64, _>("id")))
+    let script_hash = script.calc_script_hash().raw_data().to_vec();
+    query_script_ids(slice::from_ref(&script_hash), tx)
+        .await
+        .map(|script_ids| script_ids.get(&script_hash).copied())
+}
+
+/// Looks up the ids of scripts by their hash, see `calc_script_hash`, which is a single
+/// indexed column unlike the `(code_hash, hash_type, args)` triple. The hashes are looked up
+/// in chunks of `BATCH_SIZE_THRESHOLD` per statement, unknown ones are left out of the map.
+pub(crate) async fn query_script_ids(
+    script_hashes: &[Vec<u8>],
+    tx: &mut Transaction<'_, Any>,
+) -> Result<HashMap<Vec<u8>, i64>, Error> {
+    let mut script_ids = HashMap::new();
+    for chunk in script_hashes.chunks(BATCH_SIZE_THRESHOLD) {
+        let placeholders = (1..=chunk.len())
+            .map(|i| format!("${}", i))
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            r#"
+            SELECT id, script_hash
+            FROM script
+            WHERE script_hash IN ({})
+            "#,
+            placeholders
+        );
+        let mut query = SQLXPool::new_query(&sql);
+        for script_hash in chunk {
+            query = query.bind(script_hash.as_slice());
+        }
+        for row in query.fetch_all(tx.as_mut()).await.map_err(db_error)? {
+            script_ids.insert(
+                row.get::<Vec<u8>, _>("script_hash"),
+                row.get::<i64, _>("id"),
+            );
+        }
+    }
+    Ok(script_ids)
 }
 
 pub(crate) async fn query_block_id(
@@ -530,17 +1086,38 @@ pub(crate) async fn query_block_id(
     .bind(block_hash)
     .fetch_optional(tx.as_mut())
     .await
-    .map_err(|err| Error::DB(err.to_string()))
+    .map_err(db_error)
     .map(|row| row.map(|row| row.get::<i64, _>("id")))
 }
 
+/// Whether the transaction at `tx_index` of a block is the cellbase.
+///
+/// The single input of the cellbase is synthetic and refers to no output, so the cellbase
+/// never spends a cell and must be left out of fee or ledger calculations, while its reward
+/// outputs are indexed as regular live cells.
+pub(crate) fn is_cellbase(tx_index: usize) -> bool {
+    tx_index == 0
+}
+
+/// Builds the output row of a cell.
+///
+/// Script args and cell data are never stored as NULL, empty values are stored as
+/// zero-length bytes. Only the type script may be absent as a whole, in which case
+/// the `type_script_id` column is NULL. Data larger than the compression threshold
+/// is stored compressed, see `compress_data`. When `data_prefix_len` is set, the first
+/// bytes of the original data are kept in the `data_prefix` column as well.
 pub(crate) fn build_output_cell_rows(
     cell: &CellOutput,
     output_index: usize,
     data: &Bytes,
+    data_compression_threshold: Option<usize>,
+    data_prefix_len: Option<usize>,
     output_cell_rows: &mut Vec<OutputCellRow>,
 ) {
     let cell_capacity: u64 = cell.capacity().unpack();
+    let data_hash = CellOutput::calc_data_hash(data).raw_data().to_vec();
+    let data_prefix = data_prefix_len.map(|len| data[..data.len().min(len)].to_vec());
+    let (data, data_compressed) = compress_data(data, data_compression_threshold);
     let cell_row = (
         output_index as i32,
         cell_capacity as i64,
@@ -556,11 +1133,41 @@ pub(crate) fn build_output_cell_rows(
                 type_script.args().raw_data().to_vec(),
             )
         })),
-        data.to_vec(),
+        data,
+        data_compressed,
+        data_prefix,
+        data_hash,
     );
     output_cell_rows.push(cell_row);
 }
 
+/// Builds the custom field rows of a cell according to its data schema.
+///
+/// Integers are stored as decimal strings and `byte32` fields as 0x-prefixed hex,
+/// fields which lie beyond the end of the cell data are left out.
+pub(crate) fn build_custom_cell_field_rows(
+    schema: &CustomCellSchema,
+    output_index: usize,
+    data: &Bytes,
+    custom_cell_field_rows: &mut Vec<CustomCellFieldRow>,
+) {
+    for field in &schema.fields {
+        let raw = match data.get(field.offset..field.offset + field.kind.size()) {
+            Some(raw) => raw,
+            None => continue,
+        };
+        let value = match field.kind {
+            CustomCellFieldKind::Byte32 => format!("{:#x}", H256::from_slice(raw).expect("byte32")),
+            _ => {
+                let mut buf = [0u8; 16];
+                buf[..raw.len()].copy_from_slice(raw);
+                u128::from_le_bytes(buf).to_string()
+            }
+        };
+        custom_cell_field_rows.push((output_index as i32, field.name.clone(), value));
+    }
+}
+
 pub(crate) async fn build_script_set(
     cell: &CellOutput,
     script_row: &mut HashSet<(Vec<u8>, i16, Vec<u8>)>,
@@ -587,21 +1194,30 @@ pub(crate) fn build_input_rows(
     output_id: i64,
     input: &CellInput,
     input_index: usize,
-    input_rows: &mut Vec<(i64, Vec<u8>, i32)>,
+    input_rows: &mut Vec<InputRow>,
 ) {
     let since: u64 = input.since().unpack();
-    let input_row = (output_id, since.to_be_bytes().to_vec(), input_index as i32);
+    let input_row = (
+        output_id,
+        since.to_be_bytes().to_vec(),
+        input_index as i32,
+        SinceLock::decode(since).map(SinceLock::to_columns),
+    );
     input_rows.push(input_row);
 }
 
-fn build_cell_output(row: Option<AnyRow>) -> Option<(i64, CellOutput, Bytes)> {
+fn build_cell_output(row: Option<AnyRow>) -> Result<Option<(i64, CellOutput, Bytes)>, Error> {
     let row = match row {
         Some(row) => row,
-        None => return None,
+        None => return Ok(None),
     };
     let id: i64 = row.get("id");
     let capacity: i64 = row.get("capacity");
-    let data: Vec<u8> = row.get("data");
+    // A NULL data column is read back as empty data, see `build_output_cell_rows`.
+    let data: Vec<u8> = decompress_data(
+        row.get::<Option<Vec<u8>>, _>("data").unwrap_or_default(),
+        is_data_compressed(&row),
+    )?;
     let lock_code_hash: Option<Vec<u8>> = row.get("lock_code_hash");
     let lock_hash_type: Option<i16> = row.get("lock_hash_type");
     let lock_args: Option<Vec<u8>> = row.get("lock_args");
@@ -611,50 +1227,74 @@ fn build_cell_output(row: Option<AnyRow>) -> Option<(i64, CellOutput, Bytes)> {
 
     let mut lock_builder = ScriptBuilder::default();
     if let Some(lock_code_hash) = lock_code_hash {
-        lock_builder = lock_builder.code_hash(to_fixed_array::<32>(&lock_code_hash[0..32]).pack());
-    }
-    if let Some(lock_args) = lock_args {
-        lock_builder = lock_builder.args(lock_args.pack());
+        lock_builder = lock_builder.code_hash(code_hash_from_db(&lock_code_hash)?.pack());
     }
+    lock_builder = lock_builder.args(lock_args.unwrap_or_default().pack());
     if let Some(lock_hash_type) = lock_hash_type {
         lock_builder = lock_builder.hash_type(Byte::new(lock_hash_type as u8));
     }
     let lock_script = lock_builder.build();
 
-    let mut type_builder = ScriptBuilder::default();
-    if let Some(type_code_hash) = type_code_hash {
-        type_builder = type_builder.code_hash(to_fixed_array::<32>(&type_code_hash[0..32]).pack());
-    }
-    if let Some(type_args) = type_args {
-        type_builder = type_builder.args(type_args.pack());
-    }
-    if let Some(type_hash_type) = type_hash_type {
-        type_builder = type_builder.hash_type(Byte::new(type_hash_type as u8));
-    }
-    let type_script = type_builder.build();
+    // The type script is only absent when the whole script is absent,
+    // a script with empty args is still a script.
+    let type_script = type_code_hash
+        .map(|type_code_hash| {
+            let mut type_builder = ScriptBuilder::default()
+                .code_hash(code_hash_from_db(&type_code_hash)?.pack())
+                .args(type_args.unwrap_or_default().pack());
+            if let Some(type_hash_type) = type_hash_type {
+                type_builder = type_builder.hash_type(Byte::new(type_hash_type as u8));
+            }
+            Ok::<_, Error>(type_builder.build())
+        })
+        .transpose()?;
 
     let cell_output = CellOutput::new_builder()
         .capacity((capacity as u64).pack())
         .lock(lock_script)
-        .type_(Some(type_script).pack())
+        .type_(type_script.pack())
         .build();
 
-    Some((id, cell_output, data.into()))
+    Ok(Some((id, cell_output, data.into())))
+}
+
+/// Reads the `data_compressed` flag of an output row, rows indexed before the flag
+/// was introduced are uncompressed.
+pub(crate) fn is_data_compressed(row: &AnyRow) -> bool {
+    row.get::<Option<i32>, _>("data_compressed")
+        .unwrap_or_default()
+        != 0
 }
 
-async fn bulk_insert(
+/// Returns the number of rows of `fields_len` columns that can be bound in a single insert,
+/// within the bind parameter limit of the backend. SQLite resolves `$N` parameters in time
+/// quadratic in their number, so its inserts are also capped at `BATCH_SIZE_THRESHOLD` rows.
+pub(crate) fn insert_batch_size(fields_len: usize, tx: &Transaction<'_, Any>) -> usize {
+    let rows = (SQLXPool::max_bind_params(tx) / fields_len.max(1)).max(1);
+    if SQLXPool::is_sqlite(tx) {
+        rows.min(BATCH_SIZE_THRESHOLD)
+    } else {
+        rows
+    }
+}
+
+pub(crate) async fn bulk_insert(
     table: &str,
     fields: &[&str],
     rows: &[Vec<FieldValue>],
-    conflict_do_nothing_fields: Option<&[&str]>,
+    conflict_fields: Option<&[&str]>,
+    conflict_update_fields: Option<&[&str]>,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<(), Error> {
-    for bulk in rows.chunks(BATCH_SIZE_THRESHOLD) {
+    let db_driver = SQLXPool::backend_driver(tx);
+    let on_conflict = build_on_conflict_clause(db_driver, conflict_fields, conflict_update_fields)?;
+    for bulk in rows.chunks(insert_batch_size(fields.len(), tx)) {
         // build query str
-        let mut sql = build_bulk_insert_sql(table, fields, bulk)?;
-        if let Some(fields) = conflict_do_nothing_fields {
-            sql = format!("{} ON CONFLICT ({}) DO NOTHING", sql, fields.join(", "));
-        }
+        let sql = format!(
+            "{}{}",
+            build_bulk_insert_sql(db_driver, table, fields, bulk)?,
+            on_conflict
+        );
 
         // bind
         let mut query = SQLXPool::new_query(&sql);
@@ -665,25 +1305,26 @@ async fn bulk_insert(
         }
 
         // execute
-        query
-            .execute(tx.as_mut())
-            .await
-            .map_err(|err| Error::DB(err.to_string()))?;
+        query.execute(tx.as_mut()).await.map_err(db_error)?;
     }
     Ok(())
 }
 
-async fn bulk_insert_and_return_ids(
+pub(crate) async fn bulk_insert_and_return_ids(
     table: &str,
     fields: &[&str],
     rows: &[Vec<FieldValue>],
     tx: &mut Transaction<'_, Any>,
 ) -> Result<Vec<i64>, Error> {
+    let db_driver = SQLXPool::backend_driver(tx);
     let mut id_list = Vec::new();
-    for bulk in rows.chunks(BATCH_SIZE_THRESHOLD) {
+    for bulk in rows.chunks(insert_batch_size(fields.len(), tx)) {
         // build query str
-        let sql = build_bulk_insert_sql(table, fields, bulk)?;
-        let sql = format!("{} RETURNING id", sql);
+        let sql = build_bulk_insert_sql(db_driver, table, fields, bulk)?;
+        let sql = match db_driver {
+            DBDriver::Sqlite | DBDriver::Postgres => format!("{} RETURNING id", sql),
+            DBDriver::MySQL => sql,
+        };
 
         // bind
         let mut query = SQLXPool::new_query(&sql);
@@ -694,17 +1335,76 @@ async fn bulk_insert_and_return_ids(
         }
 
         // execute
-        let mut rows = query
-            .fetch_all(tx.as_mut())
-            .await
-            .map_err(|err| Error::DB(err.to_string()))?;
-        id_list.append(&mut rows);
+        match db_driver {
+            DBDriver::Sqlite | DBDriver::Postgres => {
+                let rows = query.fetch_all(tx.as_mut()).await.map_err(db_error)?;
+                id_list.extend(rows.iter().map(|row| row.get::<i64, _>("id")));
+            }
+            DBDriver::MySQL => {
+                // LAST_INSERT_ID() is the id of the first row, a multi-row insert whose
+                // row count is known ahead allocates consecutive ids in every lock mode of
+                // InnoDB
+                let first_id = query
+                    .execute(tx.as_mut())
+                    .await
+                    .map_err(db_error)?
+                    .last_insert_id()
+                    .ok_or_else(|| Error::DB("LAST_INSERT_ID() is not returned".to_string()))?;
+                id_list.extend((0..bulk.len() as i64).map(|offset| first_id + offset));
+            }
+        }
     }
-    let ret: Vec<_> = id_list.iter().map(|row| row.get::<i64, _>("id")).collect();
-    Ok(ret)
+    Ok(id_list)
 }
 
-fn build_bulk_insert_sql(
+/// Builds the `ON CONFLICT` clause of a bulk insert. The rows conflicting on
+/// `conflict_fields` are skipped, unless `conflict_update_fields` are given, in which case
+/// those fields of the existing rows are overwritten with the inserted values.
+///
+/// MySQL has no conflict target, its `ON DUPLICATE KEY UPDATE` clause applies to a
+/// conflict on any unique key, which `conflict_fields` is expected to be.
+pub(crate) fn build_on_conflict_clause(
+    db_driver: DBDriver,
+    conflict_fields: Option<&[&str]>,
+    conflict_update_fields: Option<&[&str]>,
+) -> Result<String, Error> {
+    match (conflict_fields, conflict_update_fields) {
+        (None, None) => Ok(String::new()),
+        (None, Some(_)) => Err(Error::DB(
+            "a conflict target is required to update conflicting rows".to_string(),
+        )),
+        (Some(conflict_fields), None) if db_driver == DBDriver::MySQL => Ok(format!(
+            " ON DUPLICATE KEY UPDATE {0} = {0}",
+            conflict_fields[0]
+        )),
+        (Some(_), Some(update_fields)) if db_driver == DBDriver::MySQL => Ok(format!(
+            " ON DUPLICATE KEY UPDATE {}",
+            update_fields
+                .iter()
+                .map(|field| format!("{} = VALUES({})", field, field))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        (Some(conflict_fields), None) => Ok(format!(
+            " ON CONFLICT ({}) DO NOTHING",
+            conflict_fields.join(", ")
+        )),
+        (Some(conflict_fields), Some(update_fields)) => Ok(format!(
+            " ON CONFLICT ({}) DO UPDATE SET {}",
+            conflict_fields.join(", "),
+            update_fields
+                .iter()
+                .map(|field| format!("{} = EXCLUDED.{}", field, field))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Builds a multi-row insert with the placeholders of `db_driver`, `$N` on SQLite and
+/// Postgres and `?` on MySQL.
+pub(crate) fn build_bulk_insert_sql(
+    db_driver: DBDriver,
     table: &str,
     fields: &[&str],
     bulk: &[Vec<FieldValue>],
@@ -713,19 +1413,27 @@ fn build_bulk_insert_sql(
     builder.fields(fields);
     bulk.iter().enumerate().for_each(|(row_index, row)| {
         let placeholders = (1..=row.len())
-            .map(|i| format!("${}", i + row_index * row.len()))
+            .map(|i| match db_driver {
+                DBDriver::Sqlite | DBDriver::Postgres => format!("${}", i + row_index * row.len()),
+                DBDriver::MySQL => "?".to_string(),
+            })
             .collect::<Vec<String>>();
         builder.values(&placeholders);
     });
     let sql = builder
         .sql()
-        .map_err(|err| Error::DB(err.to_string()))?
+        .map_err(db_error)?
         .trim_end_matches(';')
         .to_string();
     Ok(sql)
 }
 
-fn block_view_to_field_values(block_view: &BlockView) -> Vec<FieldValue> {
+/// Builds the block row, the transaction count is unknown for uncles, whose transactions
+/// are not part of the block.
+fn block_view_to_field_values(
+    block_view: &BlockView,
+    transaction_count: Option<usize>,
+) -> Vec<FieldValue> {
     vec![
         block_view.hash().raw_data().to_vec().into(),
         (block_view.number() as i64).into(),
@@ -748,5 +1456,8 @@ fn block_view_to_field_values(block_view: &BlockView) -> Vec<FieldValue> {
             Some(extension) => extension.raw_data().to_vec().into(),
             None => Vec::new().into(),
         },
+        transaction_count.map_or(FieldValue::NoneBigInt, |count| {
+            FieldValue::BigInt(count as i64)
+        }),
     ]
 }
@@ -1,24 +1,38 @@
+mod backfill;
 mod insert;
+mod out_point_filter;
 mod remove;
+mod since;
 
+pub(crate) use backfill::{backfill_on_startup, load_data_prefix_complete};
+#[cfg(test)]
+pub(crate) use backfill::{pending_backfills, run_pending_backfills, Backfill};
 pub(crate) use insert::*;
+pub(crate) use out_point_filter::OutPointFilter;
 pub(crate) use remove::*;
+pub(crate) use since::SinceLock;
 
 use crate::{service::SUBSCRIBER_NAME, store::SQLXPool, RichIndexerHandle};
 
+use ckb_app_config::{
+    CustomCellSchema, OversizedTransactions, RichIndexerConfig, ZeroCapacityCells,
+};
 use ckb_async_runtime::Handle;
 use ckb_indexer_sync::{CustomFilters, Error, IndexerSync, Pool};
 use ckb_types::{
     core::{BlockNumber, BlockView, TransactionView},
-    packed::Byte32,
+    packed::{Byte32, CellOutput, OutPoint},
     prelude::*,
     H256,
 };
 use sqlx::{Any, Transaction};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 
+/// In pruned mode, the number of blocks below the tip whose spent cells are kept for rollback.
+pub(crate) const PRUNED_MODE_KEEP_NUM: u64 = 100;
+
 /// the database tables are as follows:
 ///
 /// - block
@@ -30,6 +44,8 @@ use std::sync::{Arc, RwLock};
 /// - block_association_uncle
 /// - tx_association_header_dep
 /// - tx_association_cell_dep
+/// - custom_cell_field
+/// - block_commitment
 /// The detailed table design can be found in the SQL files in the resources folder of this crate
 
 /// Rich-Indexer, which is based on a relational database
@@ -46,11 +62,12 @@ impl RichIndexer {
         store: SQLXPool,
         pool: Option<Arc<RwLock<Pool>>>,
         custom_filters: CustomFilters,
+        config: RichIndexerConfig,
         async_runtime: Handle,
         request_limit: usize,
     ) -> Self {
         Self {
-            async_rich_indexer: AsyncRichIndexer::new(store, pool, custom_filters),
+            async_rich_indexer: AsyncRichIndexer::new(store, pool, custom_filters, config),
             async_runtime,
             request_limit,
         }
@@ -69,7 +86,6 @@ impl IndexerSync for RichIndexer {
         indexer_handle
             .get_indexer_tip()
             .map(|tip| tip.map(|tip| (tip.block_number.value(), tip.block_hash.0.pack())))
-            .map_err(|err| Error::DB(err.to_string()))
     }
 
     /// Appends a new block to the indexer
@@ -96,6 +112,15 @@ impl IndexerSync for RichIndexer {
             .set_init_tip(init_tip_number, init_tip_hash);
         self.async_runtime.block_on(future)
     }
+
+    /// Report the lag as the `ckb_rich_indexer_sync_lag` metric gauge
+    fn report_sync_lag(&self, lag: BlockNumber) {
+        if let Some(metrics) = ckb_metrics::handle() {
+            metrics
+                .ckb_rich_indexer_sync_lag
+                .set(lag.min(i64::MAX as u64) as i64);
+        }
+    }
 }
 
 /// Async rich-indexer.
@@ -108,6 +133,10 @@ pub(crate) struct AsyncRichIndexer {
     pub(crate) pool: Option<Arc<RwLock<Pool>>>,
     /// custom filters
     custom_filters: CustomFilters,
+    /// rich-indexer config
+    config: RichIndexerConfig,
+    /// Bloom filter of the indexed out points, loaded on the first append when enabled
+    out_point_filter: Option<Arc<RwLock<Option<OutPointFilter>>>>,
 }
 
 impl AsyncRichIndexer {
@@ -116,32 +145,71 @@ impl AsyncRichIndexer {
         store: SQLXPool,
         pool: Option<Arc<RwLock<Pool>>>,
         custom_filters: CustomFilters,
+        config: RichIndexerConfig,
     ) -> Self {
+        let out_point_filter = config
+            .out_point_filter_capacity
+            .map(|_| Arc::new(RwLock::new(None)));
         Self {
             store,
             pool,
             custom_filters,
+            config,
+            out_point_filter,
         }
     }
 }
 
 impl AsyncRichIndexer {
+    /// Indexes a block together with its transactions, cells and spends in a single database
+    /// transaction, so a failure or crash at any point leaves the block either fully indexed
+    /// or absent. Appending a block which is already indexed is a no-op, which makes it safe
+    /// to replay the last block after a restart.
     pub(crate) async fn append(&self, block: &BlockView) -> Result<(), Error> {
-        let mut tx = self
-            .store
-            .transaction()
-            .await
-            .map_err(|err| Error::DB(err.to_string()))?;
+        let mut tx = self.store.transaction().await.map_err(db_error)?;
+
+        // A competing block at the height of the tip replaces the indexed tip when both share
+        // the same parent. Blocks forking off deeper are refused, the sync loop rolls back the
+        // indexed chain to their fork point before appending them.
+        let block_hash = block.hash().raw_data().to_vec();
+        let fork_error = |tip_number: u64| {
+            Error::invalid_params(format!(
+                "block {:#x} #{} forks off below the indexed tip #{}",
+                block.hash(),
+                block.number(),
+                tip_number
+            ))
+        };
+        if let Some((tip_number, tip_hash)) = query_tip_number_and_hash(&mut tx).await? {
+            if tip_number == block.number() && tip_hash == block_hash {
+                // the block is already indexed
+                return Ok(());
+            }
+            if tip_number > block.number() {
+                return Err(fork_error(tip_number));
+            }
+            if tip_number == block.number() {
+                rollback_block(&mut tx).await?;
+                if let Some((_, parent_hash)) = query_tip_number_and_hash(&mut tx).await? {
+                    if parent_hash != block.parent_hash().raw_data().to_vec() {
+                        return Err(fork_error(tip_number));
+                    }
+                }
+            }
+        }
+
         if self.custom_filters.is_block_filter_match(block) {
+            self.load_out_point_filter(&mut tx).await?;
             let block_id = append_block(block, &mut tx).await?;
             self.insert_transactions(block_id, block, &mut tx).await?;
+            if self.config.pruned_mode {
+                prune_spent_outputs(u64::MAX, &mut tx).await?;
+            }
         } else {
             let block_headers = vec![(block.hash().raw_data().to_vec(), block.number() as i64)];
             bulk_insert_blocks_simple(block_headers, &mut tx).await?;
         }
-        tx.commit()
-            .await
-            .map_err(|err| Error::DB(err.to_string()))?;
+        tx.commit().await.map_err(db_error)?;
 
         if let Some(mut pool) = self.pool.as_ref().map(|p| p.write().expect("acquire lock")) {
             pool.transactions_committed(&block.transactions());
@@ -151,15 +219,11 @@ impl AsyncRichIndexer {
     }
 
     pub(crate) async fn rollback(&self) -> Result<(), Error> {
-        let mut tx = self
-            .store
-            .transaction()
-            .await
-            .map_err(|err| Error::DB(err.to_string()))?;
+        let mut tx = self.store.transaction().await.map_err(db_error)?;
 
         rollback_block(&mut tx).await?;
 
-        tx.commit().await.map_err(|err| Error::DB(err.to_string()))
+        tx.commit().await.map_err(db_error)
     }
 
     pub(crate) async fn insert_transactions(
@@ -182,27 +246,65 @@ impl AsyncRichIndexer {
         tx_view: TransactionView,
         tx: &mut Transaction<'_, Any>,
     ) -> Result<(), Error> {
+        self.check_transaction_size(&tx_view)?;
         let mut is_tx_matched = false;
         let mut output_cell_rows = Vec::new();
         let mut input_rows = Vec::new();
         let mut script_set = HashSet::new();
+        let mut custom_cell_field_rows = Vec::new();
 
         for (output_index, (cell, data)) in tx_view.outputs_with_data_iter().enumerate() {
+            if !self.check_output_capacity(&tx_view, output_index, &cell)? {
+                continue;
+            }
+            if self.is_ignored_cell(&cell) {
+                continue;
+            }
             if self
                 .custom_filters
                 .is_cell_filter_match(&cell, &data.pack())
             {
-                build_output_cell_rows(&cell, output_index, &data, &mut output_cell_rows);
+                build_output_cell_rows(
+                    &cell,
+                    output_index,
+                    &data,
+                    self.config.data_compression_threshold,
+                    self.config.data_prefix_len,
+                    &mut output_cell_rows,
+                );
+                self.insert_into_out_point_filter(&tx_view, output_index);
                 build_script_set(&cell, &mut script_set).await;
+                if let Some(schema) = self.custom_cell_schema(&cell) {
+                    build_custom_cell_field_rows(
+                        schema,
+                        output_index,
+                        &data,
+                        &mut custom_cell_field_rows,
+                    );
+                }
                 is_tx_matched = true;
             }
         }
 
-        if tx_index != 0 {
+        if !is_cellbase(tx_index) {
+            let out_points = self.filter_known_out_points(
+                tx_view
+                    .inputs()
+                    .into_iter()
+                    .map(|input| input.previous_output()),
+            );
+            bulk_spend_cells(&out_points, tx).await?;
+            let output_ids = if self.custom_filters.is_cell_filter_enabled() {
+                HashMap::new()
+            } else {
+                query_output_ids(&out_points, tx).await?
+            };
+            // the spent cells may not be indexed, e.g. outputs of an ignored script, which are
+            // skipped as their output can't be found
             for (input_index, input) in tx_view.inputs().into_iter().enumerate() {
                 let out_point = input.previous_output();
-                if !spend_cell(&out_point, tx).await? {
-                    break;
+                if !self.may_be_indexed(&out_point) {
+                    continue;
                 }
                 if self.custom_filters.is_cell_filter_enabled() {
                     if let Some((output_id, output, output_data)) =
@@ -216,7 +318,7 @@ impl AsyncRichIndexer {
                             is_tx_matched = true;
                         }
                     }
-                } else if let Some(output_id) = query_output_id(&out_point, tx).await? {
+                } else if let Some(&output_id) = output_ids.get(&out_point) {
                     build_input_rows(output_id, &input, input_index, &mut input_rows);
                     is_tx_matched = true;
                 }
@@ -231,13 +333,144 @@ impl AsyncRichIndexer {
             return Ok(());
         }
 
-        let tx_id = insert_transaction_table(block_id, tx_index, &tx_view, tx).await?;
+        let tx_id = insert_transaction_table(
+            block_id,
+            tx_index,
+            &tx_view,
+            self.config.index_witnesses,
+            tx,
+        )
+        .await?;
         bulk_insert_tx_association_header_dep_table(tx_id, &tx_view, tx).await?;
         bulk_insert_tx_association_cell_dep_table(tx_id, &tx_view, tx).await?;
 
         bulk_insert_input_table(tx_id, input_rows, tx).await?;
         bulk_insert_script_table(script_set, tx).await?;
-        bulk_insert_output_table(tx_id, output_cell_rows, tx).await
+        bulk_insert_output_table(tx_id, output_cell_rows, tx).await?;
+        bulk_insert_custom_cell_field_table(tx_id, custom_cell_field_rows, tx).await
+    }
+
+    /// Loads the out point filter from the indexed outputs, unless it is disabled or loaded.
+    async fn load_out_point_filter(&self, tx: &mut Transaction<'_, Any>) -> Result<(), Error> {
+        let (Some(filter), Some(capacity)) = (
+            self.out_point_filter.as_ref(),
+            self.config.out_point_filter_capacity,
+        ) else {
+            return Ok(());
+        };
+        if filter.read().expect("acquire lock").is_some() {
+            return Ok(());
+        }
+        let loaded = OutPointFilter::load(capacity, tx).await?;
+        *filter.write().expect("acquire lock") = Some(loaded);
+        Ok(())
+    }
+
+    fn insert_into_out_point_filter(&self, tx_view: &TransactionView, output_index: usize) {
+        if let Some(filter) = self.out_point_filter.as_ref() {
+            if let Some(filter) = filter.write().expect("acquire lock").as_mut() {
+                filter.insert(&tx_view.hash().raw_data(), output_index as u32);
+            }
+        }
+    }
+
+    /// Whether the out point may be the one of an indexed output, which is always the case
+    /// when the out point filter is disabled.
+    fn may_be_indexed(&self, out_point: &OutPoint) -> bool {
+        let Some(filter) = self.out_point_filter.as_ref() else {
+            return true;
+        };
+        match filter.read().expect("acquire lock").as_ref() {
+            Some(filter) => filter.may_contain(out_point),
+            None => true,
+        }
+    }
+
+    /// Drops the out points which are definitely not indexed, so that they are not looked up.
+    pub(crate) fn filter_known_out_points(
+        &self,
+        out_points: impl IntoIterator<Item = OutPoint>,
+    ) -> Vec<OutPoint> {
+        out_points
+            .into_iter()
+            .filter(|out_point| self.may_be_indexed(out_point))
+            .collect()
+    }
+
+    /// Returns the configured data schema of the cell, selected by its type script code hash.
+    fn custom_cell_schema(&self, cell: &CellOutput) -> Option<&CustomCellSchema> {
+        let type_script = cell.type_().to_opt()?;
+        let code_hash: H256 = type_script.code_hash().unpack();
+        self.config
+            .custom_cell_schemas
+            .iter()
+            .find(|schema| schema.code_hash == code_hash)
+    }
+
+    /// Whether the lock or type script code hash of the cell is configured to be ignored.
+    fn is_ignored_cell(&self, cell: &CellOutput) -> bool {
+        if self.config.ignored_code_hashes.is_empty() {
+            return false;
+        }
+        let lock_code_hash: H256 = cell.lock().code_hash().unpack();
+        let type_code_hash: Option<H256> = cell
+            .type_()
+            .to_opt()
+            .map(|type_script| type_script.code_hash().unpack());
+        self.config.ignored_code_hashes.iter().any(|code_hash| {
+            *code_hash == lock_code_hash || Some(code_hash) == type_code_hash.as_ref()
+        })
+    }
+
+    /// Checks the output capacity against the configured zero-capacity mode,
+    /// returns whether the output should be indexed.
+    fn check_output_capacity(
+        &self,
+        tx_view: &TransactionView,
+        output_index: usize,
+        cell: &CellOutput,
+    ) -> Result<bool, Error> {
+        let capacity: u64 = cell.capacity().unpack();
+        if capacity != 0 {
+            return Ok(true);
+        }
+        match self.config.zero_capacity_cells {
+            ZeroCapacityCells::Store => Ok(true),
+            ZeroCapacityCells::Skip => {
+                log::warn!(
+                    "skip zero-capacity output {:#x}:{}",
+                    tx_view.hash(),
+                    output_index
+                );
+                Ok(false)
+            }
+            ZeroCapacityCells::Reject => Err(Error::invalid_params(format!(
+                "zero-capacity output {:#x}:{}",
+                tx_view.hash(),
+                output_index
+            ))),
+        }
+    }
+
+    /// Checks the number of inputs and outputs of the transaction against the configured caps.
+    fn check_transaction_size(&self, tx_view: &TransactionView) -> Result<(), Error> {
+        let (inputs, outputs) = (tx_view.inputs().len(), tx_view.outputs().len());
+        if inputs <= self.config.max_tx_inputs && outputs <= self.config.max_tx_outputs {
+            return Ok(());
+        }
+        let message = format!(
+            "oversized transaction {:#x} with {} inputs and {} outputs",
+            tx_view.hash(),
+            inputs,
+            outputs
+        );
+        match self.config.oversized_transactions {
+            OversizedTransactions::Warn => {
+                log::warn!("{}", message);
+                Ok(())
+            }
+            OversizedTransactions::Reject => Err(Error::invalid_params(message)),
+        }
     }
 
     pub(crate) async fn set_init_tip(&self, init_tip_number: u64, init_tip_hash: &H256) {
@@ -246,7 +479,7 @@ impl AsyncRichIndexer {
             .store
             .transaction()
             .await
-            .map_err(|err| Error::DB(err.to_string()))
+            .map_err(db_error)
             .expect("set_init_tip create transaction should be OK");
         bulk_insert_blocks_simple(blocks_simple, &mut tx)
             .await
@@ -255,9 +488,77 @@ impl AsyncRichIndexer {
     }
 }
 
+/// Converts a database error into an indexer error, errors caused by a lost connection are
+/// reported as `Error::Connection` so that the sync loop can wait for the DB to come back.
+pub(crate) fn db_error<E: Into<anyhow::Error>>(err: E) -> Error {
+    let err = err.into();
+    let connection_lost = match err.downcast_ref::<sqlx::Error>() {
+        Some(
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed,
+        ) => true,
+        // PostgreSQL class 08 is "Connection Exception", 57P01 to 57P03 are raised while
+        // the server is shutting down or starting up.
+        Some(sqlx::Error::Database(db_err)) => db_err
+            .code()
+            .map(|code| code.starts_with("08") || code.starts_with("57P0"))
+            .unwrap_or(false),
+        _ => false,
+    };
+    if connection_lost {
+        Error::Connection(err.to_string())
+    } else {
+        Error::DB(err.to_string())
+    }
+}
+
+/// Reads a script code hash stored in the database, a stored value which isn't 32 bytes long
+/// means the database is corrupted.
+pub(crate) fn code_hash_from_db(code_hash: &[u8]) -> Result<[u8; 32], Error> {
+    code_hash.try_into().map_err(|_| {
+        Error::DB(format!(
+            "corrupted code_hash of {} bytes in the database, expected 32 bytes",
+            code_hash.len()
+        ))
+    })
+}
+
 pub(crate) fn to_fixed_array<const LEN: usize>(input: &[u8]) -> [u8; LEN] {
     assert_eq!(input.len(), LEN);
     let mut list = [0; LEN];
     list.copy_from_slice(input);
     list
 }
+
+/// Compresses cell data larger than the threshold, returns the data to store and whether
+/// it is compressed. Data which doesn't shrink is stored as is.
+pub(crate) fn compress_data(data: &[u8], threshold: Option<usize>) -> (Vec<u8>, bool) {
+    match threshold {
+        Some(threshold) if data.len() > threshold => {
+            match snap::raw::Encoder::new().compress_vec(data) {
+                Ok(compressed) if compressed.len() < data.len() => (compressed, true),
+                _ => (data.to_vec(), false),
+            }
+        }
+        _ => (data.to_vec(), false),
+    }
+}
+
+/// Restores cell data stored by `compress_data`, stored data which fails to decompress means
+/// the database is corrupted.
+pub(crate) fn decompress_data(data: Vec<u8>, compressed: bool) -> Result<Vec<u8>, Error> {
+    if !compressed {
+        return Ok(data);
+    }
+    snap::raw::Decoder::new()
+        .decompress_vec(&data)
+        .map_err(|err| {
+            Error::DB(format!(
+                "corrupted compressed cell data in the database: {}",
+                err
+            ))
+        })
+}
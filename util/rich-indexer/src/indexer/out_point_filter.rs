@@ -0,0 +1,107 @@
+use super::db_error;
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_types::{packed::OutPoint, prelude::*};
+use futures::TryStreamExt;
+use sqlx::{Any, Row, Transaction};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Bits per expected item, which gives a false positive rate of about 1% with
+/// `NUM_HASHES` hash functions.
+const BITS_PER_ITEM: usize = 10;
+const NUM_HASHES: u64 = 7;
+
+/// Bloom filter of the out points of the indexed outputs.
+///
+/// An out point which is not in the filter is definitely not indexed, while one which is may
+/// still be unknown, e.g. after a rollback, so a hit has to be confirmed by the database.
+pub(crate) struct OutPointFilter {
+    bits: Vec<u64>,
+}
+
+impl OutPointFilter {
+    /// Creates an empty filter sized for `capacity` out points.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        let num_words = (capacity.max(1) * BITS_PER_ITEM).div_ceil(64);
+        OutPointFilter {
+            bits: vec![0; num_words],
+        }
+    }
+
+    /// Creates a filter holding the out points of all the indexed outputs.
+    pub(crate) async fn load(
+        capacity: usize,
+        tx: &mut Transaction<'_, Any>,
+    ) -> Result<Self, Error> {
+        let mut filter = Self::with_capacity(capacity);
+        let mut rows = SQLXPool::new_query(
+            r#"
+            SELECT ckb_transaction.tx_hash, output.output_index
+            FROM output
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            "#,
+        )
+        .fetch(tx.as_mut());
+        while let Some(row) = rows.try_next().await.map_err(db_error)? {
+            filter.insert(
+                &row.get::<Vec<u8>, _>("tx_hash"),
+                row.get::<i32, _>("output_index") as u32,
+            );
+        }
+        Ok(filter)
+    }
+
+    pub(crate) fn insert(&mut self, tx_hash: &[u8], index: u32) {
+        for bit in self.bit_indexes(tx_hash, index) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    pub(crate) fn may_contain(&self, out_point: &OutPoint) -> bool {
+        let index: u32 = out_point.index().unpack();
+        self.bit_indexes(&out_point.tx_hash().raw_data(), index)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Derives the bits of an out point from a single hash, by double hashing.
+    fn bit_indexes(&self, tx_hash: &[u8], index: u32) -> impl Iterator<Item = u64> {
+        let mut hasher = DefaultHasher::new();
+        tx_hash.hash(&mut hasher);
+        index.hash(&mut hasher);
+        let hash = hasher.finish();
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let num_bits = self.bits.len() as u64 * 64;
+        (0..NUM_HASHES).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ckb_types::packed::Byte32;
+
+    fn out_point(seed: u32, index: u32) -> OutPoint {
+        let mut tx_hash = [0u8; 32];
+        tx_hash[..4].copy_from_slice(&seed.to_le_bytes());
+        OutPoint::new(Byte32::new(tx_hash), index)
+    }
+
+    #[test]
+    fn no_false_negatives() {
+        let mut filter = OutPointFilter::with_capacity(1_000);
+        for seed in 0..1_000 {
+            let out_point = out_point(seed, seed % 3);
+            filter.insert(&out_point.tx_hash().raw_data(), seed % 3);
+        }
+        assert!((0..1_000).all(|seed| filter.may_contain(&out_point(seed, seed % 3))));
+
+        let false_positives = (1_000..11_000)
+            .filter(|seed| filter.may_contain(&out_point(*seed, 0)))
+            .count();
+        assert!(false_positives < 500, "{false_positives} false positives");
+    }
+}
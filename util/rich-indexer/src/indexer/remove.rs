@@ -4,6 +4,8 @@ use ckb_indexer_sync::Error;
 use sql_builder::SqlBuilder;
 use sqlx::{Any, Row, Transaction};
 
+use std::collections::HashSet;
+
 pub(crate) async fn rollback_block(tx: &mut Transaction<'_, Any>) -> Result<(), Error> {
     let block_id = if let Some(block_id) = query_tip_id(tx).await? {
         block_id
@@ -23,6 +25,8 @@ pub(crate) async fn rollback_block(tx: &mut Transaction<'_, Any>) -> Result<(),
     remove_batch_by_blobs("tx_association_header_dep", "tx_id", &tx_id_list, tx).await?;
     remove_batch_by_blobs("input", "consumed_tx_id", &tx_id_list, tx).await?;
     remove_batch_by_blobs("output", "tx_id", &tx_id_list, tx).await?;
+    let output_id_list: Vec<i64> = output_lock_type_list.iter().map(|(id, _, _)| *id).collect();
+    remove_batch_by_blobs("custom_cell_field", "output_id", &output_id_list, tx).await?;
 
     // remove script
     let mut script_id_list_to_remove = Vec::new();
@@ -43,6 +47,7 @@ pub(crate) async fn rollback_block(tx: &mut Transaction<'_, Any>) -> Result<(),
     remove_batch_by_blobs("block", "id", &[block_id], tx).await?;
     remove_batch_by_blobs("block_association_proposal", "block_id", &[block_id], tx).await?;
     remove_batch_by_blobs("block_association_uncle", "block_id", &[block_id], tx).await?;
+    remove_batch_by_blobs("block_commitment", "block_id", &[block_id], tx).await?;
 
     // remove uncles
     remove_batch_by_blobs("block", "id", &uncle_id_list, tx).await?;
@@ -51,38 +56,221 @@ pub(crate) async fn rollback_block(tx: &mut Transaction<'_, Any>) -> Result<(),
     Ok(())
 }
 
+/// Removes the block with `block_hash` together with every block indexed on top of it,
+/// newest first, so the cells spent by the removed transactions are live again. Nothing is
+/// removed when the hash is unknown or belongs to an uncle.
+pub(crate) async fn delete_block(
+    block_hash: &[u8],
+    tx: &mut Transaction<'_, Any>,
+) -> Result<(), Error> {
+    let block_number = match SQLXPool::new_query(
+        r#"
+        SELECT block_number FROM block
+        WHERE
+            block_hash = $1
+            AND NOT EXISTS (
+                SELECT 1 FROM block_association_uncle
+                WHERE block_association_uncle.uncle_id = block.id
+            )
+        "#,
+    )
+    .bind(block_hash)
+    .fetch_optional(tx.as_mut())
+    .await
+    .map_err(db_error)?
+    {
+        Some(row) => row.get::<i64, _>("block_number") as u64,
+        None => return Ok(()),
+    };
+
+    while let Some((tip_number, _)) = query_tip_number_and_hash(tx).await? {
+        if tip_number < block_number {
+            break;
+        }
+        rollback_block(tx).await?;
+    }
+    Ok(())
+}
+
+/// Deletes the cells spent in the blocks below `before_block_number`, cascading to their
+/// custom fields and cell deps, the transactions left without cells and the scripts no longer
+/// used. The threshold is capped at `PRUNED_MODE_KEEP_NUM` blocks below the tip, so that those
+/// blocks can still be rolled back. Returns the number of deleted cells.
+///
+/// Only the cells whose spending input is recorded are deleted, so live cells are never
+/// removed, even if their spent flag is stale. The cells are deleted in pages of
+/// `BATCH_SIZE_THRESHOLD` by input id; as the inputs of the pruned cells are deleted too, the
+/// pages of a regular pruned-mode append only cover the inputs of the kept blocks.
+pub(crate) async fn prune_spent_outputs(
+    before_block_number: u64,
+    tx: &mut Transaction<'_, Any>,
+) -> Result<u64, Error> {
+    let tip_number = match query_tip_number_and_hash(tx).await? {
+        Some((tip_number, _)) if tip_number > PRUNED_MODE_KEEP_NUM => tip_number,
+        _ => return Ok(0),
+    };
+    let prune_to_block = before_block_number.min(tip_number - PRUNED_MODE_KEEP_NUM) as i64;
+
+    let mut pruned = 0;
+    let mut last_output_id = -1;
+    loop {
+        let spent_rows = SQLXPool::new_query(
+            r#"
+            SELECT
+                input.output_id,
+                input.consumed_tx_id,
+                output.tx_id,
+                output.lock_script_id,
+                output.type_script_id
+            FROM
+                input
+            JOIN output ON input.output_id = output.id
+            JOIN ckb_transaction ON input.consumed_tx_id = ckb_transaction.id
+            JOIN block ON ckb_transaction.block_id = block.id
+            WHERE
+                block.block_number < $1
+                AND input.output_id > $2
+            ORDER BY input.output_id
+            LIMIT $3
+            "#,
+        )
+        .bind(prune_to_block)
+        .bind(last_output_id)
+        .bind(BATCH_SIZE_THRESHOLD as i64)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(db_error)?;
+
+        let mut output_id_list = Vec::with_capacity(spent_rows.len());
+        let mut script_id_set = HashSet::new();
+        let mut tx_id_set = HashSet::new();
+        for row in &spent_rows {
+            output_id_list.push(row.get::<i64, _>("output_id"));
+            tx_id_set.insert(row.get::<i64, _>("consumed_tx_id"));
+            tx_id_set.insert(row.get::<i64, _>("tx_id"));
+            script_id_set.insert(row.get::<i64, _>("lock_script_id"));
+            if let Some(type_script_id) = row.get::<Option<i64>, _>("type_script_id") {
+                script_id_set.insert(type_script_id);
+            }
+        }
+        let last = match output_id_list.last() {
+            Some(last) => *last,
+            None => break,
+        };
+
+        // remove spent cells
+        remove_batch_by_blobs("input", "output_id", &output_id_list, tx).await?;
+        remove_batch_by_blobs("output", "id", &output_id_list, tx).await?;
+        remove_batch_by_blobs("custom_cell_field", "output_id", &output_id_list, tx).await?;
+        remove_batch_by_blobs("tx_association_cell_dep", "output_id", &output_id_list, tx).await?;
+
+        // remove transactions left without cells
+        let tx_id_list: Vec<i64> = tx_id_set.into_iter().collect();
+        let tx_id_list_to_remove = remove_unreferenced_by_ids(
+            "ckb_transaction",
+            &tx_id_list,
+            &[("output", "tx_id"), ("input", "consumed_tx_id")],
+            tx,
+        )
+        .await?;
+        remove_batch_by_blobs(
+            "tx_association_cell_dep",
+            "tx_id",
+            &tx_id_list_to_remove,
+            tx,
+        )
+        .await?;
+        remove_batch_by_blobs(
+            "tx_association_header_dep",
+            "tx_id",
+            &tx_id_list_to_remove,
+            tx,
+        )
+        .await?;
+
+        // remove script
+        let script_id_list: Vec<i64> = script_id_set.into_iter().collect();
+        remove_unreferenced_by_ids(
+            "script",
+            &script_id_list,
+            &[("output", "lock_script_id"), ("output", "type_script_id")],
+            tx,
+        )
+        .await?;
+
+        pruned += output_id_list.len() as u64;
+        if output_id_list.len() < BATCH_SIZE_THRESHOLD {
+            break;
+        }
+        last_output_id = last;
+    }
+
+    Ok(pruned)
+}
+
 async fn remove_batch_by_blobs(
     table_name: &str,
     column_name: &str,
     ids: &[i64],
     tx: &mut Transaction<'_, Any>,
 ) -> Result<(), Error> {
-    if ids.is_empty() {
-        return Ok(());
-    }
+    for chunk in ids.chunks(BATCH_SIZE_THRESHOLD) {
+        // build query str
+        let mut query_builder = SqlBuilder::delete_from(table_name);
+        let sql = query_builder
+            .and_where_in(column_name, &sqlx_param_placeholders(1..chunk.len())?)
+            .sql()
+            .map_err(db_error)?;
 
-    // build query str
-    let mut query_builder = SqlBuilder::delete_from(table_name);
-    let sql = query_builder
-        .and_where_in(column_name, &sqlx_param_placeholders(1..ids.len())?)
-        .sql()
-        .map_err(|err| Error::DB(err.to_string()))?;
+        // bind
+        let mut query: sqlx::query::Query<'_, Any, sqlx::any::AnyArguments<'_>> = sqlx::query(&sql);
+        for hash in chunk {
+            query = query.bind(hash);
+        }
 
-    // bind
-    let mut query: sqlx::query::Query<'_, Any, sqlx::any::AnyArguments<'_>> = sqlx::query(&sql);
-    for hash in ids {
-        query = query.bind(hash);
+        // execute
+        query.execute(tx.as_mut()).await.map_err(db_error)?;
     }
 
-    // execute
-    query
-        .execute(tx.as_mut())
-        .await
-        .map_err(|err| Error::DB(err.to_string()))?;
-
     Ok(())
 }
 
+/// Deletes the rows of `table_name` among `ids` which no row of the `(table, column)` pairs of
+/// `references` points to, in chunks of `BATCH_SIZE_THRESHOLD` ids. Returns the ids of the
+/// deleted rows.
+async fn remove_unreferenced_by_ids(
+    table_name: &str,
+    ids: &[i64],
+    references: &[(&str, &str)],
+    tx: &mut Transaction<'_, Any>,
+) -> Result<Vec<i64>, Error> {
+    let mut removed = Vec::new();
+    for chunk in ids.chunks(BATCH_SIZE_THRESHOLD) {
+        // build query str
+        let mut query_builder = SqlBuilder::delete_from(table_name);
+        query_builder.and_where_in("id", &sqlx_param_placeholders(1..chunk.len())?);
+        for (reference_table, reference_column) in references {
+            query_builder.and_where(format!(
+                "NOT EXISTS (SELECT 1 FROM {0} WHERE {0}.{1} = {2}.id)",
+                reference_table, reference_column, table_name
+            ));
+        }
+        let sql = query_builder.sql().map_err(db_error)?;
+        let sql = format!("{} RETURNING id", sql.trim_end_matches(';'));
+
+        // bind
+        let mut query = SQLXPool::new_query(&sql);
+        for id in chunk {
+            query = query.bind(id);
+        }
+
+        // execute
+        let rows = query.fetch_all(tx.as_mut()).await.map_err(db_error)?;
+        removed.extend(rows.iter().map(|row| row.get::<i64, _>("id")));
+    }
+    Ok(removed)
+}
+
 async fn reset_spent_cells(tx_id_list: &[i64], tx: &mut Transaction<'_, Any>) -> Result<(), Error> {
     let query = SqlBuilder::update_table("output")
         .set("is_spent", 0)
@@ -92,15 +280,15 @@ async fn reset_spent_cells(tx_id_list: &[i64], tx: &mut Transaction<'_, Any>) ->
                 .field("output_id")
                 .and_where_in("consumed_tx_id", tx_id_list)
                 .query()
-                .map_err(|err| Error::DB(err.to_string()))?,
+                .map_err(db_error)?,
         )
         .sql()
-        .map_err(|err| Error::DB(err.to_string()))?;
+        .map_err(db_error)?;
 
     sqlx::query(&query)
         .execute(tx.as_mut())
         .await
-        .map_err(|err| Error::DB(err.to_string()))?;
+        .map_err(db_error)?;
 
     Ok(())
 }
@@ -120,7 +308,30 @@ async fn query_uncle_id_list_by_block_id(
     .fetch_all(tx.as_mut())
     .await
     .map(|rows| rows.into_iter().map(|row| row.get("uncle_id")).collect())
-    .map_err(|err| Error::DB(err.to_string()))
+    .map_err(db_error)
+}
+
+pub(crate) async fn query_tip_number_and_hash(
+    tx: &mut Transaction<'_, Any>,
+) -> Result<Option<(u64, Vec<u8>)>, Error> {
+    SQLXPool::new_query(
+        r#"
+            SELECT block_number, block_hash FROM block
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+    )
+    .fetch_optional(tx.as_mut())
+    .await
+    .map(|res| {
+        res.map(|row| {
+            (
+                row.get::<i64, _>("block_number") as u64,
+                row.get::<Vec<u8>, _>("block_hash"),
+            )
+        })
+    })
+    .map_err(db_error)
 }
 
 async fn query_tip_id(tx: &mut Transaction<'_, Any>) -> Result<Option<i64>, Error> {
@@ -134,7 +345,7 @@ async fn query_tip_id(tx: &mut Transaction<'_, Any>) -> Result<Option<i64>, Erro
     .fetch_optional(tx.as_mut())
     .await
     .map(|res| res.map(|row| row.get::<i64, _>("id")))
-    .map_err(|err| Error::DB(err.to_string()))
+    .map_err(db_error)
 }
 
 async fn query_tx_id_list_by_block_id(
@@ -157,7 +368,7 @@ async fn query_tx_id_list_by_block_id(
             .map(|row| row.get::<i64, _>("id"))
             .collect()
     })
-    .map_err(|err| Error::DB(err.to_string()))
+    .map_err(db_error)
 }
 
 async fn query_outputs_by_tx_id_list(
@@ -175,7 +386,7 @@ async fn query_outputs_by_tx_id_list(
         .and_where_in("tx_id", &sqlx_param_placeholders(1..tx_id_list.len())?)
         .order_by("output_index", false)
         .sql()
-        .map_err(|err| Error::DB(err.to_string()))?;
+        .map_err(db_error)?;
 
     // bind
     let mut query = SQLXPool::new_query(&sql);
@@ -187,7 +398,7 @@ async fn query_outputs_by_tx_id_list(
     query
         .fetch_all(tx.as_mut())
         .await
-        .map_err(|err| Error::DB(err.to_string()))
+        .map_err(db_error)
         .map(|rows| {
             rows.iter()
                 .map(|row| {
@@ -217,7 +428,7 @@ async fn script_exists_in_output(
     .bind(script_id)
     .fetch_one(tx.as_mut())
     .await
-    .map_err(|err| Error::DB(err.to_string()))?;
+    .map_err(db_error)?;
 
     if row_lock.get::<i64, _>(0) == 1 {
         return Ok(true);
@@ -235,7 +446,7 @@ async fn script_exists_in_output(
     .bind(script_id)
     .fetch_one(tx.as_mut())
     .await
-    .map_err(|err| Error::DB(err.to_string()))?;
+    .map_err(db_error)?;
 
     Ok(row_type.get::<i64, _>(0) == 1)
 }
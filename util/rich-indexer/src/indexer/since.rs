@@ -0,0 +1,104 @@
+//! Decoding of the `since` field of inputs, see
+//! [RFC 0017](https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0017-tx-valid-since/0017-tx-valid-since.md).
+
+use ckb_types::core::EpochNumberWithFraction;
+
+const LOCK_TYPE_FLAG: u64 = 1 << 63;
+const METRIC_TYPE_FLAG_MASK: u64 = 0x6000_0000_0000_0000;
+const VALUE_MASK: u64 = 0x00ff_ffff_ffff_ffff;
+const REMAIN_FLAGS_BITS: u64 = 0x1f00_0000_0000_0000;
+
+/// The metric of a since lock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SinceMetric {
+    BlockNumber(u64),
+    Epoch(EpochNumberWithFraction),
+    /// Median block timestamp, in seconds.
+    Timestamp(u64),
+}
+
+/// A decoded since lock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SinceLock {
+    pub(crate) relative: bool,
+    pub(crate) metric: SinceMetric,
+}
+
+impl SinceLock {
+    /// Decodes the since of an input, `None` if it has no lock, i.e. is 0, or if its flags
+    /// are invalid.
+    pub(crate) fn decode(since: u64) -> Option<Self> {
+        if since == 0
+            || since & REMAIN_FLAGS_BITS != 0
+            || since & METRIC_TYPE_FLAG_MASK == METRIC_TYPE_FLAG_MASK
+        {
+            return None;
+        }
+        let value = since & VALUE_MASK;
+        let metric = match since & METRIC_TYPE_FLAG_MASK {
+            0x0000_0000_0000_0000 => SinceMetric::BlockNumber(value),
+            0x2000_0000_0000_0000 => {
+                SinceMetric::Epoch(EpochNumberWithFraction::from_full_value_unchecked(value))
+            }
+            _ => SinceMetric::Timestamp(value),
+        };
+        Some(SinceLock {
+            relative: since & LOCK_TYPE_FLAG != 0,
+            metric,
+        })
+    }
+
+    /// Returns the `since_flag`, `since_type` and `since_value` columns of the input table.
+    ///
+    /// The flag is 0 for an absolute lock and 1 for a relative one. The type is 0 for a block
+    /// number, 1 for an epoch, whose value is the full value of `EpochNumberWithFraction`, and
+    /// 2 for a timestamp in seconds.
+    pub(crate) fn to_columns(self) -> (i16, i16, i64) {
+        let (since_type, since_value) = match self.metric {
+            SinceMetric::BlockNumber(number) => (0, number),
+            SinceMetric::Epoch(epoch) => (1, epoch.full_value()),
+            SinceMetric::Timestamp(timestamp) => (2, timestamp),
+        };
+        (self.relative as i16, since_type, since_value as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_since() {
+        assert_eq!(None, SinceLock::decode(0));
+        // the metric flag 0b11 and the reserved bits are invalid
+        assert_eq!(None, SinceLock::decode(0x6000_0000_0000_0001));
+        assert_eq!(None, SinceLock::decode(0x0100_0000_0000_0001));
+
+        let absolute_block = SinceLock::decode(1_000).unwrap();
+        assert_eq!(
+            SinceLock {
+                relative: false,
+                metric: SinceMetric::BlockNumber(1_000),
+            },
+            absolute_block
+        );
+        assert_eq!((0, 0, 1_000), absolute_block.to_columns());
+
+        let epoch = EpochNumberWithFraction::new(6, 1, 2);
+        let relative_epoch = SinceLock::decode(0xa000_0000_0000_0000 | epoch.full_value()).unwrap();
+        assert_eq!(
+            SinceLock {
+                relative: true,
+                metric: SinceMetric::Epoch(epoch),
+            },
+            relative_epoch
+        );
+        assert_eq!(
+            (1, 1, epoch.full_value() as i64),
+            relative_epoch.to_columns()
+        );
+
+        let absolute_timestamp = SinceLock::decode(0x4000_0000_6000_0000).unwrap();
+        assert_eq!((0, 2, 0x6000_0000), absolute_timestamp.to_columns());
+    }
+}
@@ -0,0 +1,149 @@
+use super::*;
+
+use crate::indexer::{code_hash_from_db, decompress_data, is_data_compressed};
+use crate::store::SQLXPool;
+
+use anyhow::anyhow;
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{Capacity, OutPoint, Script, Uint128};
+use ckb_types::packed::{OutPointBuilder, ScriptBuilder};
+use ckb_types::prelude::*;
+use serde::Serialize;
+use sqlx::{any::AnyRow, Row};
+
+use std::io::Write;
+
+/// The file format of the exported live cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per line.
+    JsonLines,
+    /// Comma-separated values with a header line.
+    Csv,
+}
+
+const CSV_HEADER: &str = "tx_hash,index,lock_code_hash,lock_hash_type,lock_args,capacity,amount";
+
+/// A live cell as it is written to the export.
+#[derive(Serialize)]
+struct ExportedCell {
+    out_point: OutPoint,
+    lock: Script,
+    capacity: Capacity,
+    /// The UDT amount, if the cell data is long enough to carry one.
+    amount: Option<Uint128>,
+}
+
+impl AsyncRichIndexerHandle {
+    /// Export all live cells of a type script to `writer`.
+    ///
+    /// The rows are streamed from the database and written one by one, so the memory usage
+    /// does not grow with the number of cells. The `amount` field is the little-endian `u128`
+    /// in the first 16 bytes of the cell data, as UDT cells store it. Returns the number of
+    /// exported cells.
+    pub async fn export_live_cells<W: Write>(
+        &self,
+        type_script: Script,
+        format: ExportFormat,
+        writer: &mut W,
+    ) -> Result<u64, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                ckb_transaction.tx_hash,
+                output.output_index,
+                output.capacity,
+                lock_script.code_hash AS lock_code_hash,
+                lock_script.hash_type AS lock_hash_type,
+                lock_script.args AS lock_args,
+                output.data,
+                output.data_compressed
+            FROM
+                output
+            JOIN script AS type_script ON output.type_script_id = type_script.id
+            JOIN script AS lock_script ON output.lock_script_id = lock_script.id
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            WHERE
+                type_script.code_hash = $1
+                AND type_script.hash_type = $2
+                AND type_script.args = $3
+                AND output.is_spent = 0
+            ORDER BY output.id
+            "#,
+        )
+        .bind(type_script.code_hash.as_bytes().to_vec())
+        .bind(type_script.hash_type as i16)
+        .bind(type_script.args.as_bytes().to_vec());
+
+        if format == ExportFormat::Csv {
+            writeln!(writer, "{}", CSV_HEADER).map_err(|err| Error::DB(err.to_string()))?;
+        }
+        let mut count = 0;
+        self.store
+            .fetch_try_for_each(query, |row| {
+                let cell = build_exported_cell(row)?;
+                match format {
+                    ExportFormat::JsonLines => {
+                        serde_json::to_writer(&mut *writer, &cell)?;
+                        writeln!(writer)?;
+                    }
+                    ExportFormat::Csv => write_csv_line(writer, &cell)?,
+                }
+                count += 1;
+                Ok(())
+            })
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?;
+        writer.flush().map_err(|err| Error::DB(err.to_string()))?;
+        Ok(count)
+    }
+}
+
+fn build_exported_cell(row: &AnyRow) -> Result<ExportedCell, Error> {
+    let out_point = OutPointBuilder::default()
+        .tx_hash(to_fixed_array::<32>(&row.get::<Vec<u8>, _>("tx_hash")).pack())
+        .index((row.get::<i32, _>("output_index") as u32).pack())
+        .build();
+    let lock = ScriptBuilder::default()
+        .code_hash(code_hash_from_db(&row.get::<Vec<u8>, _>("lock_code_hash"))?.pack())
+        .hash_type((row.get::<i16, _>("lock_hash_type") as u8).into())
+        .args(
+            row.get::<Option<Vec<u8>>, _>("lock_args")
+                .unwrap_or_default()
+                .pack(),
+        )
+        .build();
+    let data = decompress_data(
+        row.get::<Option<Vec<u8>>, _>("data").unwrap_or_default(),
+        is_data_compressed(row),
+    )?;
+    let amount = (data.len() >= 16).then(|| u128::from_le_bytes(to_fixed_array(&data[0..16])));
+
+    Ok(ExportedCell {
+        out_point: out_point.into(),
+        lock: lock.into(),
+        capacity: (row.get::<i64, _>("capacity") as u64).into(),
+        amount: amount.map(Into::into),
+    })
+}
+
+fn write_csv_line<W: Write>(writer: &mut W, cell: &ExportedCell) -> anyhow::Result<()> {
+    let hash_type: u8 = ckb_types::core::ScriptHashType::from(cell.lock.hash_type.clone()).into();
+    // the args are serialized as a 0x-prefixed hex string
+    let args = serde_json::to_value(&cell.lock.args)?;
+    writeln!(
+        writer,
+        "{:#x},{},{:#x},{},{},{},{}",
+        cell.out_point.tx_hash,
+        cell.out_point.index.value(),
+        cell.lock.code_hash,
+        hash_type,
+        args.as_str().unwrap_or_default(),
+        cell.capacity.value(),
+        cell.amount
+            .as_ref()
+            .map(|amount| amount.value().to_string())
+            .unwrap_or_default(),
+    )
+    .map_err(|err| anyhow!(err))
+}
@@ -0,0 +1,74 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::OutPoint;
+use sqlx::Row;
+
+use std::collections::HashSet;
+
+impl AsyncRichIndexerHandle {
+    /// Filter the out points down to the ones referring to live cells, in the order given.
+    ///
+    /// Out points which are spent or not indexed are left out.
+    pub async fn filter_live_out_points(
+        &self,
+        out_points: Vec<OutPoint>,
+    ) -> Result<Vec<OutPoint>, Error> {
+        if out_points.len() > self.request_limit {
+            return Err(Error::invalid_params(format!(
+                "number of out points must be less than or equal to {}",
+                self.request_limit
+            )));
+        }
+        if out_points.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let placeholders = (0..out_points.len())
+            .map(|i| format!("(${}, ${})", 2 * i + 1, 2 * i + 2))
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            r#"
+            SELECT
+                ckb_transaction.tx_hash,
+                output.output_index
+            FROM
+                output
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            WHERE
+                output.is_spent = 0
+                AND (ckb_transaction.tx_hash, output.output_index) IN ({})
+            "#,
+            placeholders
+        );
+        let mut query = SQLXPool::new_query(&sql);
+        for out_point in &out_points {
+            query = query
+                .bind(out_point.tx_hash.as_bytes().to_vec())
+                .bind(out_point.index.value() as i32);
+        }
+
+        let live = self
+            .store
+            .fetch_all(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?
+            .iter()
+            .map(|row| {
+                (
+                    bytes_to_h256(&row.get::<Vec<u8>, _>("tx_hash")),
+                    row.get::<i32, _>("output_index") as u32,
+                )
+            })
+            .collect::<HashSet<_>>();
+        Ok(out_points
+            .into_iter()
+            .filter(|out_point| {
+                live.contains(&(out_point.tx_hash.clone(), out_point.index.value()))
+            })
+            .collect())
+    }
+}
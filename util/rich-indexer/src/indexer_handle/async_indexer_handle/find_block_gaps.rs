@@ -0,0 +1,60 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::BlockNumber;
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Find the ranges of block numbers missing below the indexer tip, each reported as
+    /// [from, to). Uncle blocks are stored in the block table too, they don't fill a gap.
+    pub async fn find_block_gaps(&self) -> Result<Vec<(BlockNumber, BlockNumber)>, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            WITH canonical AS (
+                SELECT
+                    block_number
+                FROM
+                    block
+                WHERE
+                    NOT EXISTS (
+                        SELECT 1 FROM block_association_uncle
+                        WHERE block_association_uncle.uncle_id = block.id
+                    )
+            )
+            SELECT
+                cur_block.block_number + 1 AS gap_from,
+                (
+                    SELECT MIN(next_block.block_number) FROM canonical AS next_block
+                    WHERE next_block.block_number > cur_block.block_number
+                ) AS gap_to
+            FROM
+                canonical AS cur_block
+            WHERE
+                NOT EXISTS (
+                    SELECT 1 FROM canonical AS next_block
+                    WHERE next_block.block_number = cur_block.block_number + 1
+                )
+                AND cur_block.block_number < (SELECT MAX(block_number) FROM canonical)
+            ORDER BY
+                gap_from
+            "#,
+        );
+
+        self.store
+            .fetch_all(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+            .map(|rows| {
+                rows.iter()
+                    .map(|row| {
+                        (
+                            (row.get::<i64, _>("gap_from") as u64).into(),
+                            (row.get::<i64, _>("gap_to") as u64).into(),
+                        )
+                    })
+                    .collect()
+            })
+    }
+}
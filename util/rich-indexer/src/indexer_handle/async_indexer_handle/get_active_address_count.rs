@@ -0,0 +1,65 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{BlockNumber, Uint64};
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the number of distinct lock scripts which created or consumed a cell in the block
+    /// range [from, to).
+    pub async fn get_active_address_count(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Uint64, Error> {
+        let from: u64 = from.into();
+        let to: u64 = to.into();
+        if from >= to {
+            return Err(Error::invalid_params("from should be less than to"));
+        }
+        if from > i64::MAX as u64 {
+            return Ok(0u64.into());
+        }
+        let from = from as i64;
+        let to = to.min(i64::MAX as u64) as i64;
+
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                COUNT(DISTINCT lock_script_id) AS count
+            FROM (
+                SELECT
+                    output.lock_script_id
+                FROM
+                    output
+                JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+                JOIN block ON ckb_transaction.block_id = block.id
+                WHERE
+                    block.block_number >= $1 AND block.block_number < $2
+                UNION ALL
+                SELECT
+                    output.lock_script_id
+                FROM
+                    input
+                JOIN output ON input.output_id = output.id
+                JOIN ckb_transaction ON input.consumed_tx_id = ckb_transaction.id
+                JOIN block ON ckb_transaction.block_id = block.id
+                WHERE
+                    block.block_number >= $3 AND block.block_number < $4
+            ) AS active_lock
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(from)
+        .bind(to);
+
+        self.store
+            .fetch_one(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+            .map(|row| (row.get::<i64, _>("count") as u64).into())
+    }
+}
@@ -0,0 +1,73 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{IndexerAddressActivity, Script};
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the first and the last block in which the lock script was active, that is a cell
+    /// under the lock was created or consumed. Returns `None` if the lock has no activity.
+    pub async fn get_address_activity(
+        &self,
+        lock_script: Script,
+    ) -> Result<Option<IndexerAddressActivity>, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                MIN(block_number) AS first_block,
+                MAX(block_number) AS last_block
+            FROM (
+                SELECT
+                    block.block_number
+                FROM
+                    output
+                JOIN script ON output.lock_script_id = script.id
+                JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+                JOIN block ON ckb_transaction.block_id = block.id
+                WHERE
+                    script.code_hash = $1
+                    AND script.hash_type = $2
+                    AND script.args = $3
+                UNION ALL
+                SELECT
+                    block.block_number
+                FROM
+                    input
+                JOIN output ON input.output_id = output.id
+                JOIN script ON output.lock_script_id = script.id
+                JOIN ckb_transaction ON input.consumed_tx_id = ckb_transaction.id
+                JOIN block ON ckb_transaction.block_id = block.id
+                WHERE
+                    script.code_hash = $4
+                    AND script.hash_type = $5
+                    AND script.args = $6
+            ) AS activity
+            "#,
+        );
+        let code_hash = lock_script.code_hash.as_bytes().to_vec();
+        let hash_type = lock_script.hash_type as i16;
+        let args = lock_script.args.as_bytes().to_vec();
+        let query = query
+            .bind(code_hash.clone())
+            .bind(hash_type)
+            .bind(args.clone())
+            .bind(code_hash)
+            .bind(hash_type)
+            .bind(args);
+
+        self.store
+            .fetch_one(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+            .map(|row| {
+                let first_block = row.get::<Option<i64>, _>("first_block")?;
+                let last_block = row.get::<Option<i64>, _>("last_block")?;
+                Some(IndexerAddressActivity {
+                    first_block: (first_block as u64).into(),
+                    last_block: (last_block as u64).into(),
+                })
+            })
+    }
+}
@@ -0,0 +1,98 @@
+use super::*;
+
+use crate::indexer::code_hash_from_db;
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{IndexerAssetType, Script};
+use ckb_types::{packed, prelude::*};
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the number of live cells of a lock script per type script code hash.
+    ///
+    /// Cells without a type script are counted under a `None` code hash, which comes first.
+    pub async fn get_asset_types_for_lock(
+        &self,
+        lock: Script,
+    ) -> Result<Vec<IndexerAssetType>, Error> {
+        let lock_hash: H256 = packed::Script::from(lock).calc_script_hash().unpack();
+
+        let mut param_index = 2;
+        let mut query_builder = SqlBuilder::select_from("output");
+        query_builder
+            .field("type_script.code_hash AS type_code_hash")
+            .field("COUNT(*) AS cell_count")
+            .join("script AS lock_script")
+            .on("output.lock_script_id = lock_script.id")
+            .left()
+            .join("script AS type_script")
+            .on("output.type_script_id = type_script.id")
+            .join("ckb_transaction")
+            .on("output.tx_id = ckb_transaction.id")
+            .and_where("lock_script.script_hash = $1")
+            .and_where("output.is_spent = 0"); // live cells
+
+        // filter cells in pool
+        let mut dead_cells = Vec::new();
+        if let Some(pool) = self
+            .pool
+            .as_ref()
+            .map(|pool| pool.read().expect("acquire lock"))
+        {
+            dead_cells = pool
+                .dead_cells()
+                .map(|out_point| {
+                    let tx_hash: H256 = out_point.tx_hash().unpack();
+                    (tx_hash.as_bytes().to_vec(), out_point.index().unpack())
+                })
+                .collect::<Vec<(_, u32)>>()
+        }
+        if !dead_cells.is_empty() {
+            let placeholders = dead_cells
+                .iter()
+                .map(|(_, output_index)| {
+                    let placeholder = format!("(${}, {})", param_index, output_index);
+                    param_index += 1;
+                    placeholder
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            query_builder.and_where(format!(
+                "(ckb_transaction.tx_hash, output_index) NOT IN ({})",
+                placeholders
+            ));
+        }
+        query_builder.group_by("type_script.code_hash");
+
+        let sql = query_builder
+            .sql()
+            .map_err(|err| Error::DB(err.to_string()))?
+            .trim_end_matches(';')
+            .to_string();
+        let mut query = SQLXPool::new_query(&sql).bind(lock_hash.as_bytes().to_vec());
+        for (tx_hash, _) in dead_cells {
+            query = query.bind(tx_hash);
+        }
+
+        let mut asset_types = self
+            .store
+            .fetch_all(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?
+            .iter()
+            .map(|row| {
+                let type_code_hash = row
+                    .get::<Option<Vec<u8>>, _>("type_code_hash")
+                    .map(|code_hash| code_hash_from_db(&code_hash).map(H256))
+                    .transpose()?;
+                Ok(IndexerAssetType {
+                    type_code_hash,
+                    cell_count: (row.get::<i64, _>("cell_count") as u64).into(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        asset_types.sort_by(|a, b| a.type_code_hash.cmp(&b.type_code_hash));
+        Ok(asset_types)
+    }
+}
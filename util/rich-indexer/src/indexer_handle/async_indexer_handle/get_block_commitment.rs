@@ -0,0 +1,43 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::IndexerBlockCommitment;
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the light-client commitment of the block extension.
+    ///
+    /// Returns `None` if the block is not indexed or its extension doesn't match the known
+    /// layout, the raw extension is still stored with the block.
+    pub async fn get_block_commitment(
+        &self,
+        block_hash: H256,
+    ) -> Result<Option<IndexerBlockCommitment>, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                block_commitment.chain_root,
+                block_commitment.extra_data
+            FROM
+                block_commitment
+            JOIN block ON block_commitment.block_id = block.id
+            WHERE
+                block.block_hash = $1
+            "#,
+        )
+        .bind(block_hash.as_bytes().to_vec());
+
+        self.store
+            .fetch_optional(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+            .map(|row| {
+                row.map(|row| IndexerBlockCommitment {
+                    chain_root: bytes_to_h256(row.get("chain_root")),
+                    extra_data: JsonBytes::from_vec(row.get("extra_data")),
+                })
+            })
+    }
+}
@@ -0,0 +1,103 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{BlockNumber, IndexerCellEvent, IndexerCellType};
+use ckb_types::{packed::OutPointBuilder, prelude::*};
+use sqlx::Row;
+
+/// The maximum number of blocks that a single call to `get_block_range_cell_events` can cover.
+const MAX_CELL_EVENTS_BLOCK_RANGE: u64 = 1_000;
+
+impl AsyncRichIndexerHandle {
+    /// Get the cells created and consumed in the block range [from, to).
+    ///
+    /// The events are ordered by block number and transaction index, and within a transaction
+    /// the consumed cells (inputs) come before the created cells (outputs).
+    pub async fn get_block_range_cell_events(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<IndexerCellEvent>, Error> {
+        let from: u64 = from.into();
+        let to: u64 = to.into();
+        if from >= to {
+            return Err(Error::invalid_params("from should be less than to"));
+        }
+        if to - from > MAX_CELL_EVENTS_BLOCK_RANGE {
+            return Err(Error::invalid_params(format!(
+                "block range must not exceed {} blocks",
+                MAX_CELL_EVENTS_BLOCK_RANGE,
+            )));
+        }
+        if from > i64::MAX as u64 {
+            return Ok(vec![]);
+        }
+        let from = from as i64;
+        let to = to.min(i64::MAX as u64) as i64;
+
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                block.block_number,
+                ckb_transaction.tx_index,
+                ckb_transaction.tx_hash,
+                0 AS io_type,
+                input.input_index AS io_index,
+                output_tx.tx_hash AS cell_tx_hash,
+                output.output_index AS cell_index
+            FROM
+                input
+            JOIN output ON input.output_id = output.id
+            JOIN ckb_transaction AS output_tx ON output.tx_id = output_tx.id
+            JOIN ckb_transaction ON input.consumed_tx_id = ckb_transaction.id
+            JOIN block ON ckb_transaction.block_id = block.id
+            WHERE
+                block.block_number >= $1 AND block.block_number < $2
+            UNION ALL
+            SELECT
+                block.block_number,
+                ckb_transaction.tx_index,
+                ckb_transaction.tx_hash,
+                1 AS io_type,
+                output.output_index AS io_index,
+                ckb_transaction.tx_hash AS cell_tx_hash,
+                output.output_index AS cell_index
+            FROM
+                output
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            JOIN block ON ckb_transaction.block_id = block.id
+            WHERE
+                block.block_number >= $3 AND block.block_number < $4
+            ORDER BY block_number, tx_index, io_type, io_index
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(from)
+        .bind(to);
+
+        self.store
+            .fetch_map(query, |row| {
+                let out_point = OutPointBuilder::default()
+                    .tx_hash(to_fixed_array::<32>(&row.get::<Vec<u8>, _>("cell_tx_hash")).pack())
+                    .index((row.get::<i32, _>("cell_index") as u32).pack())
+                    .build();
+                IndexerCellEvent {
+                    out_point: out_point.into(),
+                    block_number: (row.get::<i64, _>("block_number") as u64).into(),
+                    tx_index: (row.get::<i32, _>("tx_index") as u32).into(),
+                    tx_hash: bytes_to_h256(row.get("tx_hash")),
+                    io_type: match row.get::<i32, _>("io_type") {
+                        0 => IndexerCellType::Input,
+                        1 => IndexerCellType::Output,
+                        _ => unreachable!(),
+                    },
+                    io_index: (row.get::<i32, _>("io_index") as u32).into(),
+                }
+            })
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+    }
+}
@@ -0,0 +1,116 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{Capacity, Script};
+use ckb_types::{packed, prelude::*};
+use sqlx::Row;
+
+use std::collections::HashMap;
+
+impl AsyncRichIndexerHandle {
+    /// Get the total capacity of the live cells of each lock script, keyed by lock hash.
+    ///
+    /// Locks without live cells are reported with a zero capacity.
+    pub async fn get_capacity_multi_lock(
+        &self,
+        locks: Vec<Script>,
+    ) -> Result<HashMap<H256, Capacity>, Error> {
+        if locks.len() > self.request_limit {
+            return Err(Error::invalid_params(format!(
+                "number of locks must be less than or equal to {}",
+                self.request_limit
+            )));
+        }
+        let lock_hashes: Vec<H256> = locks
+            .into_iter()
+            .map(|lock| packed::Script::from(lock).calc_script_hash().unpack())
+            .collect();
+        let mut capacities: HashMap<H256, Capacity> = lock_hashes
+            .iter()
+            .map(|lock_hash| (lock_hash.clone(), 0u64.into()))
+            .collect();
+        if lock_hashes.is_empty() {
+            return Ok(capacities);
+        }
+
+        let mut param_index = 1;
+        let placeholders = lock_hashes
+            .iter()
+            .map(|_| {
+                let placeholder = format!("${}", param_index);
+                param_index += 1;
+                placeholder
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut query_builder = SqlBuilder::select_from("output");
+        query_builder
+            .field("lock_script.script_hash")
+            .field("CAST(SUM(output.capacity) AS BIGINT) AS total_capacity")
+            .join("script AS lock_script")
+            .on("output.lock_script_id = lock_script.id")
+            .join("ckb_transaction")
+            .on("output.tx_id = ckb_transaction.id")
+            .and_where(format!("lock_script.script_hash IN ({})", placeholders))
+            .and_where("output.is_spent = 0"); // live cells
+
+        // filter cells in pool
+        let mut dead_cells = Vec::new();
+        if let Some(pool) = self
+            .pool
+            .as_ref()
+            .map(|pool| pool.read().expect("acquire lock"))
+        {
+            dead_cells = pool
+                .dead_cells()
+                .map(|out_point| {
+                    let tx_hash: H256 = out_point.tx_hash().unpack();
+                    (tx_hash.as_bytes().to_vec(), out_point.index().unpack())
+                })
+                .collect::<Vec<(_, u32)>>()
+        }
+        if !dead_cells.is_empty() {
+            let placeholders = dead_cells
+                .iter()
+                .map(|(_, output_index)| {
+                    let placeholder = format!("(${}, {})", param_index, output_index);
+                    param_index += 1;
+                    placeholder
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            query_builder.and_where(format!(
+                "(ckb_transaction.tx_hash, output_index) NOT IN ({})",
+                placeholders
+            ));
+        }
+        query_builder.group_by("lock_script.script_hash");
+
+        let sql = query_builder
+            .sql()
+            .map_err(|err| Error::DB(err.to_string()))?
+            .trim_end_matches(';')
+            .to_string();
+        let mut query = SQLXPool::new_query(&sql);
+        for lock_hash in &lock_hashes {
+            query = query.bind(lock_hash.as_bytes().to_vec());
+        }
+        for (tx_hash, _) in dead_cells {
+            query = query.bind(tx_hash);
+        }
+
+        let rows = self
+            .store
+            .fetch_all(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?;
+        for row in rows {
+            let lock_hash = bytes_to_h256(&row.get::<Vec<u8>, _>("script_hash"));
+            let capacity = row.get::<i64, _>("total_capacity") as u64;
+            capacities.insert(lock_hash, capacity.into());
+        }
+        Ok(capacities)
+    }
+}
@@ -0,0 +1,70 @@
+use super::get_cells::build_indexer_cell;
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::IndexerCellWithStatus;
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get an output by its transaction hash and index, whether it is live or spent, and the
+    /// transaction consuming it. The spending status only reflects committed transactions.
+    pub async fn get_cell(
+        &self,
+        tx_hash: H256,
+        index: u32,
+    ) -> Result<Option<IndexerCellWithStatus>, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                output.output_index,
+                output.capacity,
+                output.data AS output_data,
+                output.data_compressed,
+                output.is_spent,
+                lock_script.code_hash AS lock_code_hash,
+                lock_script.hash_type AS lock_hash_type,
+                lock_script.args AS lock_args,
+                type_script.code_hash AS type_code_hash,
+                type_script.hash_type AS type_hash_type,
+                type_script.args AS type_args,
+                ckb_transaction.tx_index,
+                ckb_transaction.tx_hash,
+                block.block_number,
+                consumed_tx.tx_hash AS spent_by_tx
+            FROM
+                output
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            JOIN block ON ckb_transaction.block_id = block.id
+            JOIN script AS lock_script ON output.lock_script_id = lock_script.id
+            LEFT JOIN script AS type_script ON output.type_script_id = type_script.id
+            LEFT JOIN input ON input.output_id = output.id
+            LEFT JOIN ckb_transaction AS consumed_tx ON input.consumed_tx_id = consumed_tx.id
+            WHERE
+                ckb_transaction.tx_hash = $1
+                AND output.output_index = $2
+            "#,
+        )
+        .bind(tx_hash.as_bytes().to_vec())
+        .bind(index as i32);
+
+        let row = self
+            .store
+            .fetch_optional(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?;
+        row.map(|row| {
+            let spent_by_tx = row
+                .get::<Option<Vec<u8>>, _>("spent_by_tx")
+                .map(|tx_hash| bytes_to_h256(&tx_hash));
+            Ok(IndexerCellWithStatus {
+                cell: build_indexer_cell(&row)?,
+                spent: row.get::<Option<i32>, _>("is_spent").unwrap_or_default() != 0
+                    || spent_by_tx.is_some(),
+                spent_by_tx,
+            })
+        })
+        .transpose()
+    }
+}
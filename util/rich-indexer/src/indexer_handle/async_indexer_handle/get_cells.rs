@@ -1,11 +1,12 @@
 use super::*;
 
-use crate::indexer::to_fixed_array;
+use crate::indexer::{code_hash_from_db, decompress_data, is_data_compressed, to_fixed_array};
 use crate::store::SQLXPool;
 
 use ckb_indexer_sync::Error;
 use ckb_jsonrpc_types::{
-    IndexerCell, IndexerOrder, IndexerPagination, IndexerSearchKey, JsonBytes, Uint32,
+    IndexerCell, IndexerCellsOrderBy, IndexerOrder, IndexerPagination, IndexerSearchKey, JsonBytes,
+    Uint32,
 };
 use ckb_jsonrpc_types::{IndexerScriptType, IndexerSearchMode};
 use ckb_types::packed::{CellOutputBuilder, OutPointBuilder, ScriptBuilder};
@@ -21,6 +22,22 @@ impl AsyncRichIndexerHandle {
         order: IndexerOrder,
         limit: Uint32,
         after: Option<JsonBytes>,
+    ) -> Result<IndexerPagination<IndexerCell>, Error> {
+        self.get_cells_with_order_by(search_key, order, IndexerCellsOrderBy::Id, limit, after)
+            .await
+    }
+
+    /// Get cells sorted by the given key.
+    ///
+    /// With `IndexerCellsOrderBy::Creation` the cells are sorted by block number, transaction
+    /// index and output index, and the cursor encodes that triple instead of the cell id.
+    pub async fn get_cells_with_order_by(
+        &self,
+        search_key: IndexerSearchKey,
+        order: IndexerOrder,
+        order_by: IndexerCellsOrderBy,
+        limit: Uint32,
+        after: Option<JsonBytes>,
     ) -> Result<IndexerPagination<IndexerCell>, Error> {
         let limit = limit.value();
         if limit == 0 {
@@ -74,10 +91,14 @@ impl AsyncRichIndexerHandle {
             .field("block.block_number");
         match search_key.with_data {
             Some(true) | None => {
-                query_builder.field("output.data as output_data");
+                query_builder
+                    .field("output.data as output_data")
+                    .field("output.data_compressed");
             }
             Some(false) => {
-                query_builder.field("NULL as output_data");
+                query_builder
+                    .field("NULL as output_data")
+                    .field("0 as data_compressed");
             }
         }
         query_builder.join(&format!("{} AS query_script", script_sub_query_sql));
@@ -134,25 +155,70 @@ impl AsyncRichIndexerHandle {
             query_builder.and_where(format!("(tx_hash, output_index) NOT IN ({})", placeholders));
         }
 
-        if let Some(after) = after {
-            let after = decode_i64(after.as_bytes())?;
-            match order {
-                IndexerOrder::Asc => query_builder.and_where_gt("output.id", after),
-                IndexerOrder::Desc => query_builder.and_where_lt("output.id", after),
-            };
+        let desc = matches!(order, IndexerOrder::Desc);
+        let operator = if desc { "<" } else { ">" };
+        let cursor = match after {
+            Some(after) => Some(match order_by {
+                IndexerCellsOrderBy::Id => {
+                    let after = decode_i64(after.as_bytes())?;
+                    format!("output.id {} {}", operator, after)
+                }
+                IndexerCellsOrderBy::Creation => {
+                    let (block_number, tx_index, output_index) =
+                        decode_creation_cursor(after.as_bytes())?;
+                    format!(
+                        "(block.block_number, ckb_transaction.tx_index, output.output_index) {} ({}, {}, {})",
+                        operator, block_number, tx_index, output_index
+                    )
+                }
+            }),
+            None => None,
+        };
+        if let Some(cursor) = &cursor {
+            query_builder.and_where(cursor);
         }
+        let order_columns = match order_by {
+            IndexerCellsOrderBy::Id => vec!["output.id"],
+            IndexerCellsOrderBy::Creation => vec![
+                "block.block_number",
+                "ckb_transaction.tx_index",
+                "output.output_index",
+            ],
+        };
 
+        // read once, the conditions and their bound values must agree
+        let data_prefix_len = self.store.queryable_data_prefix_len();
+        // every dead cell in the pool may take the place of a match in the page
+        let scope = CompressedDataScope {
+            live_only: true,
+            output_block_range: true,
+            condition: cursor,
+            page: Some((
+                order_columns.clone(),
+                desc,
+                limit as usize + dead_cells.len(),
+            )),
+        };
+        let compressed_matches = query_compressed_data_matches(
+            self.store.db_driver,
+            data_prefix_len,
+            &search_key,
+            &scope,
+            self.store.get_pool().map_err(db_error)?,
+        )
+        .await?;
         build_cell_filter(
             self.store.db_driver,
+            data_prefix_len,
+            &compressed_matches,
             &mut query_builder,
             &search_key,
             &mut param_index,
         );
 
-        match order {
-            IndexerOrder::Asc => query_builder.order_by("output.id", false),
-            IndexerOrder::Desc => query_builder.order_by("output.id", true),
-        };
+        for column in order_columns {
+            query_builder.order_by(column, desc);
+        }
         query_builder.limit(limit);
 
         // sql string
@@ -181,7 +247,7 @@ impl AsyncRichIndexerHandle {
                     let new_args = escape_and_wrap_for_postgres_like(&search_key.script.args);
                     query = query.bind(new_args);
                 }
-                DBDriver::Sqlite => {
+                DBDriver::Sqlite | DBDriver::MySQL => {
                     query = query.bind(search_key.script.args.as_bytes());
                 }
             },
@@ -211,12 +277,15 @@ impl AsyncRichIndexerHandle {
                             let new_data = escape_and_wrap_for_postgres_like(data);
                             query = query.bind(new_data);
                         }
-                        DBDriver::Sqlite => {
+                        DBDriver::Sqlite | DBDriver::MySQL => {
                             query = query.bind(data.as_bytes());
                         }
                     },
                 }
             }
+            if let Some((lower, upper)) = data_prefix_bounds(filter, data_prefix_len) {
+                query = query.bind(lower).bind(upper);
+            }
         }
         if !dead_cells.is_empty() {
             for (tx_hash, _) in dead_cells {
@@ -228,15 +297,17 @@ impl AsyncRichIndexerHandle {
         let mut last_cursor = Vec::new();
         let cells = self
             .store
-            .fetch_all(query)
-            .await
-            .map_err(|err| Error::DB(err.to_string()))?
-            .iter()
-            .map(|row| {
-                last_cursor = row.get::<i64, _>("id").to_le_bytes().to_vec();
+            .fetch_map(query, |row| {
+                last_cursor = match order_by {
+                    IndexerCellsOrderBy::Id => row.get::<i64, _>("id").to_le_bytes().to_vec(),
+                    IndexerCellsOrderBy::Creation => encode_creation_cursor(row),
+                };
                 build_indexer_cell(row)
             })
-            .collect::<Vec<_>>();
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(IndexerPagination {
             objects: cells,
@@ -245,39 +316,76 @@ impl AsyncRichIndexerHandle {
     }
 }
 
-fn build_indexer_cell(row: &AnyRow) -> IndexerCell {
+fn encode_creation_cursor(row: &AnyRow) -> Vec<u8> {
+    [
+        &row.get::<i64, _>("block_number").to_le_bytes()[..],
+        &row.get::<i32, _>("tx_index").to_le_bytes()[..],
+        &row.get::<i32, _>("output_index").to_le_bytes()[..],
+    ]
+    .concat()
+}
+
+fn decode_creation_cursor(data: &[u8]) -> Result<(i64, i32, i32), Error> {
+    if data.len() != 16 {
+        return Err(Error::Params(
+            "unable to decode the creation cursor, expected 16 bytes in little-endian format"
+                .to_string(),
+        ));
+    }
+    Ok((
+        decode_i64(&data[0..8])?,
+        decode_i32(&data[8..12])?,
+        decode_i32(&data[12..16])?,
+    ))
+}
+
+pub(super) fn build_indexer_cell(row: &AnyRow) -> Result<IndexerCell, Error> {
     let out_point = OutPointBuilder::default()
         .tx_hash(to_fixed_array::<32>(&row.get::<Vec<u8>, _>("tx_hash")).pack())
         .index((row.get::<i32, _>("output_index") as u32).pack())
         .build();
     let lock_script = ScriptBuilder::default()
-        .code_hash(to_fixed_array::<32>(&row.get::<Vec<u8>, _>("lock_code_hash")).pack())
+        .code_hash(code_hash_from_db(&row.get::<Vec<u8>, _>("lock_code_hash"))?.pack())
         .hash_type((row.get::<i16, _>("lock_hash_type") as u8).into())
-        .args(row.get::<Vec<u8>, _>("lock_args").pack())
+        .args(
+            row.get::<Option<Vec<u8>>, _>("lock_args")
+                .unwrap_or_default()
+                .pack(),
+        )
         .build();
     let type_script = row
         .get::<Option<Vec<u8>>, _>("type_code_hash")
         .as_ref()
         .map(|value| {
-            ScriptBuilder::default()
-                .code_hash(to_fixed_array::<32>(value).pack())
-                .hash_type((row.get::<Option<i16>, _>("type_hash_type").unwrap() as u8).into())
-                .args(row.get::<Option<Vec<u8>>, _>("type_args").unwrap().pack())
-                .build()
-        });
+            Ok::<_, Error>(
+                ScriptBuilder::default()
+                    .code_hash(code_hash_from_db(value)?.pack())
+                    .hash_type((row.get::<Option<i16>, _>("type_hash_type").unwrap() as u8).into())
+                    .args(
+                        row.get::<Option<Vec<u8>>, _>("type_args")
+                            .unwrap_or_default()
+                            .pack(),
+                    )
+                    .build(),
+            )
+        })
+        .transpose()?;
     let output = CellOutputBuilder::default()
         .capacity((row.get::<i64, _>("capacity") as u64).pack())
         .lock(lock_script)
         .type_(type_script.pack())
         .build();
 
-    IndexerCell {
+    let output_data = row
+        .get::<Option<Vec<u8>>, _>("output_data")
+        .map(|data| decompress_data(data, is_data_compressed(row)).map(JsonBytes::from_vec))
+        .transpose()?;
+
+    Ok(IndexerCell {
         output: output.into(),
-        output_data: row
-            .get::<Option<Vec<u8>>, _>("output_data")
-            .map(JsonBytes::from_vec),
+        output_data,
         out_point: out_point.into(),
         block_number: (row.get::<i64, _>("block_number") as u64).into(),
         tx_index: (row.get::<i32, _>("tx_index") as u32).into(),
-    }
+    })
 }
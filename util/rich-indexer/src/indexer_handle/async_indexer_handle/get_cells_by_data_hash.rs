@@ -0,0 +1,95 @@
+use super::get_cells::build_indexer_cell;
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{IndexerCell, IndexerOrder, IndexerPagination, JsonBytes, Uint32};
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the live cells whose data has the given hash, as computed by
+    /// `CellOutput::calc_data_hash`, so that empty data has the zero hash.
+    ///
+    /// The cells are paged by their id, like `get_cells`.
+    pub async fn get_cells_by_data_hash(
+        &self,
+        data_hash: H256,
+        order: IndexerOrder,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<IndexerPagination<IndexerCell>, Error> {
+        let limit = limit.value();
+        if limit == 0 {
+            return Err(Error::invalid_params("limit should be greater than 0"));
+        }
+        if limit as usize > self.request_limit {
+            return Err(Error::invalid_params(format!(
+                "limit must be less than {}",
+                self.request_limit,
+            )));
+        }
+        let (operator, direction) = match order {
+            IndexerOrder::Asc => (">", "ASC"),
+            IndexerOrder::Desc => ("<", "DESC"),
+        };
+        let after = after
+            .map(|after| decode_i64(after.as_bytes()))
+            .transpose()?;
+
+        let sql = format!(
+            r#"
+            SELECT
+                output.id,
+                output.output_index,
+                output.capacity,
+                output.data AS output_data,
+                output.data_compressed,
+                lock_script.code_hash AS lock_code_hash,
+                lock_script.hash_type AS lock_hash_type,
+                lock_script.args AS lock_args,
+                type_script.code_hash AS type_code_hash,
+                type_script.hash_type AS type_hash_type,
+                type_script.args AS type_args,
+                ckb_transaction.tx_index,
+                ckb_transaction.tx_hash,
+                block.block_number
+            FROM
+                output
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            JOIN block ON ckb_transaction.block_id = block.id
+            JOIN script AS lock_script ON output.lock_script_id = lock_script.id
+            LEFT JOIN script AS type_script ON output.type_script_id = type_script.id
+            WHERE
+                output.data_hash = $1
+                AND output.is_spent = 0
+                {}
+            ORDER BY output.id {}
+            LIMIT {}
+            "#,
+            after
+                .map(|after| format!("AND output.id {} {}", operator, after))
+                .unwrap_or_default(),
+            direction,
+            limit
+        );
+        let query = SQLXPool::new_query(&sql).bind(data_hash.as_bytes().to_vec());
+
+        let mut last_cursor = Vec::new();
+        let cells = self
+            .store
+            .fetch_map(query, |row| {
+                last_cursor = row.get::<i64, _>("id").to_le_bytes().to_vec();
+                build_indexer_cell(row)
+            })
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(IndexerPagination {
+            objects: cells,
+            last_cursor: JsonBytes::from_vec(last_cursor),
+        })
+    }
+}
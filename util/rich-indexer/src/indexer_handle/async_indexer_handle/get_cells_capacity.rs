@@ -102,8 +102,31 @@ impl AsyncRichIndexerHandle {
             ));
         }
 
+        let mut tx = self
+            .store
+            .transaction()
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?;
+
+        // read once, the conditions and their bound values must agree
+        let data_prefix_len = self.store.queryable_data_prefix_len();
+        let scope = CompressedDataScope {
+            live_only: true,
+            output_block_range: true,
+            ..Default::default()
+        };
+        let compressed_matches = query_compressed_data_matches(
+            self.store.db_driver,
+            data_prefix_len,
+            &search_key,
+            &scope,
+            tx.as_mut(),
+        )
+        .await?;
         build_cell_filter(
             self.store.db_driver,
+            data_prefix_len,
+            &compressed_matches,
             &mut query_builder,
             &search_key,
             &mut param_index,
@@ -135,7 +158,7 @@ impl AsyncRichIndexerHandle {
                     let new_args = escape_and_wrap_for_postgres_like(&search_key.script.args);
                     query = query.bind(new_args);
                 }
-                DBDriver::Sqlite => {
+                DBDriver::Sqlite | DBDriver::MySQL => {
                     query = query.bind(search_key.script.args.as_bytes());
                 }
             },
@@ -165,12 +188,15 @@ impl AsyncRichIndexerHandle {
                             let new_data = escape_and_wrap_for_postgres_like(data);
                             query = query.bind(new_data);
                         }
-                        DBDriver::Sqlite => {
+                        DBDriver::Sqlite | DBDriver::MySQL => {
                             query = query.bind(data.as_bytes());
                         }
                     },
                 }
             }
+            if let Some((lower, upper)) = data_prefix_bounds(filter, data_prefix_len) {
+                query = query.bind(lower).bind(upper);
+            }
         }
         if !dead_cells.is_empty() {
             for (tx_hash, _) in dead_cells {
@@ -178,12 +204,6 @@ impl AsyncRichIndexerHandle {
             }
         }
 
-        let mut tx = self
-            .store
-            .transaction()
-            .await
-            .map_err(|err| Error::DB(err.to_string()))?;
-
         // fetch
         let capacity = query
             .fetch_optional(&mut *tx)
@@ -0,0 +1,45 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::OutPoint;
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the custom fields extracted from the data of a cell, as `(name, value)` pairs
+    /// ordered by name.
+    pub async fn get_custom_cell_fields(
+        &self,
+        out_point: OutPoint,
+    ) -> Result<Vec<(String, String)>, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                custom_cell_field.field_name,
+                custom_cell_field.field_value
+            FROM
+                custom_cell_field
+            JOIN output ON custom_cell_field.output_id = output.id
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            WHERE
+                ckb_transaction.tx_hash = $1
+                AND output.output_index = $2
+            ORDER BY
+                custom_cell_field.field_name
+            "#,
+        )
+        .bind(out_point.tx_hash.as_bytes().to_vec())
+        .bind(out_point.index.value() as i32);
+
+        self.store
+            .fetch_all(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+            .map(|rows| {
+                rows.iter()
+                    .map(|row| (row.get("field_name"), row.get("field_value")))
+                    .collect()
+            })
+    }
+}
@@ -0,0 +1,76 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{IndexerPagination, IndexerTxInfo, JsonBytes, Uint32};
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the latest transactions of the chain, newest first by block number and
+    /// transaction index.
+    ///
+    /// The cellbase transactions are only returned when `include_cellbase` is set.
+    pub async fn get_latest_transactions(
+        &self,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+        include_cellbase: bool,
+    ) -> Result<IndexerPagination<IndexerTxInfo>, Error> {
+        let limit = limit.value();
+        if limit == 0 {
+            return Err(Error::invalid_params("limit should be greater than 0"));
+        }
+        if limit as usize > self.request_limit {
+            return Err(Error::invalid_params(format!(
+                "limit must be less than {}",
+                self.request_limit,
+            )));
+        }
+
+        let mut query_builder = SqlBuilder::select_from("ckb_transaction");
+        query_builder
+            .field("ckb_transaction.id")
+            .field("ckb_transaction.tx_hash")
+            .field("ckb_transaction.tx_index")
+            .field("block.block_number")
+            .join("block")
+            .on("ckb_transaction.block_id = block.id");
+        if !include_cellbase {
+            query_builder.and_where_gt("ckb_transaction.tx_index", 0);
+        }
+        if let Some(after) = after {
+            let after = decode_i64(after.as_bytes())?;
+            query_builder.and_where_lt("ckb_transaction.id", after);
+        }
+        // transactions are indexed in chain order, so the id follows the block number and
+        // the transaction index
+        query_builder
+            .order_by("ckb_transaction.id", true)
+            .limit(limit);
+        let sql = query_builder
+            .sql()
+            .map_err(|err| Error::DB(err.to_string()))?
+            .trim_end_matches(';')
+            .to_string();
+
+        let mut last_id = 0;
+        let txs = self
+            .store
+            .fetch_map(SQLXPool::new_query(&sql), |row| {
+                last_id = row.get::<i64, _>("id");
+                IndexerTxInfo {
+                    tx_hash: bytes_to_h256(row.get("tx_hash")),
+                    block_number: (row.get::<i64, _>("block_number") as u64).into(),
+                    tx_index: (row.get::<i32, _>("tx_index") as u32).into(),
+                }
+            })
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?;
+
+        Ok(IndexerPagination {
+            objects: txs,
+            last_cursor: JsonBytes::from_vec(last_id.to_le_bytes().to_vec()),
+        })
+    }
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{IndexerLockUsage, Uint32};
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the live cell count of each lock script code hash, most used first.
+    pub async fn get_lock_usage_stats(
+        &self,
+        limit: Uint32,
+    ) -> Result<Vec<IndexerLockUsage>, Error> {
+        let limit = limit.value();
+        if limit == 0 {
+            return Err(Error::invalid_params("limit should be greater than 0"));
+        }
+        if limit as usize > self.request_limit {
+            return Err(Error::invalid_params(format!(
+                "limit must be less than {}",
+                self.request_limit,
+            )));
+        }
+
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                script.code_hash,
+                COUNT(*) AS live_cell_count
+            FROM
+                output
+            JOIN script ON output.lock_script_id = script.id
+            WHERE
+                output.is_spent = 0
+            GROUP BY
+                script.code_hash
+            ORDER BY
+                live_cell_count DESC,
+                script.code_hash
+            LIMIT $1
+            "#,
+        )
+        .bind(limit as i64);
+
+        self.store
+            .fetch_all(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+            .map(|rows| {
+                rows.iter()
+                    .map(|row| IndexerLockUsage {
+                        code_hash: bytes_to_h256(row.get("code_hash")),
+                        live_cell_count: (row.get::<i64, _>("live_cell_count") as u64).into(),
+                    })
+                    .collect()
+            })
+    }
+}
@@ -0,0 +1,55 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::IndexerOutputSpendingStatus;
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get whether each indexed output of the transaction is spent, and by which transaction,
+    /// ordered by output index.
+    pub async fn get_outputs_spending_status(
+        &self,
+        tx_hash: H256,
+    ) -> Result<Vec<IndexerOutputSpendingStatus>, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                output.output_index,
+                output.is_spent,
+                consumed_tx.tx_hash AS spent_by_tx
+            FROM
+                output
+            JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+            LEFT JOIN input ON input.output_id = output.id
+            LEFT JOIN ckb_transaction AS consumed_tx ON input.consumed_tx_id = consumed_tx.id
+            WHERE
+                ckb_transaction.tx_hash = $1
+            ORDER BY
+                output.output_index
+            "#,
+        )
+        .bind(tx_hash.as_bytes().to_vec());
+
+        self.store
+            .fetch_all(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+            .map(|rows| {
+                rows.iter()
+                    .map(|row| {
+                        let spent_by_tx = row
+                            .get::<Option<Vec<u8>>, _>("spent_by_tx")
+                            .map(|tx_hash| bytes_to_h256(&tx_hash));
+                        IndexerOutputSpendingStatus {
+                            output_index: (row.get::<i32, _>("output_index") as u32).into(),
+                            spent: row.get::<Option<i32>, _>("is_spent").unwrap_or_default() != 0
+                                || spent_by_tx.is_some(),
+                            spent_by_tx,
+                        }
+                    })
+                    .collect()
+            })
+    }
+}
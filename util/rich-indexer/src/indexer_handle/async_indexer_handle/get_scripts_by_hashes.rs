@@ -0,0 +1,77 @@
+use super::*;
+
+use crate::indexer::code_hash_from_db;
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::Script;
+use ckb_types::packed::ScriptBuilder;
+use ckb_types::prelude::*;
+use sqlx::Row;
+
+use std::collections::HashMap;
+
+impl AsyncRichIndexerHandle {
+    /// Resolve script hashes to the scripts indexed under them, hashes that are not
+    /// indexed are absent from the result.
+    pub async fn get_scripts_by_hashes(
+        &self,
+        script_hashes: Vec<H256>,
+    ) -> Result<HashMap<H256, Script>, Error> {
+        if script_hashes.len() > self.request_limit {
+            return Err(Error::invalid_params(format!(
+                "number of script hashes must be less than or equal to {}",
+                self.request_limit
+            )));
+        }
+        if script_hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let placeholders = (1..=script_hashes.len())
+            .map(|param_index| format!("${}", param_index))
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            r#"
+            SELECT
+                script_hash,
+                code_hash,
+                hash_type,
+                args
+            FROM
+                script
+            WHERE
+                script_hash IN ({})
+            "#,
+            placeholders
+        );
+        let mut query = SQLXPool::new_query(&sql);
+        for script_hash in &script_hashes {
+            query = query.bind(script_hash.as_bytes().to_vec());
+        }
+
+        let rows = self
+            .store
+            .fetch_all(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?;
+        rows.iter()
+            .map(|row| {
+                let script = ScriptBuilder::default()
+                    .code_hash(code_hash_from_db(&row.get::<Vec<u8>, _>("code_hash"))?.pack())
+                    .hash_type((row.get::<i16, _>("hash_type") as u8).into())
+                    .args(
+                        row.get::<Option<Vec<u8>>, _>("args")
+                            .unwrap_or_default()
+                            .pack(),
+                    )
+                    .build();
+                Ok((
+                    bytes_to_h256(&row.get::<Vec<u8>, _>("script_hash")),
+                    script.into(),
+                ))
+            })
+            .collect()
+    }
+}
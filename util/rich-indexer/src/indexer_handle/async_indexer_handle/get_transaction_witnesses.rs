@@ -0,0 +1,45 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_types::{packed, prelude::*};
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the witnesses of the transaction, in order.
+    ///
+    /// Returns `None` if the transaction is not indexed, or if its witnesses are not stored
+    /// because `index_witnesses` was disabled when it was indexed.
+    pub async fn get_transaction_witnesses(
+        &self,
+        tx_hash: H256,
+    ) -> Result<Option<Vec<JsonBytes>>, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT witnesses
+            FROM ckb_transaction
+            WHERE tx_hash = $1
+            "#,
+        )
+        .bind(tx_hash.as_bytes().to_vec());
+
+        let row = self
+            .store
+            .fetch_optional(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?;
+        let Some(witnesses) = row.and_then(|row| row.get::<Option<Vec<u8>>, _>("witnesses")) else {
+            return Ok(None);
+        };
+        let witnesses =
+            packed::BytesVec::from_slice(&witnesses).map_err(|err| Error::DB(err.to_string()))?;
+        Ok(Some(
+            witnesses
+                .into_iter()
+                .map(|witness| JsonBytes::from_bytes(witness.raw_data()))
+                .collect(),
+        ))
+    }
+}
@@ -8,6 +8,7 @@ use ckb_jsonrpc_types::{
     IndexerTxWithCells,
 };
 use ckb_jsonrpc_types::{IndexerOrder, IndexerPagination, IndexerSearchKey, JsonBytes, Uint32};
+use futures::TryStreamExt;
 use sql_builder::{name, name::SqlName, SqlBuilder};
 use sqlx::{any::Any, Row, Transaction};
 
@@ -55,6 +56,7 @@ impl AsyncRichIndexerHandle {
 
                 let txs = get_tx_with_cell(
                     self.store.db_driver,
+                    self.store.queryable_data_prefix_len(),
                     search_key,
                     &order,
                     limit,
@@ -100,6 +102,7 @@ impl AsyncRichIndexerHandle {
             Some(true) => {
                 let txs = get_tx_with_cells(
                     self.store.db_driver,
+                    self.store.queryable_data_prefix_len(),
                     search_key,
                     &order,
                     limit,
@@ -144,13 +147,23 @@ impl AsyncRichIndexerHandle {
 
 pub async fn get_tx_with_cell(
     db_driver: DBDriver,
+    data_prefix_len: Option<usize>,
     search_key: IndexerSearchKey,
     order: &IndexerOrder,
     limit: u32,
     last_cursor: Option<(i64, i32)>,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<Vec<(i64, u64, u32, Vec<u8>, u16, u32)>, Error> {
-    let sql_union = build_tx_with_cell_union_sub_query(db_driver, &search_key)?;
+    let scope = compressed_data_scope(order, last_cursor.map(|(last, _)| last));
+    let compressed_matches =
+        query_compressed_data_matches(db_driver, data_prefix_len, &search_key, &scope, tx.as_mut())
+            .await?;
+    let sql_union = build_tx_with_cell_union_sub_query(
+        db_driver,
+        data_prefix_len,
+        &compressed_matches,
+        &search_key,
+    )?;
 
     let mut query_builder = SqlBuilder::select_from(format!("{} AS res", sql_union));
     query_builder.field("tx_id, block.block_number, ckb_transaction.tx_index, ckb_transaction.tx_hash, io_type, io_index");
@@ -208,7 +221,7 @@ pub async fn get_tx_with_cell(
                     let new_args = escape_and_wrap_for_postgres_like(&search_key.script.args);
                     query = query.bind(new_args);
                 }
-                DBDriver::Sqlite => {
+                DBDriver::Sqlite | DBDriver::MySQL => {
                     query = query.bind(search_key.script.args.as_bytes());
                 }
             },
@@ -238,45 +251,61 @@ pub async fn get_tx_with_cell(
                             let new_data = escape_and_wrap_for_postgres_like(data);
                             query = query.bind(new_data);
                         }
-                        DBDriver::Sqlite => {
+                        DBDriver::Sqlite | DBDriver::MySQL => {
                             query = query.bind(data.as_bytes());
                         }
                     },
                 }
             }
+            if let Some((lower, upper)) = data_prefix_bounds(filter, data_prefix_len) {
+                query = query.bind(lower).bind(upper);
+            }
         }
     }
 
     // fetch
-    let outputs = query
-        .fetch_all(tx.as_mut())
+    let mut outputs = vec![];
+    let mut rows = query.fetch(tx.as_mut());
+    while let Some(row) = rows
+        .try_next()
         .await
         .map_err(|err| Error::DB(err.to_string()))?
-        .iter()
-        .map(|row| {
-            (
-                row.get::<i64, _>("tx_id"),
-                row.get::<i64, _>("block_number") as u64,
-                row.get::<i32, _>("tx_index") as u32,
-                row.get::<Vec<u8>, _>("tx_hash"),
-                row.get::<i32, _>("io_type") as u16,
-                row.get::<i32, _>("io_index") as u32,
-            )
-        })
-        .collect::<Vec<_>>();
+    {
+        outputs.push((
+            row.get::<i64, _>("tx_id"),
+            row.get::<i64, _>("block_number") as u64,
+            row.get::<i32, _>("tx_index") as u32,
+            row.get::<Vec<u8>, _>("tx_hash"),
+            row.get::<i32, _>("io_type") as u16,
+            row.get::<i32, _>("io_index") as u32,
+        ));
+    }
 
     Ok(outputs)
 }
 
 pub async fn get_tx_with_cells(
     db_driver: DBDriver,
+    data_prefix_len: Option<usize>,
     search_key: IndexerSearchKey,
     order: &IndexerOrder,
     limit: u32,
     after: Option<JsonBytes>,
     tx: &mut Transaction<'_, Any>,
 ) -> Result<Vec<(i64, u64, u32, Vec<u8>, Vec<(u16, u32)>)>, Error> {
-    let sql_union = build_tx_with_cell_union_sub_query(db_driver, &search_key)?;
+    let after = after
+        .map(|after| decode_i64(after.as_bytes()))
+        .transpose()?;
+    let scope = compressed_data_scope(order, after);
+    let compressed_matches =
+        query_compressed_data_matches(db_driver, data_prefix_len, &search_key, &scope, tx.as_mut())
+            .await?;
+    let sql_union = build_tx_with_cell_union_sub_query(
+        db_driver,
+        data_prefix_len,
+        &compressed_matches,
+        &search_key,
+    )?;
 
     let mut query_builder = SqlBuilder::select_from(format!("{} AS res_union", sql_union));
     query_builder
@@ -315,10 +344,14 @@ pub async fn get_tx_with_cells(
                 " '\"' || GROUP_CONCAT(io_type || ',' || io_index, '\",\"') || '\"' AS io_pairs",
             );
         }
+        DBDriver::MySQL => {
+            query_builder.field(
+                "CONCAT('\"', GROUP_CONCAT(CONCAT(io_type, ',', io_index) SEPARATOR '\",\"'), '\"') AS io_pairs",
+            );
+        }
     }
 
     if let Some(after) = after {
-        let after = decode_i64(after.as_bytes())?;
         match order {
             IndexerOrder::Asc => query_builder.and_where_gt("tx_id", after),
             IndexerOrder::Desc => query_builder.and_where_lt("tx_id", after),
@@ -358,7 +391,7 @@ pub async fn get_tx_with_cells(
                     let new_args = escape_and_wrap_for_postgres_like(&search_key.script.args);
                     query = query.bind(new_args);
                 }
-                DBDriver::Sqlite => {
+                DBDriver::Sqlite | DBDriver::MySQL => {
                     query = query.bind(search_key.script.args.as_bytes());
                 }
             },
@@ -388,49 +421,72 @@ pub async fn get_tx_with_cells(
                             let new_data = escape_and_wrap_for_postgres_like(data);
                             query = query.bind(new_data);
                         }
-                        DBDriver::Sqlite => {
+                        DBDriver::Sqlite | DBDriver::MySQL => {
                             query = query.bind(data.as_bytes());
                         }
                     },
                 }
             }
+            if let Some((lower, upper)) = data_prefix_bounds(filter, data_prefix_len) {
+                query = query.bind(lower).bind(upper);
+            }
         }
     }
 
     // fetch
-    let outputs = query
-        .fetch_all(tx.as_mut())
+    let mut outputs = vec![];
+    let mut rows = query.fetch(tx.as_mut());
+    while let Some(row) = rows
+        .try_next()
         .await
         .map_err(|err| Error::DB(err.to_string()))?
-        .iter()
-        .map(|row| {
-            (
-                row.get::<i64, _>("tx_id"),
-                row.get::<i64, _>("block_number") as u64,
-                row.get::<i32, _>("tx_index") as u32,
-                row.get::<Vec<u8>, _>("tx_hash"),
-                {
-                    row.get::<String, _>("io_pairs")
-                        .split("\",\"")
-                        .map(|s| {
-                            let s = s.trim_matches('\"');
-                            let mut iter = s.split(',');
-                            (
-                                iter.next().unwrap().parse::<u16>().unwrap(),
-                                iter.next().unwrap().parse::<u32>().unwrap(),
-                            )
-                        })
-                        .collect::<Vec<_>>()
-                },
-            )
-        })
-        .collect::<Vec<_>>();
+    {
+        outputs.push((
+            row.get::<i64, _>("tx_id"),
+            row.get::<i64, _>("block_number") as u64,
+            row.get::<i32, _>("tx_index") as u32,
+            row.get::<Vec<u8>, _>("tx_hash"),
+            {
+                row.get::<String, _>("io_pairs")
+                    .split("\",\"")
+                    .map(|s| {
+                        let s = s.trim_matches('\"');
+                        let mut iter = s.split(',');
+                        (
+                            iter.next().unwrap().parse::<u16>().unwrap(),
+                            iter.next().unwrap().parse::<u32>().unwrap(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            },
+        ));
+    }
 
     Ok(outputs)
 }
 
+/// Scopes the compressed outputs to the ones which can have a row in the page after the
+/// transaction id of the cursor, for the transaction creating them or one consuming them.
+fn compressed_data_scope(order: &IndexerOrder, last_tx_id: Option<i64>) -> CompressedDataScope {
+    let condition = last_tx_id.map(|last| match order {
+        IndexerOrder::Asc => format!(
+            "(output.tx_id >= {0} OR output.id IN \
+            (SELECT input.output_id FROM input WHERE input.consumed_tx_id >= {0}))",
+            last
+        ),
+        // a transaction consuming an output comes after the one creating it
+        IndexerOrder::Desc => format!("output.tx_id <= {}", last),
+    });
+    CompressedDataScope {
+        condition,
+        ..Default::default()
+    }
+}
+
 fn build_tx_with_cell_union_sub_query(
     db_driver: DBDriver,
+    data_prefix_len: Option<usize>,
+    compressed_matches: &[i64],
     search_key: &IndexerSearchKey,
 ) -> Result<String, Error> {
     let mut param_index = 1;
@@ -473,6 +529,8 @@ fn build_tx_with_cell_union_sub_query(
     }
     build_filter(
         db_driver,
+        data_prefix_len,
+        compressed_matches,
         &mut query_output_builder,
         search_key,
         &mut param_index,
@@ -519,6 +577,8 @@ fn build_tx_with_cell_union_sub_query(
     }
     build_filter(
         db_driver,
+        data_prefix_len,
+        compressed_matches,
         &mut query_input_builder,
         search_key,
         &mut param_index,
@@ -542,6 +602,8 @@ fn build_tx_with_cell_union_sub_query(
 
 fn build_filter(
     db_driver: DBDriver,
+    data_prefix_len: Option<usize>,
+    compressed_matches: &[i64],
     query_builder: &mut SqlBuilder,
     search_key: &IndexerSearchKey,
     param_index: &mut usize,
@@ -585,39 +647,17 @@ fn build_filter(
                 }
             }
         }
-        if let Some(data_len_range) = &filter.output_data_len_range {
-            query_builder.and_where_ge("LENGTH(output.data)", data_len_range.start());
-            query_builder.and_where_lt("LENGTH(output.data)", data_len_range.end());
-        }
         if let Some(capacity_range) = &filter.output_capacity_range {
             query_builder.and_where_ge("output.capacity", capacity_range.start());
             query_builder.and_where_lt("output.capacity", capacity_range.end());
         }
-        if filter.output_data.is_some() {
-            match filter.output_data_filter_mode {
-                Some(IndexerSearchMode::Prefix) | None => {
-                    query_builder.and_where_ge("output.data", &format!("${}", param_index));
-                    *param_index += 1;
-                    query_builder.and_where_lt("output.data", &format!("${}", param_index));
-                    *param_index += 1;
-                }
-                Some(IndexerSearchMode::Exact) => {
-                    query_builder.and_where_eq("output.data", format!("${}", param_index));
-                    *param_index += 1;
-                }
-                Some(IndexerSearchMode::Partial) => {
-                    match db_driver {
-                        DBDriver::Postgres => {
-                            query_builder.and_where(format!("output.data LIKE ${}", param_index));
-                        }
-                        DBDriver::Sqlite => {
-                            query_builder
-                                .and_where(format!("instr(output.data, ${}) > 0", param_index));
-                        }
-                    }
-                    *param_index += 1;
-                }
-            }
-        }
+        add_output_data_conditions(
+            db_driver,
+            data_prefix_len,
+            compressed_matches,
+            query_builder,
+            filter,
+            param_index,
+        );
     }
 }
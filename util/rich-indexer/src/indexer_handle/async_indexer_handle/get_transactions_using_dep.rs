@@ -0,0 +1,91 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::{
+    IndexerOrder, IndexerPagination, IndexerTxInfo, JsonBytes, OutPoint, Uint32,
+};
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the transactions which reference the cell of `out_point` as a cell dep, e.g. the
+    /// users of a script code cell.
+    ///
+    /// Only cell deps referring to indexed cells are recorded, so an unknown out point
+    /// returns no transactions.
+    pub async fn get_transactions_using_dep(
+        &self,
+        out_point: OutPoint,
+        order: IndexerOrder,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<IndexerPagination<IndexerTxInfo>, Error> {
+        let limit = limit.value();
+        if limit == 0 {
+            return Err(Error::invalid_params("limit should be greater than 0"));
+        }
+        if limit as usize > self.request_limit {
+            return Err(Error::invalid_params(format!(
+                "limit must be less than {}",
+                self.request_limit,
+            )));
+        }
+        let (operator, direction) = match order {
+            IndexerOrder::Asc => (">", "ASC"),
+            IndexerOrder::Desc => ("<", "DESC"),
+        };
+        let after = after
+            .map(|after| decode_i64(after.as_bytes()))
+            .transpose()?;
+
+        let sql = format!(
+            r#"
+            SELECT DISTINCT
+                ckb_transaction.id,
+                ckb_transaction.tx_hash,
+                ckb_transaction.tx_index,
+                block.block_number
+            FROM
+                tx_association_cell_dep
+            JOIN output ON tx_association_cell_dep.output_id = output.id
+            JOIN ckb_transaction AS dep_transaction ON output.tx_id = dep_transaction.id
+            JOIN ckb_transaction ON tx_association_cell_dep.tx_id = ckb_transaction.id
+            JOIN block ON ckb_transaction.block_id = block.id
+            WHERE
+                dep_transaction.tx_hash = $1
+                AND output.output_index = $2
+                {}
+            ORDER BY ckb_transaction.id {}
+            LIMIT {}
+            "#,
+            after
+                .map(|after| format!("AND ckb_transaction.id {} {}", operator, after))
+                .unwrap_or_default(),
+            direction,
+            limit
+        );
+        let query = SQLXPool::new_query(&sql)
+            .bind(out_point.tx_hash.as_bytes().to_vec())
+            .bind(out_point.index.value() as i32);
+
+        let mut last_cursor = Vec::new();
+        let txs = self
+            .store
+            .fetch_map(query, |row| {
+                last_cursor = row.get::<i64, _>("id").to_le_bytes().to_vec();
+                IndexerTxInfo {
+                    tx_hash: bytes_to_h256(row.get("tx_hash")),
+                    block_number: (row.get::<i64, _>("block_number") as u64).into(),
+                    tx_index: (row.get::<i32, _>("tx_index") as u32).into(),
+                }
+            })
+            .await
+            .map_err(|err| Error::DB(err.to_string()))?;
+
+        Ok(IndexerPagination {
+            objects: txs,
+            last_cursor: JsonBytes::from_vec(last_cursor),
+        })
+    }
+}
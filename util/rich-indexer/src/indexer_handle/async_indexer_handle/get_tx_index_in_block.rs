@@ -0,0 +1,45 @@
+use super::*;
+
+use crate::store::SQLXPool;
+
+use ckb_indexer_sync::Error;
+use ckb_jsonrpc_types::IndexerTxPosition;
+use sqlx::Row;
+
+impl AsyncRichIndexerHandle {
+    /// Get the block and the index of a transaction in it, along with the number of
+    /// transactions of the block, as needed to build a transaction proof.
+    pub async fn get_tx_index_in_block(
+        &self,
+        tx_hash: H256,
+    ) -> Result<Option<IndexerTxPosition>, Error> {
+        let query = SQLXPool::new_query(
+            r#"
+            SELECT
+                block.block_hash,
+                block.transaction_count,
+                ckb_transaction.tx_index
+            FROM
+                ckb_transaction
+            JOIN block ON ckb_transaction.block_id = block.id
+            WHERE
+                ckb_transaction.tx_hash = $1
+            "#,
+        )
+        .bind(tx_hash.as_bytes());
+
+        self.store
+            .fetch_optional(query)
+            .await
+            .map_err(|err| Error::DB(err.to_string()))
+            .map(|row| {
+                row.map(|row| IndexerTxPosition {
+                    block_hash: bytes_to_h256(&row.get::<Vec<u8>, _>("block_hash")),
+                    tx_index: (row.get::<i32, _>("tx_index") as u32).into(),
+                    tx_count: row
+                        .get::<Option<i64>, _>("transaction_count")
+                        .map(|count| (count as u32).into()),
+                })
+            })
+    }
+}
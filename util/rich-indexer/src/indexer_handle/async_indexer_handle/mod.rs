@@ -1,20 +1,42 @@
+mod export_live_cells;
+mod filter_live_out_points;
+mod find_block_gaps;
+mod get_active_address_count;
+mod get_address_activity;
+mod get_asset_types_for_lock;
+mod get_block_commitment;
+mod get_block_range_cell_events;
+mod get_capacity_multi_lock;
+mod get_cell;
 mod get_cells;
+mod get_cells_by_data_hash;
 mod get_cells_capacity;
+mod get_custom_cell_fields;
+mod get_latest_transactions;
+mod get_lock_usage_stats;
+mod get_outputs_spending_status;
+mod get_scripts_by_hashes;
+mod get_transaction_witnesses;
 mod get_transactions;
+mod get_transactions_using_dep;
+mod get_tx_index_in_block;
 
-use crate::indexer::to_fixed_array;
+pub use export_live_cells::ExportFormat;
+
+use crate::indexer::{db_error, decompress_data, is_data_compressed, to_fixed_array};
 use crate::store::SQLXPool;
 
-use ckb_app_config::DBDriver;
+use ckb_app_config::{DBDriver, RichIndexerConfig};
 use ckb_indexer_sync::{Error, Pool};
 use ckb_jsonrpc_types::{
-    IndexerRange, IndexerScriptType, IndexerSearchKey, IndexerSearchKeyFilter, IndexerSearchMode,
-    IndexerTip, JsonBytes,
+    BlockNumber, IndexerRange, IndexerScriptType, IndexerSearchKey, IndexerSearchKeyFilter,
+    IndexerSearchMode, IndexerSyncStatus, IndexerTip, JsonBytes,
 };
 use ckb_types::H256;
+use futures::TryStreamExt;
 use num_bigint::BigUint;
-use sql_builder::SqlBuilder;
-use sqlx::Row;
+use sql_builder::{name, name::SqlName, SqlBuilder};
+use sqlx::{any::Any, Executor, Row};
 
 use std::sync::{Arc, RwLock};
 
@@ -35,6 +57,18 @@ impl AsyncRichIndexerHandle {
             request_limit,
         }
     }
+
+    /// Construct new AsyncRichIndexerHandle instance backed by a read-only connection to the
+    /// database, see `SQLXPool::connect_read_only`.
+    pub async fn new_read_only(
+        db_config: &RichIndexerConfig,
+        pool: Option<Arc<RwLock<Pool>>>,
+        request_limit: usize,
+    ) -> Result<Self, Error> {
+        let mut store = SQLXPool::default();
+        store.connect_read_only(db_config).await.map_err(db_error)?;
+        Ok(Self::new(store, pool, request_limit))
+    }
 }
 
 impl AsyncRichIndexerHandle {
@@ -56,7 +90,24 @@ impl AsyncRichIndexerHandle {
                     block_hash: bytes_to_h256(row.get("block_hash")),
                 })
             })
-            .map_err(|err| Error::DB(err.to_string()))
+            .map_err(db_error)
+    }
+
+    /// Get the indexed tip and how many blocks the indexer is behind the node tip.
+    pub async fn get_sync_status(
+        &self,
+        node_tip_number: BlockNumber,
+    ) -> Result<IndexerSyncStatus, Error> {
+        let indexer_tip = self.get_indexer_tip().await?;
+        let node_tip_number: u64 = node_tip_number.into();
+        let lag = match &indexer_tip {
+            Some(tip) => node_tip_number.saturating_sub(tip.block_number.into()),
+            None => node_tip_number.saturating_add(1),
+        };
+        Ok(IndexerSyncStatus {
+            indexer_tip,
+            lag: lag.into(),
+        })
     }
 }
 
@@ -106,7 +157,7 @@ fn build_query_script_sql(
                 DBDriver::Postgres => {
                     query_builder.and_where(format!("args LIKE ${}", param_index));
                 }
-                DBDriver::Sqlite => {
+                DBDriver::Sqlite | DBDriver::MySQL => {
                     query_builder.and_where(format!("instr(args, ${}) > 0", param_index));
                 }
             }
@@ -147,7 +198,7 @@ fn build_query_script_id_sql(
                 DBDriver::Postgres => {
                     query_builder.and_where(format!("args LIKE ${}", param_index));
                 }
-                DBDriver::Sqlite => {
+                DBDriver::Sqlite | DBDriver::MySQL => {
                     query_builder.and_where(format!("instr(args, ${}) > 0", param_index));
                 }
             }
@@ -162,6 +213,8 @@ fn build_query_script_id_sql(
 
 fn build_cell_filter(
     db_driver: DBDriver,
+    data_prefix_len: Option<usize>,
+    compressed_matches: &[i64],
     query_builder: &mut SqlBuilder,
     search_key: &IndexerSearchKey,
     param_index: &mut usize,
@@ -169,83 +222,362 @@ fn build_cell_filter(
     let filter = convert_max_values_in_search_filter(&search_key.filter);
 
     if let Some(ref filter) = filter {
-        if filter.script.is_some() {
-            match search_key.script_type {
-                IndexerScriptType::Lock => {
-                    query_builder
-                        .and_where_eq("type_script.code_hash", format!("${}", param_index));
-                    *param_index += 1;
-                    query_builder
-                        .and_where_eq("type_script.hash_type", format!("${}", param_index));
-                    *param_index += 1;
-                    query_builder.and_where_ge("type_script.args", &format!("${}", param_index));
-                    *param_index += 1;
-                    query_builder.and_where_lt("type_script.args", &format!("${}", param_index));
-                    *param_index += 1;
-                }
-                IndexerScriptType::Type => {
-                    query_builder
-                        .and_where_eq("lock_script.code_hash", format!("${}", param_index));
-                    *param_index += 1;
-                    query_builder
-                        .and_where_eq("lock_script.hash_type", format!("${}", param_index));
-                    *param_index += 1;
-                    query_builder.and_where_ge("lock_script.args", &format!("${}", param_index));
-                    *param_index += 1;
-                    query_builder.and_where_lt("lock_script.args", &format!("${}", param_index));
-                    *param_index += 1;
-                }
+        add_output_conditions(query_builder, search_key, filter, true, param_index);
+        add_output_data_conditions(
+            db_driver,
+            data_prefix_len,
+            compressed_matches,
+            query_builder,
+            filter,
+            param_index,
+        );
+    }
+}
+
+/// Adds the conditions of the filter on the other script of the output, its capacity and,
+/// with `with_block_range`, the block of the output. The other script is bound by the caller.
+fn add_output_conditions(
+    query_builder: &mut SqlBuilder,
+    search_key: &IndexerSearchKey,
+    filter: &IndexerSearchKeyFilter,
+    with_block_range: bool,
+    param_index: &mut usize,
+) {
+    if filter.script.is_some() {
+        match search_key.script_type {
+            IndexerScriptType::Lock => {
+                query_builder.and_where_eq("type_script.code_hash", format!("${}", param_index));
+                *param_index += 1;
+                query_builder.and_where_eq("type_script.hash_type", format!("${}", param_index));
+                *param_index += 1;
+                query_builder.and_where_ge("type_script.args", &format!("${}", param_index));
+                *param_index += 1;
+                query_builder.and_where_lt("type_script.args", &format!("${}", param_index));
+                *param_index += 1;
             }
-        }
-        if let Some(script_len_range) = &filter.script_len_range {
-            match search_key.script_type {
-                IndexerScriptType::Lock => {
-                    add_filter_script_len_range_conditions(query_builder, "type", script_len_range);
-                }
-                IndexerScriptType::Type => {
-                    add_filter_script_len_range_conditions(query_builder, "lock", script_len_range);
-                }
+            IndexerScriptType::Type => {
+                query_builder.and_where_eq("lock_script.code_hash", format!("${}", param_index));
+                *param_index += 1;
+                query_builder.and_where_eq("lock_script.hash_type", format!("${}", param_index));
+                *param_index += 1;
+                query_builder.and_where_ge("lock_script.args", &format!("${}", param_index));
+                *param_index += 1;
+                query_builder.and_where_lt("lock_script.args", &format!("${}", param_index));
+                *param_index += 1;
             }
         }
-        if let Some(data_len_range) = &filter.output_data_len_range {
-            query_builder.and_where_ge("LENGTH(output.data)", data_len_range.start());
-            query_builder.and_where_lt("LENGTH(output.data)", data_len_range.end());
-        }
-        if let Some(capacity_range) = &filter.output_capacity_range {
-            query_builder.and_where_ge("output.capacity", capacity_range.start());
-            query_builder.and_where_lt("output.capacity", capacity_range.end());
+    }
+    if let Some(script_len_range) = &filter.script_len_range {
+        match search_key.script_type {
+            IndexerScriptType::Lock => {
+                add_filter_script_len_range_conditions(query_builder, "type", script_len_range);
+            }
+            IndexerScriptType::Type => {
+                add_filter_script_len_range_conditions(query_builder, "lock", script_len_range);
+            }
         }
+    }
+    if let Some(capacity_range) = &filter.output_capacity_range {
+        query_builder.and_where_ge("output.capacity", capacity_range.start());
+        query_builder.and_where_lt("output.capacity", capacity_range.end());
+    }
+    if with_block_range {
         if let Some(block_range) = &filter.block_range {
             query_builder.and_where_ge("block.block_number", block_range.start());
             query_builder.and_where_lt("block.block_number", block_range.end());
         }
-        if filter.output_data.is_some() {
-            match filter.output_data_filter_mode {
-                Some(IndexerSearchMode::Prefix) | None => {
-                    query_builder.and_where_ge("output.data", &format!("${}", param_index));
-                    *param_index += 1;
-                    query_builder.and_where_lt("output.data", &format!("${}", param_index));
-                    *param_index += 1;
-                }
-                Some(IndexerSearchMode::Exact) => {
-                    query_builder.and_where_eq("output.data", format!("${}", param_index));
-                    *param_index += 1;
-                }
-                Some(IndexerSearchMode::Partial) => {
-                    match db_driver {
-                        DBDriver::Postgres => {
-                            query_builder.and_where(format!("output.data LIKE ${}", param_index));
-                        }
-                        DBDriver::Sqlite => {
-                            query_builder
-                                .and_where(format!("instr(output.data, ${}) > 0", param_index));
-                        }
+    }
+}
+
+/// Adds the output data and data length conditions of the filter. The data, and the upper
+/// boundary of a prefix search, are bound by the caller in this order, followed by the bounds
+/// of `data_prefix_bounds`.
+///
+/// SQL can only compare the data of uncompressed outputs, the compressed outputs matching the
+/// filter are selected by their ids instead, see `query_compressed_data_matches`.
+fn add_output_data_conditions(
+    db_driver: DBDriver,
+    data_prefix_len: Option<usize>,
+    compressed_matches: &[i64],
+    query_builder: &mut SqlBuilder,
+    filter: &IndexerSearchKeyFilter,
+    param_index: &mut usize,
+) {
+    let mut conditions = Vec::new();
+    if let Some(data_len_range) = &filter.output_data_len_range {
+        conditions.push(format!("LENGTH(output.data) >= {}", data_len_range.start()));
+        conditions.push(format!("LENGTH(output.data) < {}", data_len_range.end()));
+    }
+    if filter.output_data.is_some() {
+        match filter.output_data_filter_mode {
+            Some(IndexerSearchMode::Prefix) | None => {
+                conditions.push(format!("output.data >= ${}", param_index));
+                *param_index += 1;
+                conditions.push(format!("output.data < ${}", param_index));
+                *param_index += 1;
+            }
+            Some(IndexerSearchMode::Exact) => {
+                conditions.push(format!("output.data = ${}", param_index));
+                *param_index += 1;
+            }
+            Some(IndexerSearchMode::Partial) => {
+                match db_driver {
+                    DBDriver::Postgres => {
+                        conditions.push(format!("output.data LIKE ${}", param_index));
+                    }
+                    DBDriver::Sqlite | DBDriver::MySQL => {
+                        conditions.push(format!("instr(output.data, ${}) > 0", param_index));
                     }
-                    *param_index += 1;
                 }
+                *param_index += 1;
             }
         }
     }
+    if conditions.is_empty() {
+        return;
+    }
+
+    let uncompressed_conditions = format!(
+        "COALESCE(output.data_compressed, 0) = 0 AND {}",
+        conditions.join(" AND ")
+    );
+    if compressed_matches.is_empty() {
+        query_builder.and_where(uncompressed_conditions);
+    } else {
+        let ids = compressed_matches
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        query_builder.and_where(format!(
+            "(({}) OR output.id IN ({}))",
+            uncompressed_conditions, ids
+        ));
+    }
+    // the prefix column holds the prefix of the original data, whether compressed or not
+    if data_prefix_bounds(filter, data_prefix_len).is_some() {
+        add_data_prefix_conditions(query_builder, param_index);
+    }
+}
+
+/// Narrows the compressed outputs `query_compressed_data_matches` decompresses to the ones
+/// the calling query can return.
+#[derive(Default)]
+pub(crate) struct CompressedDataScope {
+    /// Only live outputs are returned
+    pub(crate) live_only: bool,
+    /// The block range of the filter applies to the block of the output, rather than to the
+    /// block of the transaction of a row
+    pub(crate) output_block_range: bool,
+    /// A condition on `output`, `ckb_transaction` and `block` which the returned outputs
+    /// satisfy, e.g. the cursor of the page
+    pub(crate) condition: Option<String>,
+    /// The columns the page is ordered by, whether in descending order, and the number of
+    /// matches it can include at most. The page holds the first matches in this order, so the
+    /// scan stops once as many are found.
+    pub(crate) page: Option<(Vec<&'static str>, bool, usize)>,
+}
+
+/// Returns the ids of the compressed outputs of the searched script which match the output
+/// data and data length filters of the search key.
+///
+/// The stored bytes of compressed outputs are meaningless to SQL, so their data is
+/// decompressed and matched here, see `add_output_data_conditions`. Every other filter, and
+/// the scope of the calling query, is applied by SQL first so that only the outputs the query
+/// can return are decompressed.
+async fn query_compressed_data_matches<'c, E>(
+    db_driver: DBDriver,
+    data_prefix_len: Option<usize>,
+    search_key: &IndexerSearchKey,
+    scope: &CompressedDataScope,
+    executor: E,
+) -> Result<Vec<i64>, Error>
+where
+    E: Executor<'c, Database = Any>,
+{
+    let filter = match convert_max_values_in_search_filter(&search_key.filter) {
+        Some(filter) if filter.output_data.is_some() || filter.output_data_len_range.is_some() => {
+            filter
+        }
+        _ => return Ok(Vec::new()),
+    };
+
+    let mut param_index = 1;
+    let script_sub_query_sql =
+        build_query_script_id_sql(db_driver, &search_key.script_search_mode, &mut param_index)?;
+    let mut query_builder = SqlBuilder::select_from("output");
+    query_builder
+        .field("output.id")
+        .field("output.data")
+        .field("output.data_compressed")
+        .join(&format!("{} AS query_script", script_sub_query_sql));
+    match search_key.script_type {
+        IndexerScriptType::Lock => {
+            query_builder.on("output.lock_script_id = query_script.id");
+        }
+        IndexerScriptType::Type => {
+            query_builder.on("output.type_script_id = query_script.id");
+        }
+    }
+    query_builder
+        .join("ckb_transaction")
+        .on("output.tx_id = ckb_transaction.id")
+        .join("block")
+        .on("ckb_transaction.block_id = block.id");
+    if filter.script.is_some() || filter.script_len_range.is_some() {
+        match search_key.script_type {
+            IndexerScriptType::Lock => query_builder
+                .left()
+                .join(name!("script";"type_script"))
+                .on("output.type_script_id = type_script.id"),
+            IndexerScriptType::Type => query_builder
+                .left()
+                .join(name!("script";"lock_script"))
+                .on("output.lock_script_id = lock_script.id"),
+        };
+    }
+    query_builder.and_where("output.data_compressed = 1");
+    if scope.live_only {
+        query_builder.and_where("output.is_spent = 0");
+    }
+    add_output_conditions(
+        &mut query_builder,
+        search_key,
+        &filter,
+        scope.output_block_range,
+        &mut param_index,
+    );
+    let prefix_bounds = data_prefix_bounds(&filter, data_prefix_len);
+    if prefix_bounds.is_some() {
+        add_data_prefix_conditions(&mut query_builder, &mut param_index);
+    }
+    if let Some(condition) = &scope.condition {
+        query_builder.and_where(condition);
+    }
+    if let Some((columns, desc, _)) = &scope.page {
+        for column in columns {
+            query_builder.order_by(column, *desc);
+        }
+    }
+    let sql = query_builder
+        .sql()
+        .map_err(db_error)?
+        .trim_end_matches(';')
+        .to_string();
+
+    // bind
+    let mut query = SQLXPool::new_query(&sql);
+    query = query
+        .bind(search_key.script.code_hash.as_bytes())
+        .bind(search_key.script.hash_type.clone() as i16);
+    match &search_key.script_search_mode {
+        Some(IndexerSearchMode::Prefix) | None => {
+            query = query
+                .bind(search_key.script.args.as_bytes())
+                .bind(get_binary_upper_boundary(search_key.script.args.as_bytes()));
+        }
+        Some(IndexerSearchMode::Exact) => {
+            query = query.bind(search_key.script.args.as_bytes());
+        }
+        Some(IndexerSearchMode::Partial) => match db_driver {
+            DBDriver::Postgres => {
+                let new_args = escape_and_wrap_for_postgres_like(&search_key.script.args);
+                query = query.bind(new_args);
+            }
+            DBDriver::Sqlite | DBDriver::MySQL => {
+                query = query.bind(search_key.script.args.as_bytes());
+            }
+        },
+    }
+    if let Some(script) = filter.script.as_ref() {
+        query = query
+            .bind(script.code_hash.as_bytes())
+            .bind(script.hash_type.clone() as i16)
+            .bind(script.args.as_bytes())
+            .bind(get_binary_upper_boundary(script.args.as_bytes()));
+    }
+    if let Some((lower, upper)) = prefix_bounds {
+        query = query.bind(lower).bind(upper);
+    }
+
+    let max_matches = scope
+        .page
+        .as_ref()
+        .map_or(usize::MAX, |(_, _, max_matches)| *max_matches);
+    let mut matches = Vec::new();
+    let mut rows = query.fetch(executor);
+    while matches.len() < max_matches {
+        let row = match rows.try_next().await.map_err(db_error)? {
+            Some(row) => row,
+            None => break,
+        };
+        let data = decompress_data(
+            row.get::<Option<Vec<u8>>, _>("data").unwrap_or_default(),
+            is_data_compressed(&row),
+        )?;
+        if is_output_data_match(&filter, &data) {
+            matches.push(row.get::<i64, _>("id"));
+        }
+    }
+    Ok(matches)
+}
+
+/// Whether the data of an output matches the output data and data length filters, which
+/// are evaluated like the SQL conditions of `add_output_data_conditions`.
+fn is_output_data_match(filter: &IndexerSearchKeyFilter, data: &[u8]) -> bool {
+    if let Some(data_len_range) = &filter.output_data_len_range {
+        let len = data.len() as u64;
+        if len < data_len_range.start().value() || len >= data_len_range.end().value() {
+            return false;
+        }
+    }
+    let expected = match &filter.output_data {
+        Some(expected) => expected.as_bytes(),
+        None => return true,
+    };
+    match filter.output_data_filter_mode {
+        Some(IndexerSearchMode::Prefix) | None => {
+            data >= expected && data < get_binary_upper_boundary(expected).as_slice()
+        }
+        Some(IndexerSearchMode::Exact) => data == expected,
+        Some(IndexerSearchMode::Partial) => {
+            expected.is_empty()
+                || data
+                    .windows(expected.len())
+                    .any(|window| window == expected)
+        }
+    }
+}
+
+/// Narrows an output data filter with the indexed `output.data_prefix` column, the bound
+/// values are bound by the caller in the same order, see `data_prefix_bounds`.
+fn add_data_prefix_conditions(query_builder: &mut SqlBuilder, param_index: &mut usize) {
+    query_builder.and_where_ge("output.data_prefix", format!("${}", param_index));
+    *param_index += 1;
+    query_builder.and_where_le("output.data_prefix", format!("${}", param_index));
+    *param_index += 1;
+}
+
+/// Returns the inclusive bounds of `output.data_prefix` for the output data filter when the
+/// prefix column is maintained.
+///
+/// Truncating byte strings to the first `data_prefix_len` bytes preserves their order, so the
+/// prefix of any data within the bounds of the filter lies within the truncated bounds. Partial
+/// search has no such bounds and is left to the full data column.
+fn data_prefix_bounds(
+    filter: &IndexerSearchKeyFilter,
+    data_prefix_len: Option<usize>,
+) -> Option<(Vec<u8>, Vec<u8>)> {
+    let len = data_prefix_len?;
+    let data = filter.output_data.as_ref()?.as_bytes();
+    let truncate = |value: &[u8]| value[..value.len().min(len)].to_vec();
+    match filter.output_data_filter_mode {
+        Some(IndexerSearchMode::Prefix) | None => {
+            Some((truncate(data), truncate(&get_binary_upper_boundary(data))))
+        }
+        Some(IndexerSearchMode::Exact) => Some((truncate(data), truncate(data))),
+        Some(IndexerSearchMode::Partial) => None,
+    }
 }
 
 fn get_binary_upper_boundary(value: &[u8]) -> Vec<u8> {
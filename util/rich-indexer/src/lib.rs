@@ -6,7 +6,7 @@ mod indexer_handle;
 mod service;
 mod store;
 
-pub use indexer_handle::{AsyncRichIndexerHandle, RichIndexerHandle};
+pub use indexer_handle::{AsyncRichIndexerHandle, ExportFormat, RichIndexerHandle};
 pub use service::RichIndexerService;
 
 #[cfg(test)]
@@ -2,14 +2,20 @@
 
 use std::usize;
 
-use crate::indexer::RichIndexer;
+use crate::indexer::{
+    backfill_on_startup, db_error, delete_block, prune_spent_outputs, reconcile_spent_flags,
+    RichIndexer, RECONCILE_SPENT_FLAGS_CHUNK_SIZE,
+};
 use crate::store::SQLXPool;
 use crate::{AsyncRichIndexerHandle, RichIndexerHandle};
 
-use ckb_app_config::IndexerConfig;
+use ckb_app_config::{IndexerConfig, RichIndexerConfig};
 use ckb_async_runtime::Handle;
-use ckb_indexer_sync::{CustomFilters, IndexerSyncService, PoolService, SecondaryDB};
+use ckb_indexer_sync::{
+    CustomFilters, Error, IndexerSyncService, PoolService, SecondaryDB, SyncControl,
+};
 use ckb_notify::NotifyController;
+use ckb_types::H256;
 
 pub(crate) const SUBSCRIBER_NAME: &str = "Rich-Indexer";
 
@@ -20,6 +26,7 @@ pub struct RichIndexerService {
     sync: IndexerSyncService,
     block_filter: Option<String>,
     cell_filter: Option<String>,
+    rich_indexer_config: RichIndexerConfig,
     async_handle: Handle,
     request_limit: usize,
 }
@@ -36,6 +43,12 @@ impl RichIndexerService {
         async_handle
             .block_on(store.connect(&config.rich_indexer))
             .expect("Failed to connect to rich-indexer database");
+        async_handle
+            .block_on(backfill_on_startup(
+                &store,
+                config.rich_indexer.auto_migrate,
+            ))
+            .expect("Failed to run the backfills of rich-indexer database");
 
         let sync = IndexerSyncService::new(
             ckb_db,
@@ -49,6 +62,7 @@ impl RichIndexerService {
             sync,
             block_filter: config.block_filter.clone(),
             cell_filter: config.cell_filter.clone(),
+            rich_indexer_config: config.rich_indexer.clone(),
             async_handle,
             request_limit: config.request_limit.unwrap_or(usize::MAX),
         }
@@ -59,6 +73,7 @@ impl RichIndexerService {
             self.store.clone(),
             self.sync.pool(),
             CustomFilters::new(self.block_filter.as_deref(), self.cell_filter.as_deref()),
+            self.rich_indexer_config.clone(),
             self.async_handle.clone(),
             self.request_limit,
         )
@@ -79,6 +94,44 @@ impl RichIndexerService {
             .index_tx_pool(self.get_indexer(), notify_controller)
     }
 
+    /// Returns the handle for pausing and resuming the indexing, e.g. during DB maintenance.
+    pub fn sync_control(&self) -> SyncControl {
+        self.sync.control()
+    }
+
+    /// Removes an indexed block together with the blocks indexed on top of it, so that
+    /// indexing resumes from its parent. Unknown blocks are ignored.
+    pub fn delete_block(&self, block_hash: &H256) -> Result<(), Error> {
+        self.async_handle.block_on(async {
+            let mut tx = self.store.transaction().await.map_err(db_error)?;
+            delete_block(block_hash.as_bytes(), &mut tx).await?;
+            tx.commit().await.map_err(db_error)
+        })
+    }
+
+    /// Corrects the spent flags of outputs which disagree with the recorded inputs, for
+    /// databases written by versions which failed to maintain them. Returns the number of
+    /// corrected outputs; running it again on a consistent database changes nothing.
+    pub fn reconcile_spent_flags(&self) -> Result<u64, Error> {
+        self.async_handle.block_on(reconcile_spent_flags(
+            &self.store,
+            RECONCILE_SPENT_FLAGS_CHUNK_SIZE,
+        ))
+    }
+
+    /// Deletes the cells spent in the blocks below `before_block_number`, along with the
+    /// transactions left without cells, to bound the size of the database. The cells spent
+    /// in the last blocks which may still be rolled back are kept. Returns the number of
+    /// deleted cells.
+    pub fn prune_spent_cells(&self, before_block_number: u64) -> Result<u64, Error> {
+        self.async_handle.block_on(async {
+            let mut tx = self.store.transaction().await.map_err(db_error)?;
+            let pruned = prune_spent_outputs(before_block_number, &mut tx).await?;
+            tx.commit().await.map_err(db_error)?;
+            Ok(pruned)
+        })
+    }
+
     /// Returns a handle to the rich-indexer.
     ///
     /// The returned handle can be used to get data from rich-indexer,
@@ -1,3 +1,5 @@
+use crate::indexer::load_data_prefix_complete;
+
 use anyhow::{anyhow, Result};
 use ckb_app_config::{DBDriver, RichIndexerConfig};
 use futures::TryStreamExt;
@@ -8,15 +10,21 @@ use sqlx::{
     any::{Any, AnyArguments, AnyConnectOptions, AnyPoolOptions, AnyRow},
     migrate::Migrator,
     query::{Query, QueryAs},
-    AnyPool, ConnectOptions, IntoArguments, Row, Transaction,
+    AnyConnection, AnyPool, ConnectOptions, Database, Executor, IntoArguments, Postgres, Row,
+    Sqlite, Transaction,
 };
 use tempfile::tempdir;
 
+use std::collections::HashSet;
 use std::fs::{self, OpenOptions};
 use std::marker::{Send, Unpin};
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::{fmt::Debug, sync::Arc, time::Duration};
+use std::{
+    fmt::Debug,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 const MEMORY_DB: &str = "sqlite://?mode=memory";
 const SQL_SQLITE_CREATE_TABLE: &str = include_str!("../resources/create_sqlite_table.sql");
@@ -24,11 +32,21 @@ const SQL_SQLITE_CREATE_INDEX: &str = include_str!("../resources/create_sqlite_i
 const SQL_POSTGRES_CREATE_TABLE: &str = include_str!("../resources/create_postgres_table.sql");
 const SQL_POSTGRES_CREATE_INDEX: &str = include_str!("../resources/create_postgres_index.sql");
 static MIGRATIONS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/resources/migrations");
+// https://www.sqlite.org/limits.html#max_variable_number, since SQLite 3.32.0
+const SQLITE_MAX_BIND_PARAMS: usize = 32_766;
+// the number of parameters is sent as an Int16 in the Bind message
+const POSTGRES_MAX_BIND_PARAMS: usize = 65_535;
+// the number of parameters of a prepared statement is sent as an unsigned 16-bit integer
+const MYSQL_MAX_BIND_PARAMS: usize = 65_535;
 
 #[derive(Clone, Default)]
 pub struct SQLXPool {
     pool: Arc<OnceCell<AnyPool>>,
     pub(crate) db_driver: DBDriver,
+    pub(crate) data_prefix_len: Option<usize>,
+    /// The prefix length the `data_prefix` column of every output is complete for, shared by
+    /// the clones so that a completed backfill applies to all of them
+    pub(crate) complete_data_prefix_len: Arc<RwLock<Option<usize>>>,
 }
 
 impl Debug for SQLXPool {
@@ -43,19 +61,12 @@ impl SQLXPool {
     pub async fn connect(&mut self, db_config: &RichIndexerConfig) -> Result<()> {
         // if not init, it will panic, see doc for more
         sqlx::any::install_default_drivers();
-        let mut pool_options = AnyPoolOptions::new()
-            .max_connections(10)
-            .min_connections(0)
-            .acquire_timeout(Duration::from_secs(60))
-            .max_lifetime(Duration::from_secs(1800))
-            .idle_timeout(Duration::from_secs(30));
-        if db_config.store == Into::<PathBuf>::into(MEMORY_DB) {
-            // See related issue: https://github.com/launchbadge/sqlx/issues/2510
-            pool_options = pool_options.max_connections(1);
-        }
+        let pool_options = build_pool_options(db_config);
         let pool = match db_config.db_type {
             DBDriver::Sqlite => {
-                create_sqlite(db_config);
+                if db_config.auto_migrate {
+                    create_sqlite(db_config);
+                }
                 let uri = build_url_for_sqlite(db_config);
                 let connection_options =
                     AnyConnectOptions::from_str(&uri)?.log_statements(LevelFilter::Trace);
@@ -64,13 +75,17 @@ impl SQLXPool {
                 self.pool
                     .set(pool.clone())
                     .map_err(|_| anyhow!("set pool failed!"))?;
-                self.create_tables_for_sqlite().await?;
+                if db_config.auto_migrate {
+                    self.create_tables_for_sqlite().await?;
+                }
 
                 self.db_driver = DBDriver::Sqlite;
                 pool
             }
             DBDriver::Postgres => {
-                self.postgres_init(db_config).await?;
+                if db_config.auto_migrate {
+                    self.postgres_init(db_config).await?;
+                }
                 let uri = build_url_for_postgres(db_config);
                 let connection_options =
                     AnyConnectOptions::from_str(&uri)?.log_statements(LevelFilter::Trace);
@@ -80,15 +95,17 @@ impl SQLXPool {
                     .set(pool.clone())
                     .map_err(|_| anyhow!("set pool failed"))?;
 
-                self.create_tables_for_postgres().await?;
+                if db_config.auto_migrate {
+                    self.create_tables_for_postgres().await?;
+                }
 
                 self.db_driver = DBDriver::Postgres;
                 pool
             }
+            DBDriver::MySQL => return Err(mysql_schema_unavailable()),
         };
 
         // Run migrations
-        log::info!("Running migrations...");
         let temp_dir = tempdir()?;
         for file in MIGRATIONS_DIR.files() {
             log::info!("Found migration file: {:?}", file.path());
@@ -99,12 +116,60 @@ impl SQLXPool {
             fs::write(&file_path, file.contents())?;
         }
         let migrator = Migrator::new(temp_dir.path()).await?;
-        migrator.run(&pool).await?;
-        log::info!("Migrations are done.");
+        if db_config.auto_migrate {
+            log::info!("Running migrations...");
+            migrator.run(&pool).await?;
+            log::info!("Migrations are done.");
+        } else {
+            check_schema(&pool, &migrator).await?;
+        }
+        self.data_prefix_len = db_config.data_prefix_len;
+
+        Ok(())
+    }
+
+    /// Connects to an existing database with every connection restricted to reads, so that
+    /// queries served from it can't modify the indexed data. Tables and migrations are left
+    /// to the read-write connection of the indexer.
+    pub async fn connect_read_only(&mut self, db_config: &RichIndexerConfig) -> Result<()> {
+        sqlx::any::install_default_drivers();
+        let (uri, read_only_sql) = match db_config.db_type {
+            DBDriver::Sqlite => (build_url_for_sqlite(db_config), "PRAGMA query_only = ON"),
+            DBDriver::Postgres => (
+                build_url_for_postgres(db_config),
+                "SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY",
+            ),
+            DBDriver::MySQL => return Err(mysql_schema_unavailable()),
+        };
+        let connection_options =
+            AnyConnectOptions::from_str(&uri)?.log_statements(LevelFilter::Trace);
+        let pool = build_pool_options(db_config)
+            .after_connect(move |conn, _meta| {
+                Box::pin(async move { conn.execute(read_only_sql).await.map(|_| ()) })
+            })
+            .connect_with(connection_options)
+            .await?;
+        log::info!("Rich-indexer database is connected read-only.");
+        self.pool
+            .set(pool)
+            .map_err(|_| anyhow!("set pool failed"))?;
+        self.db_driver = db_config.db_type;
+        self.data_prefix_len = db_config.data_prefix_len;
+        load_data_prefix_complete(self)
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?;
 
         Ok(())
     }
 
+    /// Returns the prefix length the `data_prefix` column can narrow queries with, which is
+    /// none until the prefixes of the configured length are complete.
+    pub(crate) fn queryable_data_prefix_len(&self) -> Option<usize> {
+        let complete_len = *self.complete_data_prefix_len.read().expect("acquire lock");
+        self.data_prefix_len
+            .filter(|len| Some(*len) == complete_len)
+    }
+
     pub async fn fetch_count(&self, table_name: &str) -> Result<u64> {
         let pool = self.get_pool()?;
         let sql = format!("SELECT COUNT(*) as count FROM {}", table_name);
@@ -117,6 +182,32 @@ impl SQLXPool {
         sqlx::query(sql)
     }
 
+    /// Returns the maximum number of bind parameters in a single statement on the backend of
+    /// `conn`.
+    pub fn max_bind_params(conn: &AnyConnection) -> usize {
+        match Self::backend_driver(conn) {
+            DBDriver::Sqlite => SQLITE_MAX_BIND_PARAMS,
+            DBDriver::Postgres => POSTGRES_MAX_BIND_PARAMS,
+            DBDriver::MySQL => MYSQL_MAX_BIND_PARAMS,
+        }
+    }
+
+    /// Whether the backend of `conn` is SQLite.
+    pub fn is_sqlite(conn: &AnyConnection) -> bool {
+        conn.backend_name() == <Sqlite as Database>::NAME
+    }
+
+    /// Returns the driver of the backend of `conn`, which decides the SQL dialect of the
+    /// statements built for it.
+    pub fn backend_driver(conn: &AnyConnection) -> DBDriver {
+        match conn.backend_name() {
+            name if name == <Sqlite as Database>::NAME => DBDriver::Sqlite,
+            name if name == <Postgres as Database>::NAME => DBDriver::Postgres,
+            // the only other driver, installed with the `mysql` feature
+            _ => DBDriver::MySQL,
+        }
+    }
+
     pub fn new_query_as<T>(sql: &str) -> QueryAs<Any, T, AnyArguments>
     where
         T: for<'r> sqlx::FromRow<'r, AnyRow>,
@@ -161,6 +252,41 @@ impl SQLXPool {
         Ok(res)
     }
 
+    /// Streams the rows of the query and maps each row as it arrives,
+    /// so that the raw rows are never all held in memory at once.
+    pub async fn fetch_map<'a, T, F, R>(&self, query: Query<'a, Any, T>, mut f: F) -> Result<Vec<R>>
+    where
+        T: Send + IntoArguments<'a, Any> + 'a,
+        F: FnMut(&AnyRow) -> R,
+    {
+        let pool = self.get_pool()?;
+        let mut res = vec![];
+        let mut rows = query.fetch(pool);
+        while let Some(row) = rows.try_next().await? {
+            res.push(f(&row))
+        }
+        Ok(res)
+    }
+
+    /// Streams the rows of the query and feeds each row to `f` as it arrives,
+    /// stopping at the first error.
+    pub async fn fetch_try_for_each<'a, T, F>(
+        &self,
+        query: Query<'a, Any, T>,
+        mut f: F,
+    ) -> Result<()>
+    where
+        T: Send + IntoArguments<'a, Any> + 'a,
+        F: FnMut(&AnyRow) -> Result<()>,
+    {
+        let pool = self.get_pool()?;
+        let mut rows = query.fetch(pool);
+        while let Some(row) = rows.try_next().await? {
+            f(&row)?;
+        }
+        Ok(())
+    }
+
     pub async fn fetch_one_by_query_as<T>(
         &self,
         query: QueryAs<'static, Any, T, AnyArguments<'static>>,
@@ -231,6 +357,52 @@ impl SQLXPool {
     }
 }
 
+/// Checks that the tables exist and every migration has been applied, for databases which
+/// are set up outside of the indexer.
+async fn check_schema(pool: &AnyPool, migrator: &Migrator) -> Result<()> {
+    sqlx::query("SELECT 1 FROM block LIMIT 1")
+        .fetch_optional(pool)
+        .await
+        .map_err(|err| anyhow!("rich-indexer tables are missing: {}", err))?;
+    let applied: HashSet<i64> = sqlx::query("SELECT version FROM _sqlx_migrations")
+        .fetch_all(pool)
+        .await
+        .map_err(|err| anyhow!("rich-indexer migrations are missing: {}", err))?
+        .iter()
+        .map(|row| row.get::<i64, _>("version"))
+        .collect();
+    if let Some(migration) = migrator
+        .iter()
+        .find(|migration| !applied.contains(&migration.version))
+    {
+        return Err(anyhow!(
+            "rich-indexer migration {} ({}) has not been applied",
+            migration.version,
+            migration.description
+        ));
+    }
+    Ok(())
+}
+
+fn build_pool_options(db_config: &RichIndexerConfig) -> AnyPoolOptions {
+    let pool_options = AnyPoolOptions::new()
+        .max_connections(10)
+        .min_connections(0)
+        .acquire_timeout(Duration::from_secs(60))
+        .max_lifetime(Duration::from_secs(1800))
+        .idle_timeout(Duration::from_secs(30));
+    if db_config.store == Into::<PathBuf>::into(MEMORY_DB) {
+        // See related issue: https://github.com/launchbadge/sqlx/issues/2510
+        pool_options.max_connections(1)
+    } else {
+        pool_options
+    }
+}
+
+fn mysql_schema_unavailable() -> anyhow::Error {
+    anyhow!("the rich-indexer tables and queries are not available on MySQL yet")
+}
+
 fn build_url_for_sqlite(db_config: &RichIndexerConfig) -> String {
     db_config.db_type.to_string() + db_config.store.to_str().expect("get store path")
 }
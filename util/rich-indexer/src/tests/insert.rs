@@ -1,16 +1,31 @@
+use std::collections::HashMap;
 use std::usize;
 
 use super::*;
 
+use crate::indexer::{
+    backfill_on_startup, build_bulk_insert_sql, build_on_conflict_clause, bulk_insert,
+    bulk_spend_cells, insert_batch_size, pending_backfills, prune_spent_outputs, query_output_cell,
+    query_output_ids, query_script_id, query_script_ids, reconcile_spent_flags,
+    run_pending_backfills, spend_cell, Backfill, FieldValue, BATCH_SIZE_THRESHOLD,
+    PRUNED_MODE_KEEP_NUM,
+};
+
+use ckb_app_config::{
+    CustomCellField, CustomCellFieldKind, CustomCellSchema, DBDriver, OversizedTransactions,
+    ZeroCapacityCells,
+};
+use ckb_indexer_sync::Error;
 use ckb_types::{
     bytes::Bytes,
     core::{
         capacity_bytes, BlockBuilder, Capacity, EpochNumberWithFraction, HeaderBuilder,
         ScriptHashType, TransactionBuilder,
     },
-    packed::{CellInput, CellOutputBuilder, OutPoint, Script, ScriptBuilder},
+    packed::{Byte32, CellInput, CellOutputBuilder, OutPoint, Script, ScriptBuilder},
     H256,
 };
+use sqlx::Row;
 use tokio::test;
 
 #[test]
@@ -23,6 +38,7 @@ async fn test_append_block_0() {
             Some("block.header.number.to_uint() >= \"0x0\".to_uint()"),
             None,
         ),
+        RichIndexerConfig::default(),
     );
 
     let data_path = String::from(BLOCK_DIR);
@@ -77,6 +93,7 @@ async fn with_custom_block_filter() {
             Some("block.header.number.to_uint() >= \"0x1\".to_uint()"),
             None,
         ),
+        RichIndexerConfig::default(),
     );
     let indexer_handle = AsyncRichIndexerHandle::new(storage, None, usize::MAX);
 
@@ -291,6 +308,7 @@ async fn with_custom_cell_filter() {
             None,
             Some(r#"output.type?.args == "0x747970655f73637269707431""#),
         ),
+        RichIndexerConfig::default(),
     );
     let indexer_handle = AsyncRichIndexerHandle::new(storage, None, usize::MAX);
 
@@ -530,3 +548,2240 @@ async fn with_custom_cell_filter() {
             .len()
     );
 }
+
+#[test]
+async fn empty_args_and_data_reconstruction() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let type_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .build();
+
+    let cell_without_type = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .lock(lock_script.clone())
+        .build();
+    let cell_with_empty_type_args = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(2000).pack())
+        .lock(lock_script)
+        .type_(Some(type_script).pack())
+        .build();
+
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(cell_without_type.clone())
+        .output_data(Default::default())
+        .output(cell_with_empty_type_args.clone())
+        .output_data(Default::default())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let mut tx = storage.transaction().await.unwrap();
+    let (_, output, data) = query_output_cell(&OutPoint::new(cellbase.hash(), 0), &mut tx)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cell_without_type, output);
+    assert!(output.type_().is_none());
+    assert!(data.is_empty());
+
+    let (_, output, data) = query_output_cell(&OutPoint::new(cellbase.hash(), 1), &mut tx)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cell_with_empty_type_args, output);
+    assert!(output.type_().to_opt().unwrap().args().is_empty());
+    assert!(data.is_empty());
+}
+
+#[test]
+async fn zero_capacity_cells() {
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(Capacity::zero().pack())
+                .lock(lock_script.clone())
+                .build(),
+        )
+        .output_data(Default::default())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script)
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+
+    for (mode, expected) in [
+        (ZeroCapacityCells::Store, Some((true, true))),
+        (ZeroCapacityCells::Skip, Some((false, true))),
+        (ZeroCapacityCells::Reject, None),
+    ] {
+        let storage = connect_sqlite(MEMORY_DB).await;
+        let indexer = AsyncRichIndexer::new(
+            storage.clone(),
+            None,
+            CustomFilters::new(None, None),
+            RichIndexerConfig {
+                zero_capacity_cells: mode,
+                ..Default::default()
+            },
+        );
+        let result = indexer.append(&block).await;
+
+        let mut tx = storage.transaction().await.unwrap();
+        let zero_cell = query_output_cell(&OutPoint::new(cellbase.hash(), 0), &mut tx)
+            .await
+            .unwrap();
+        let normal_cell = query_output_cell(&OutPoint::new(cellbase.hash(), 1), &mut tx)
+            .await
+            .unwrap();
+        match expected {
+            Some((has_zero_cell, has_normal_cell)) => {
+                assert!(result.is_ok());
+                assert_eq!(has_zero_cell, zero_cell.is_some());
+                assert_eq!(has_normal_cell, normal_cell.is_some());
+            }
+            None => {
+                assert!(matches!(result, Err(Error::Params(_))));
+                assert!(zero_cell.is_none());
+                assert!(normal_cell.is_none());
+            }
+        }
+    }
+}
+
+#[test]
+async fn cellbase_is_not_an_input() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(lock_script.clone())
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+    };
+
+    let cellbase0 = cellbase(0);
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    // block 1 carries a reward and a transaction spending the previous reward
+    let tx = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(900).pack())
+                .lock(lock_script.clone())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase(1))
+        .transaction(tx)
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    // only the regular transaction has an input
+    assert_eq!(1, storage.fetch_count("input").await.unwrap());
+    assert_eq!(3, storage.fetch_count("output").await.unwrap());
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COUNT(*) AS count FROM output WHERE is_spent = 1",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(1, row.get::<i64, _>("count"));
+
+    // the cellbases are flagged
+    let rows = storage
+        .fetch_all(SQLXPool::new_query(
+            "SELECT tx_index, is_cellbase FROM ckb_transaction ORDER BY id",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![(0, 1), (0, 1), (1, 0)],
+        rows.iter()
+            .map(|row| (
+                row.get::<i32, _>("tx_index"),
+                row.get::<i32, _>("is_cellbase")
+            ))
+            .collect::<Vec<_>>()
+    );
+
+    // the rewards are still counted as receipts
+    let capacity = rpc
+        .get_cells_capacity(IndexerSearchKey {
+            script: lock_script.into(),
+            script_type: IndexerScriptType::Lock,
+            script_search_mode: Some(IndexerSearchMode::Exact),
+            filter: None,
+            with_data: None,
+            group_by_transaction: None,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(capacity_bytes!(1900).as_u64(), capacity.capacity.value());
+}
+
+#[test]
+async fn custom_cell_fields() {
+    let type_code_hash = H256(rand::random());
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig {
+            custom_cell_schemas: vec![CustomCellSchema {
+                code_hash: type_code_hash.clone(),
+                fields: vec![
+                    CustomCellField {
+                        name: "amount".to_string(),
+                        kind: CustomCellFieldKind::Uint128,
+                        offset: 0,
+                    },
+                    CustomCellField {
+                        name: "owner".to_string(),
+                        kind: CustomCellFieldKind::Byte32,
+                        offset: 16,
+                    },
+                ],
+            }],
+            ..Default::default()
+        },
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let owner = H256(rand::random());
+    let mut data = 1_000_000u128.to_le_bytes().to_vec();
+    data.extend_from_slice(owner.as_bytes());
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(Script::default())
+                .type_(
+                    Some(
+                        ScriptBuilder::default()
+                            .code_hash(type_code_hash.pack())
+                            .hash_type(ScriptHashType::Type.into())
+                            .build(),
+                    )
+                    .pack(),
+                )
+                .build(),
+        )
+        .output_data(Bytes::from(data).pack())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(Script::default())
+                .build(),
+        )
+        .output_data(Bytes::from(vec![1u8; 48]).pack())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let fields = rpc
+        .get_custom_cell_fields(OutPoint::new(cellbase.hash(), 0).into())
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            ("amount".to_string(), "1000000".to_string()),
+            ("owner".to_string(), format!("{:#x}", owner)),
+        ],
+        fields
+    );
+    // cells of other types are not extracted
+    assert!(rpc
+        .get_custom_cell_fields(OutPoint::new(cellbase.hash(), 1).into())
+        .await
+        .unwrap()
+        .is_empty());
+
+    indexer.rollback().await.unwrap();
+    assert_eq!(0, storage.fetch_count("custom_cell_field").await.unwrap());
+}
+
+#[test]
+async fn compressed_data_round_trip() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig {
+            data_compression_threshold: Some(1024),
+            ..Default::default()
+        },
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let large_data: Vec<u8> = b"compressible ".repeat(1000);
+    let small_data = vec![7u8; 100];
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(20000).pack())
+                .lock(lock_script.clone())
+                .build(),
+        )
+        .output_data(Bytes::from(large_data.clone()).pack())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script.clone())
+                .build(),
+        )
+        .output_data(Bytes::from(small_data.clone()).pack())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    // only the large data is stored compressed
+    let stored = storage
+        .fetch_all(SQLXPool::new_query(
+            "SELECT LENGTH(data) AS len, data_compressed FROM output ORDER BY output_index",
+        ))
+        .await
+        .unwrap();
+    assert!((stored[0].get::<i64, _>("len") as usize) < large_data.len());
+    assert_eq!(1, stored[0].get::<i32, _>("data_compressed"));
+    assert_eq!(small_data.len(), stored[1].get::<i64, _>("len") as usize);
+    assert_eq!(0, stored[1].get::<i32, _>("data_compressed"));
+
+    // the data is read back byte-identical
+    let mut tx = storage.transaction().await.unwrap();
+    let (_, _, data) = query_output_cell(&OutPoint::new(cellbase.hash(), 0), &mut tx)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(large_data, data.to_vec());
+    drop(tx);
+    let cells = rpc
+        .get_cells(
+            IndexerSearchKey {
+                script: lock_script.into(),
+                script_type: IndexerScriptType::Lock,
+                script_search_mode: Some(IndexerSearchMode::Exact),
+                filter: None,
+                with_data: None,
+                group_by_transaction: None,
+            },
+            IndexerOrder::Asc,
+            10u32.into(),
+            None,
+        )
+        .await
+        .unwrap();
+    let data: Vec<Vec<u8>> = cells
+        .objects
+        .into_iter()
+        .map(|cell| cell.output_data.unwrap().into_bytes().to_vec())
+        .collect();
+    assert_eq!(vec![large_data, small_data], data);
+}
+
+#[test]
+async fn block_commitment() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let block = |number: u64, parent_hash, extension: Vec<u8>| {
+        let cellbase = TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build();
+        BlockBuilder::default()
+            .transaction(cellbase)
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+                    .build(),
+            )
+            .extension(Some(Bytes::from(extension).pack()))
+            .build()
+    };
+
+    // an extension which commits to the chain root is parsed
+    let chain_root = H256(rand::random());
+    let mut extension = chain_root.as_bytes().to_vec();
+    extension.extend_from_slice(&[7; 8]);
+    let block0 = block(0, Byte32::zero(), extension);
+    indexer.append(&block0).await.unwrap();
+    let commitment = rpc
+        .get_block_commitment(block0.hash().unpack())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(chain_root, commitment.chain_root);
+    assert_eq!(vec![7; 8], commitment.extra_data.into_bytes().to_vec());
+
+    // an unknown extension is only stored raw
+    let block1 = block(1, block0.hash(), vec![1; 16]);
+    indexer.append(&block1).await.unwrap();
+    assert!(rpc
+        .get_block_commitment(block1.hash().unpack())
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(1, storage.fetch_count("block_commitment").await.unwrap());
+
+    indexer.rollback().await.unwrap();
+    indexer.rollback().await.unwrap();
+    assert_eq!(0, storage.fetch_count("block_commitment").await.unwrap());
+}
+
+#[test]
+async fn lost_connection_is_reported_as_connection_error() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    indexer
+        .append(&read_block_view(0, String::from(BLOCK_DIR)).into())
+        .await
+        .unwrap();
+
+    // once the pool can no longer hand out connections, the sync loop is told to retry
+    // the block instead of treating it as a failure of the block itself
+    storage.get_pool().unwrap().close().await;
+    let err = indexer
+        .append(&read_block_view(1, String::from(BLOCK_DIR)).into())
+        .await
+        .unwrap_err();
+    assert!(err.is_connection_lost(), "{}", err);
+    assert!(indexer.rollback().await.unwrap_err().is_connection_lost());
+}
+
+#[test]
+async fn pruned_mode() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig {
+            pruned_mode: true,
+            ..Default::default()
+        },
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock = |args: u8| {
+        ScriptBuilder::default()
+            .code_hash(H256([1; 32]).pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![args]).pack())
+            .build()
+    };
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(lock(if number == 0 { 1 } else { 0 }))
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+    };
+    let block = |number: u64, parent_hash: Byte32, txs: Vec<_>| {
+        BlockBuilder::default()
+            .transactions(txs)
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+                    .build(),
+            )
+            .build()
+    };
+    let capacity = |args: u8| {
+        let rpc = rpc.clone();
+        async move {
+            rpc.get_cells_capacity(IndexerSearchKey {
+                script: lock(args).into(),
+                script_type: IndexerScriptType::Lock,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .map(|capacity| capacity.capacity.value())
+        }
+    };
+    let spent_cells = || async {
+        storage
+            .fetch_one(SQLXPool::new_query(
+                "SELECT COUNT(*) AS count FROM output WHERE is_spent = 1",
+            ))
+            .await
+            .unwrap()
+            .get::<i64, _>("count")
+    };
+
+    // the cell of lock 1 created in block 0 is spent to lock 2 in block 1
+    let cellbase0 = cellbase(0);
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(900).pack())
+                .lock(lock(2))
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block0 = block(0, Byte32::zero(), vec![cellbase0]);
+    indexer.append(&block0).await.unwrap();
+    let mut parent_hash = block0.hash();
+    let block1 = block(1, parent_hash, vec![cellbase(1), spend]);
+    indexer.append(&block1).await.unwrap();
+    parent_hash = block1.hash();
+
+    // the spent cell is kept as long as block 1 may be rolled back
+    for number in 2..=(PRUNED_MODE_KEEP_NUM + 1) {
+        let next = block(number, parent_hash, vec![cellbase(number)]);
+        indexer.append(&next).await.unwrap();
+        parent_hash = next.hash();
+    }
+    assert_eq!(1, spent_cells().await);
+    assert_eq!(1, storage.fetch_count("input").await.unwrap());
+
+    let next = block(PRUNED_MODE_KEEP_NUM + 2, parent_hash, vec![cellbase(102)]);
+    indexer.append(&next).await.unwrap();
+    assert_eq!(0, spent_cells().await);
+    assert_eq!(0, storage.fetch_count("input").await.unwrap());
+    // the transaction which created the spent cell is left without cells
+    assert_eq!(
+        PRUNED_MODE_KEEP_NUM + 3,
+        storage.fetch_count("ckb_transaction").await.unwrap()
+    );
+
+    // live cells are still queryable
+    assert_eq!(None, capacity(1).await);
+    assert_eq!(Some(900 * 100_000_000), capacity(2).await);
+    assert_eq!(
+        Some((PRUNED_MODE_KEEP_NUM + 2) * 1000 * 100_000_000),
+        capacity(0).await
+    );
+}
+
+#[test]
+async fn pruned_mode_on_existing_database() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = |pruned_mode: bool| {
+        AsyncRichIndexer::new(
+            storage.clone(),
+            None,
+            CustomFilters::new(None, None),
+            RichIndexerConfig {
+                pruned_mode,
+                ..Default::default()
+            },
+        )
+    };
+
+    // more spent cells than the bind parameters of a single statement
+    let tx = storage.transaction().await.unwrap();
+    let cell_count = SQLXPool::max_bind_params(&tx) + 1;
+    drop(tx);
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .build();
+    let cellbase = |number: u64, output_count: usize| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .outputs((0..output_count).map(|_| cell_output.clone()))
+            .outputs_data((0..output_count).map(|_| Bytes::new().pack()))
+            .build()
+    };
+    let block = |number: u64, parent_hash: Byte32, txs: Vec<_>| {
+        BlockBuilder::default()
+            .transactions(txs)
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+                    .build(),
+            )
+            .build()
+    };
+
+    // the cells created in block 0 are all spent in block 1, before pruned mode is enabled
+    let cellbase0 = cellbase(0, cell_count);
+    let spend = TransactionBuilder::default()
+        .inputs(
+            (0..cell_count as u32)
+                .map(|index| CellInput::new(OutPoint::new(cellbase0.hash(), index), 0)),
+        )
+        .output(cell_output.clone())
+        .output_data(Default::default())
+        .build();
+    let block0 = block(0, Byte32::zero(), vec![cellbase0]);
+    indexer(false).append(&block0).await.unwrap();
+    let block1 = block(1, block0.hash(), vec![cellbase(1, 1), spend]);
+    indexer(false).append(&block1).await.unwrap();
+    let mut parent_hash = block1.hash();
+    for number in 2..=(PRUNED_MODE_KEEP_NUM + 1) {
+        let next = block(number, parent_hash, vec![cellbase(number, 1)]);
+        indexer(false).append(&next).await.unwrap();
+        parent_hash = next.hash();
+    }
+    assert_eq!(
+        cell_count as u64,
+        storage.fetch_count("input").await.unwrap()
+    );
+
+    let next = block(
+        PRUNED_MODE_KEEP_NUM + 2,
+        parent_hash,
+        vec![cellbase(102, 1)],
+    );
+    indexer(true).append(&next).await.unwrap();
+    assert_eq!(0, storage.fetch_count("input").await.unwrap());
+    // the cellbase outputs of blocks 1 and above and the output of the spending transaction
+    assert_eq!(
+        PRUNED_MODE_KEEP_NUM + 3,
+        storage.fetch_count("output").await.unwrap()
+    );
+    // the cellbase of block 0 is left without cells
+    assert_eq!(
+        PRUNED_MODE_KEEP_NUM + 3,
+        storage.fetch_count("ckb_transaction").await.unwrap()
+    );
+}
+
+#[test]
+async fn prune_spent_outputs_before_block() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .build();
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(cell_output.clone())
+            .output_data(Default::default())
+            .build()
+    };
+    let spend = |out_point: OutPoint| {
+        TransactionBuilder::default()
+            .input(CellInput::new(out_point, 0))
+            .output(cell_output.clone())
+            .output_data(Default::default())
+            .build()
+    };
+    let block = |number: u64, parent_hash: Byte32, txs: Vec<_>| {
+        BlockBuilder::default()
+            .transactions(txs)
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+                    .build(),
+            )
+            .build()
+    };
+    let spent_cells = || async {
+        storage
+            .fetch_one(SQLXPool::new_query(
+                "SELECT COUNT(*) AS count FROM output WHERE is_spent = 1",
+            ))
+            .await
+            .unwrap()
+            .get::<i64, _>("count")
+    };
+    let prune = |block_number: u64| {
+        let storage = storage.clone();
+        async move {
+            let mut tx = storage.transaction().await.unwrap();
+            let pruned = prune_spent_outputs(block_number, &mut tx).await.unwrap();
+            tx.commit().await.unwrap();
+            pruned
+        }
+    };
+
+    // the cellbase output of block 0 is spent in block 1, whose output is spent in block 2
+    let cellbase0 = cellbase(0);
+    let spend1 = spend(OutPoint::new(cellbase0.hash(), 0));
+    let spend2 = spend(OutPoint::new(spend1.hash(), 0));
+    let block0 = block(0, Byte32::zero(), vec![cellbase0]);
+    indexer.append(&block0).await.unwrap();
+    let block1 = block(1, block0.hash(), vec![cellbase(1), spend1]);
+    indexer.append(&block1).await.unwrap();
+    let block2 = block(2, block1.hash(), vec![cellbase(2), spend2.clone()]);
+    indexer.append(&block2).await.unwrap();
+    let mut parent_hash = block2.hash();
+    for number in 3..=(PRUNED_MODE_KEEP_NUM + 3) {
+        let next = block(number, parent_hash, vec![cellbase(number)]);
+        indexer.append(&next).await.unwrap();
+        parent_hash = next.hash();
+    }
+    let outputs = storage.fetch_count("output").await.unwrap();
+    assert_eq!(2, spent_cells().await);
+
+    // only the cell spent below block 2 is pruned
+    assert_eq!(1, prune(2).await);
+    assert_eq!(1, spent_cells().await);
+    assert_eq!(1, storage.fetch_count("input").await.unwrap());
+    assert_eq!(outputs - 1, storage.fetch_count("output").await.unwrap());
+
+    // the threshold is capped at the blocks which may still be rolled back
+    assert_eq!(1, prune(u64::MAX).await);
+    assert_eq!(0, spent_cells().await);
+    assert_eq!(outputs - 2, storage.fetch_count("output").await.unwrap());
+    assert_eq!(0, prune(u64::MAX).await);
+
+    // the live output of the last spending transaction survives
+    let mut tx = storage.transaction().await.unwrap();
+    let live = query_output_ids(&[OutPoint::new(spend2.hash(), 0)], &mut tx)
+        .await
+        .unwrap();
+    drop(tx);
+    assert_eq!(1, live.len());
+}
+
+#[test]
+async fn ignored_code_hashes() {
+    let ignored_code_hash = H256([9; 32]);
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig {
+            ignored_code_hashes: vec![ignored_code_hash.clone()],
+            ..Default::default()
+        },
+    );
+
+    let cell_output = |code_hash: &H256| {
+        CellOutputBuilder::default()
+            .capacity(capacity_bytes!(1000).pack())
+            .lock(
+                ScriptBuilder::default()
+                    .code_hash(code_hash.pack())
+                    .hash_type(ScriptHashType::Type.into())
+                    .build(),
+            )
+            .build()
+    };
+    let normal_code_hash = H256([1; 32]);
+
+    // the first output of the cellbase is under an ignored lock
+    let cellbase0 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs(vec![
+            cell_output(&ignored_code_hash),
+            cell_output(&normal_code_hash),
+        ])
+        .outputs_data(vec![Default::default(); 2])
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+    assert_eq!(1, storage.fetch_count("output").await.unwrap());
+
+    // spending the ignored cell doesn't stop the following input from being spent
+    let cellbase1 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .witness(Script::default().into_witness())
+        .output(cell_output(&ignored_code_hash))
+        .output_data(Default::default())
+        .build();
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 1), 0))
+        .output(cell_output(&normal_code_hash))
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase1, spend.clone()])
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    assert_eq!(2, storage.fetch_count("output").await.unwrap());
+    assert_eq!(1, storage.fetch_count("input").await.unwrap());
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COUNT(*) AS count FROM output WHERE is_spent = 1",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(1, row.get::<i64, _>("count"));
+    let mut tx = storage.transaction().await.unwrap();
+    assert!(query_output_cell(&OutPoint::new(spend.hash(), 0), &mut tx)
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+async fn corrupted_code_hash() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256([1; 32]).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let type_script = ScriptBuilder::default()
+        .code_hash(H256([2; 32]).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script.clone())
+                .type_(Some(type_script.clone()).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    // truncate the stored code hash of the lock script
+    sqlx::query("UPDATE script SET code_hash = $1 WHERE code_hash = $2")
+        .bind(vec![1u8; 20])
+        .bind(vec![1u8; 32])
+        .execute(storage.get_pool().unwrap())
+        .await
+        .unwrap();
+
+    let mut tx = storage.transaction().await.unwrap();
+    let err = query_output_cell(&OutPoint::new(cellbase.hash(), 0), &mut tx)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("corrupted code_hash of 20 bytes"));
+    drop(tx);
+
+    let result = rpc
+        .get_cells(
+            IndexerSearchKey {
+                script: type_script.into(),
+                script_type: IndexerScriptType::Type,
+                ..Default::default()
+            },
+            IndexerOrder::Asc,
+            100u32.into(),
+            None,
+        )
+        .await;
+    assert!(matches!(result, Err(err) if err.to_string().contains("corrupted code_hash")));
+
+    let err = rpc
+        .get_scripts_by_hashes(vec![lock_script.calc_script_hash().unpack()])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("corrupted code_hash"));
+}
+
+#[test]
+async fn witness_metrics() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Bytes::from(vec![0u8; 10]).pack())
+        .witness(Bytes::new().pack())
+        .witness(Bytes::from(vec![1u8; 65]).pack())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase)
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT witness_count, witnesses_total_bytes FROM ckb_transaction",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(3, row.get::<i32, _>("witness_count"));
+    assert_eq!(75, row.get::<i64, _>("witnesses_total_bytes"));
+}
+
+#[test]
+async fn failed_append_leaves_no_partial_block() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig {
+            zero_capacity_cells: ZeroCapacityCells::Reject,
+            ..Default::default()
+        },
+    );
+
+    let cell_output = |capacity: Capacity| {
+        CellOutputBuilder::default()
+            .capacity(capacity.pack())
+            .lock(
+                ScriptBuilder::default()
+                    .code_hash(H256([1; 32]).pack())
+                    .hash_type(ScriptHashType::Type.into())
+                    .build(),
+            )
+            .build()
+    };
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(cell_output(capacity_bytes!(1000)))
+            .output_data(Default::default())
+            .build()
+    };
+    let header = |number: u64, parent_hash: Byte32| {
+        HeaderBuilder::default()
+            .number(number.pack())
+            .parent_hash(parent_hash)
+            .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+            .build()
+    };
+
+    let cellbase0 = cellbase(0);
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    let tables = [
+        "block",
+        "ckb_transaction",
+        "output",
+        "input",
+        "script",
+        "block_association_proposal",
+    ];
+    let mut counts = Vec::new();
+    for table in tables {
+        counts.push(storage.fetch_count(table).await.unwrap());
+    }
+
+    // the spend is indexed before the zero-capacity output of the last transaction fails
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .output(cell_output(capacity_bytes!(1000)))
+        .output_data(Default::default())
+        .build();
+    let zero_capacity = TransactionBuilder::default()
+        .output(cell_output(Capacity::zero()))
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase(1), spend.clone(), zero_capacity])
+        .header(header(1, block0.hash()))
+        .build();
+    assert!(indexer.append(&block1).await.is_err());
+
+    for (table, count) in tables.iter().zip(counts) {
+        assert_eq!(
+            count,
+            storage.fetch_count(table).await.unwrap(),
+            "{}",
+            table
+        );
+    }
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT is_spent FROM output WHERE output_index = 0",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(0, row.get::<i32, _>("is_spent"));
+
+    // indexing resumes from the last complete block
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase(1), spend])
+        .header(header(1, block0.hash()))
+        .build();
+    indexer.append(&block1).await.unwrap();
+    indexer.append(&block1).await.unwrap();
+    assert_eq!(2, storage.fetch_count("block").await.unwrap());
+    assert_eq!(1, storage.fetch_count("input").await.unwrap());
+}
+
+#[test]
+async fn auto_migrate() {
+    let dir = tempfile::tempdir().unwrap();
+    let manual = RichIndexerConfig {
+        store: dir.path().join("indexer.db"),
+        auto_migrate: false,
+        ..Default::default()
+    };
+    let auto = RichIndexerConfig {
+        auto_migrate: true,
+        ..manual.clone()
+    };
+
+    // nothing is created on a fresh database
+    assert!(SQLXPool::default().connect(&manual).await.is_err());
+    assert!(!manual.store.exists());
+    std::fs::File::create(&manual.store).unwrap();
+    let err = SQLXPool::default().connect(&manual).await.unwrap_err();
+    assert!(err.to_string().contains("tables are missing"));
+
+    let mut storage = SQLXPool::default();
+    storage.connect(&auto).await.unwrap();
+    assert_eq!(0, storage.fetch_count("block").await.unwrap());
+    SQLXPool::default().connect(&manual).await.unwrap();
+
+    // a pending migration is reported
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = 20241022")
+        .execute(storage.get_pool().unwrap())
+        .await
+        .unwrap();
+    let err = SQLXPool::default().connect(&manual).await.unwrap_err();
+    assert!(err.to_string().contains("20241022"));
+}
+
+#[test]
+async fn backfills_run_once_with_auto_migrate() {
+    let mut storage = connect_sqlite(MEMORY_DB).await;
+    insert_blocks(storage.clone()).await;
+    storage.data_prefix_len = Some(4);
+
+    let clear_columns = || async {
+        for sql in [
+            "UPDATE script SET script_hash = NULL",
+            "UPDATE output SET data_hash = NULL",
+        ] {
+            sqlx::query(sql)
+                .execute(storage.get_pool().unwrap())
+                .await
+                .unwrap();
+        }
+    };
+    let count_missing = || async {
+        let mut missing = 0;
+        for sql in [
+            "SELECT COUNT(*) FROM script WHERE script_hash IS NULL",
+            "SELECT COUNT(*) FROM output WHERE data_hash IS NULL",
+            "SELECT COUNT(*) FROM output WHERE data_prefix IS NULL",
+        ] {
+            missing += sqlx::query(sql)
+                .fetch_one(storage.get_pool().unwrap())
+                .await
+                .unwrap()
+                .get::<i64, _>(0);
+        }
+        missing
+    };
+    let completed = || async {
+        sqlx::query("SELECT name FROM backfill ORDER BY name")
+            .fetch_all(storage.get_pool().unwrap())
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect::<Vec<String>>()
+    };
+    clear_columns().await;
+    assert!(count_missing().await > 0);
+
+    // the database is left untouched without auto_migrate
+    backfill_on_startup(&storage, false).await.unwrap();
+    assert!(count_missing().await > 0);
+    assert!(completed().await.is_empty());
+
+    backfill_on_startup(&storage, true).await.unwrap();
+    assert_eq!(0, count_missing().await);
+    assert_eq!(
+        vec!["data_hash", "data_prefix_4", "script_hash", "since_fields"],
+        completed().await
+    );
+    assert!(pending_backfills(&storage).await.unwrap().is_empty());
+
+    // completed backfills don't run again on later startups
+    clear_columns().await;
+    backfill_on_startup(&storage, true).await.unwrap();
+    assert!(count_missing().await > 0);
+}
+
+#[test]
+async fn bulk_spend_cells_in_chunks() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let output_count = BATCH_SIZE_THRESHOLD + 500;
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs((0..output_count).map(|_| {
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build()
+        }))
+        .outputs_data((0..output_count).map(|_| Bytes::new().pack()))
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    // every other cell, plus out points which are not indexed
+    let mut out_points: Vec<OutPoint> = (0..output_count as u32)
+        .step_by(2)
+        .map(|index| OutPoint::new(cellbase.hash(), index))
+        .collect();
+    out_points.push(OutPoint::new(cellbase.hash(), output_count as u32));
+    out_points.push(OutPoint::new(Byte32::zero(), 0));
+
+    let mut tx = storage.transaction().await.unwrap();
+    let updated = bulk_spend_cells(&out_points, &mut tx).await.unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(output_count as u64 / 2, updated);
+
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COUNT(*) AS count FROM output WHERE is_spent = 1 AND output_index % 2 = 0",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(output_count as i64 / 2, row.get::<i64, _>("count"));
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COUNT(*) AS count FROM output WHERE is_spent = 1",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(output_count as i64 / 2, row.get::<i64, _>("count"));
+
+    // the single cell wrapper tells whether the cell is indexed
+    let mut tx = storage.transaction().await.unwrap();
+    assert!(spend_cell(&OutPoint::new(cellbase.hash(), 1), &mut tx)
+        .await
+        .unwrap());
+    assert!(!spend_cell(&OutPoint::new(Byte32::zero(), 1), &mut tx)
+        .await
+        .unwrap());
+    tx.commit().await.unwrap();
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COUNT(*) AS count FROM output WHERE is_spent = 1",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(output_count as i64 / 2 + 1, row.get::<i64, _>("count"));
+}
+
+#[test]
+async fn query_output_ids_in_chunks() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let output_count = 2_500;
+    assert!(output_count > BATCH_SIZE_THRESHOLD * 2);
+    let cellbase0 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs((0..output_count).map(|_| {
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build()
+        }))
+        .outputs_data((0..output_count).map(|_| Bytes::new().pack()))
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    let mut out_points: Vec<OutPoint> = (0..output_count as u32)
+        .map(|index| OutPoint::new(cellbase0.hash(), index))
+        .collect();
+    let mut tx = storage.transaction().await.unwrap();
+    let mut lookup = out_points.clone();
+    lookup.push(OutPoint::new(cellbase0.hash(), output_count as u32));
+    lookup.push(OutPoint::new(Byte32::zero(), 0));
+    let output_ids = query_output_ids(&lookup, &mut tx).await.unwrap();
+    drop(tx);
+    assert_eq!(output_count, output_ids.len());
+    let mut ids: Vec<i64> = output_ids.values().copied().collect();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(output_count, ids.len());
+
+    // a transaction spending every output resolves all of its inputs
+    let cellbase1 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    out_points.reverse();
+    let tx1 = TransactionBuilder::default()
+        .inputs(
+            out_points
+                .iter()
+                .map(|out_point| CellInput::new(out_point.clone(), 0)),
+        )
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase1)
+        .transaction(tx1)
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let sql = format!(
+        r#"
+        SELECT COUNT(*) AS count
+        FROM input
+        JOIN output ON input.output_id = output.id
+        WHERE output.output_index = {} - input.input_index
+        "#,
+        output_count - 1
+    );
+    let row = storage.fetch_one(SQLXPool::new_query(&sql)).await.unwrap();
+    assert_eq!(output_count as i64, row.get::<i64, _>("count"));
+}
+
+#[test]
+async fn append_block_with_uncles() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let cellbase = |number| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+    };
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase(0))
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    let uncles: Vec<_> = (1..=2u128)
+        .map(|nonce| {
+            BlockBuilder::default()
+                .transaction(cellbase(1))
+                .header(
+                    HeaderBuilder::default()
+                        .number(1.pack())
+                        .parent_hash(block0.hash())
+                        .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                        .nonce(nonce.pack())
+                        .build(),
+                )
+                .build()
+                .as_uncle()
+        })
+        .collect();
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase(1))
+        .uncles(uncles.clone())
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    assert_eq!(4, storage.fetch_count("block").await.unwrap());
+    // the uncles are inserted first, so the row with the maximum ID is the tip block
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT id, block_hash FROM block ORDER BY id DESC LIMIT 1",
+        ))
+        .await
+        .unwrap();
+    let block1_id = row.get::<i64, _>("id");
+    assert_eq!(
+        block1.hash().raw_data().to_vec(),
+        row.get::<Vec<u8>, _>("block_hash")
+    );
+    let tip = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX)
+        .get_indexer_tip()
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(block1.hash(), tip.block_hash.pack());
+
+    let rows = storage
+        .fetch_all(
+            SQLXPool::new_query(
+                r#"
+                SELECT block.block_hash, block.id
+                FROM block_association_uncle
+                JOIN block ON block_association_uncle.uncle_id = block.id
+                WHERE block_association_uncle.block_id = $1
+                ORDER BY block.id
+                "#,
+            )
+            .bind(block1_id),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        uncles
+            .iter()
+            .map(|uncle| uncle.hash().raw_data().to_vec())
+            .collect::<Vec<_>>(),
+        rows.iter()
+            .map(|row| row.get::<Vec<u8>, _>("block_hash"))
+            .collect::<Vec<_>>()
+    );
+    assert!(rows.iter().all(|row| row.get::<i64, _>("id") < block1_id));
+}
+
+#[test]
+async fn out_point_filter() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let config = RichIndexerConfig {
+        out_point_filter_capacity: Some(1_000),
+        ..Default::default()
+    };
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        config.clone(),
+    );
+
+    let cellbase = |number, outputs: usize| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .outputs((0..outputs).map(|_| {
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .build()
+            }))
+            .outputs_data((0..outputs).map(|_| Bytes::new().pack()))
+            .build()
+    };
+    let cellbase0 = cellbase(0, 3);
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    let known: Vec<_> = (0..3)
+        .map(|index| OutPoint::new(cellbase0.hash(), index))
+        .collect();
+    let unknown = [
+        OutPoint::new(cellbase0.hash(), 3),
+        OutPoint::new(Byte32::zero(), 0),
+        OutPoint::new(h256!("0x1").pack(), 1),
+    ];
+    let out_points = known.iter().chain(unknown.iter()).cloned();
+    assert_eq!(known, indexer.filter_known_out_points(out_points.clone()));
+
+    // a restarted indexer loads the filter from the indexed outputs before appending
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        config,
+    );
+    let tx1 = TransactionBuilder::default()
+        .input(CellInput::new(known[1].clone(), 0))
+        .input(CellInput::new(unknown[1].clone(), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase(1, 1))
+        .transaction(tx1.clone())
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let mut expected = known.clone();
+    expected.push(OutPoint::new(tx1.hash(), 0));
+    let out_points = out_points.chain(std::iter::once(OutPoint::new(tx1.hash(), 0)));
+    assert_eq!(expected, indexer.filter_known_out_points(out_points));
+
+    // the known input still resolves to its output
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            r#"
+                SELECT input.input_index, output.output_index
+                FROM input
+                JOIN output ON input.output_id = output.id
+                "#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(0, row.get::<i32, _>("input_index"));
+    assert_eq!(1, row.get::<i32, _>("output_index"));
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COUNT(*) AS count FROM output WHERE is_spent = 1",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(1, row.get::<i64, _>("count"));
+}
+
+#[test]
+async fn block_header_fields() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let cellbase = |number| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+    };
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase(0))
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+    let epoch = EpochNumberWithFraction::new(1234, 567, 1800);
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase(1))
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .timestamp(1_700_000_000_000.pack())
+                .epoch(epoch.pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let row = storage
+        .fetch_one(
+            SQLXPool::new_query(
+                r#"
+                SELECT timestamp, epoch, parent_hash, transactions_root, proposals_hash
+                FROM block
+                WHERE block_hash = $1
+                "#,
+            )
+            .bind(block1.hash().raw_data().to_vec()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(1_700_000_000_000, row.get::<i64, _>("timestamp"));
+    let stored_epoch = EpochNumberWithFraction::from_full_value(u64::from_be_bytes(
+        row.get::<Vec<u8>, _>("epoch").try_into().unwrap(),
+    ));
+    assert_eq!(1234, stored_epoch.number());
+    assert_eq!(567, stored_epoch.index());
+    assert_eq!(1800, stored_epoch.length());
+    assert_eq!(
+        block0.hash().raw_data().to_vec(),
+        row.get::<Vec<u8>, _>("parent_hash")
+    );
+    assert_eq!(
+        block1.transactions_root().raw_data().to_vec(),
+        row.get::<Vec<u8>, _>("transactions_root")
+    );
+    assert_eq!(
+        block1.proposals_hash().raw_data().to_vec(),
+        row.get::<Vec<u8>, _>("proposals_hash")
+    );
+}
+
+#[test]
+async fn oversized_transactions() {
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let oversized_tx = TransactionBuilder::default()
+        .inputs((0..3).map(|index| CellInput::new(OutPoint::new(Byte32::zero(), index), 0)))
+        .outputs((0..3).map(|_| {
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build()
+        }))
+        .outputs_data((0..3).map(|_| Bytes::new().pack()))
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase)
+        .transaction(oversized_tx.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+
+    for (max_tx_inputs, max_tx_outputs) in [(2, 3), (3, 2)] {
+        for mode in [OversizedTransactions::Warn, OversizedTransactions::Reject] {
+            let storage = connect_sqlite(MEMORY_DB).await;
+            let indexer = AsyncRichIndexer::new(
+                storage.clone(),
+                None,
+                CustomFilters::new(None, None),
+                RichIndexerConfig {
+                    max_tx_inputs,
+                    max_tx_outputs,
+                    oversized_transactions: mode,
+                    ..Default::default()
+                },
+            );
+            let result = indexer.append(&block).await;
+
+            let mut tx = storage.transaction().await.unwrap();
+            let output = query_output_cell(&OutPoint::new(oversized_tx.hash(), 2), &mut tx)
+                .await
+                .unwrap();
+            if mode == OversizedTransactions::Warn {
+                assert!(result.is_ok());
+                assert!(output.is_some());
+            } else {
+                assert!(matches!(result, Err(Error::Params(_))));
+                assert!(output.is_none());
+                drop(tx);
+                assert_eq!(0, storage.fetch_count("block").await.unwrap());
+            }
+        }
+    }
+}
+
+#[test]
+async fn reconcile_stale_spent_flags() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let cellbase0 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs((0..6).map(|_| {
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build()
+        }))
+        .outputs_data((0..6).map(|_| Bytes::new().pack()))
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+    // the even outputs are spent in block 1
+    let spend = TransactionBuilder::default()
+        .inputs(
+            (0..6)
+                .step_by(2)
+                .map(|index| CellInput::new(OutPoint::new(cellbase0.hash(), index), 0)),
+        )
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let cellbase1 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .witness(Script::default().into_witness())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase1)
+        .transaction(spend)
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let spent_indexes = || async {
+        storage
+            .fetch_all(SQLXPool::new_query(
+                r#"
+                SELECT output.output_index
+                FROM output
+                JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+                WHERE output.is_spent = 1 AND ckb_transaction.tx_index = 0
+                ORDER BY output.output_index
+                "#,
+            ))
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get::<i32, _>("output_index"))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec![0, 2, 4], spent_indexes().await);
+    assert_eq!(0, reconcile_spent_flags(&storage, 2).await.unwrap());
+
+    // stale flags in both directions, and a flag left unset
+    let mut tx = storage.transaction().await.unwrap();
+    SQLXPool::new_query(
+        r#"
+        UPDATE output
+        SET is_spent = CASE output_index WHEN 0 THEN 0 WHEN 1 THEN 1 ELSE NULL END
+        WHERE output_index IN (0, 1, 2)
+        AND tx_id = (SELECT id FROM ckb_transaction WHERE tx_hash = $1)
+        "#,
+    )
+    .bind(cellbase0.hash().raw_data().to_vec())
+    .execute(tx.as_mut())
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(vec![1, 4], spent_indexes().await);
+
+    assert_eq!(3, reconcile_spent_flags(&storage, 2).await.unwrap());
+    assert_eq!(vec![0, 2, 4], spent_indexes().await);
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COUNT(*) AS count FROM output WHERE is_spent IS NULL",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(0, row.get::<i64, _>("count"));
+    // idempotent
+    assert_eq!(0, reconcile_spent_flags(&storage, 2).await.unwrap());
+}
+
+#[test]
+async fn query_script_id_by_hash() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(vec![7; 1024]).pack())
+        .build();
+    let type_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .args(Bytes::from(vec![8; 20]).pack())
+        .build();
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script.clone())
+                .type_(Some(type_script.clone()).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase)
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT lock_script_id, type_script_id FROM output",
+        ))
+        .await
+        .unwrap();
+    let mut tx = storage.transaction().await.unwrap();
+    for (script, id) in [
+        (lock_script, row.get::<i64, _>("lock_script_id")),
+        (type_script, row.get::<i64, _>("type_script_id")),
+    ] {
+        let script_hash = script.calc_script_hash().raw_data().to_vec();
+        let stored_hash = SQLXPool::new_query("SELECT script_hash FROM script WHERE id = $1")
+            .bind(id)
+            .fetch_one(tx.as_mut())
+            .await
+            .unwrap()
+            .get::<Vec<u8>, _>("script_hash");
+        assert_eq!(script_hash, stored_hash);
+        let script_ids = query_script_ids(&[script_hash.clone(), vec![0; 32]], &mut tx)
+            .await
+            .unwrap();
+        assert_eq!(HashMap::from([(script_hash, id)]), script_ids);
+        assert_eq!(Some(id), query_script_id(&script, &mut tx).await.unwrap());
+    }
+    assert_eq!(
+        None,
+        query_script_id(&Script::default(), &mut tx).await.unwrap()
+    );
+}
+
+#[test]
+async fn bulk_insert_wide_table() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+
+    // wide enough that a fixed batch of `BATCH_SIZE_THRESHOLD` rows would exceed the limit
+    let field_count = 50;
+    let row_count = 2_000;
+    let fields: Vec<String> = (0..field_count).map(|i| format!("f{}", i)).collect();
+    let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+    let columns: Vec<String> = fields
+        .iter()
+        .map(|field| format!("{} BIGINT", field))
+        .collect();
+    let mut tx = storage.transaction().await.unwrap();
+    SQLXPool::new_query(&format!("CREATE TABLE wide ({})", columns.join(", ")))
+        .execute(tx.as_mut())
+        .await
+        .unwrap();
+
+    let batch_size = insert_batch_size(field_count, &tx);
+    assert!(batch_size < BATCH_SIZE_THRESHOLD);
+    assert!(batch_size * field_count <= SQLXPool::max_bind_params(&tx));
+    // narrow tables are only bounded by the row cap of SQLite
+    assert_eq!(BATCH_SIZE_THRESHOLD, insert_batch_size(2, &tx));
+
+    let rows: Vec<Vec<FieldValue>> = (0..row_count)
+        .map(|row| {
+            (0..field_count)
+                .map(|column| FieldValue::BigInt((row * field_count + column) as i64))
+                .collect()
+        })
+        .collect();
+    bulk_insert("wide", &fields, &rows, None, None, &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    let row = storage
+        .fetch_one(SQLXPool::new_query(
+            "SELECT COUNT(*) AS count, SUM(f49) AS f49 FROM wide",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(row_count as i64, row.get::<i64, _>("count"));
+    let f49: i64 = (0..row_count)
+        .map(|row| (row * field_count + 49) as i64)
+        .sum();
+    assert_eq!(f49, row.get::<i64, _>("f49"));
+}
+
+#[test]
+async fn bulk_insert_on_conflict() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let fields = ["output_id", "field_name", "field_value"];
+    let row = |output_id: i64, field_name: &str, field_value: &str| {
+        vec![
+            FieldValue::BigInt(output_id),
+            FieldValue::Text(field_name.to_string()),
+            FieldValue::Text(field_value.to_string()),
+        ]
+    };
+    let field_values = || async {
+        storage
+            .fetch_all(SQLXPool::new_query(
+                "SELECT field_name, field_value FROM custom_cell_field ORDER BY field_name",
+            ))
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<String, _>("field_name"),
+                    row.get::<String, _>("field_value"),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let conflict_fields: &[&str] = &["output_id", "field_name"];
+
+    let mut tx = storage.transaction().await.unwrap();
+    bulk_insert(
+        "custom_cell_field",
+        &fields,
+        &[row(1, "decimals", "8"), row(1, "name", "Old")],
+        None,
+        None,
+        &mut tx,
+    )
+    .await
+    .unwrap();
+    // conflicting rows are skipped without update fields
+    bulk_insert(
+        "custom_cell_field",
+        &fields,
+        &[row(1, "name", "Skipped")],
+        Some(conflict_fields),
+        None,
+        &mut tx,
+    )
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(
+        vec![
+            ("decimals".to_string(), "8".to_string()),
+            ("name".to_string(), "Old".to_string()),
+        ],
+        field_values().await
+    );
+
+    let mut tx = storage.transaction().await.unwrap();
+    bulk_insert(
+        "custom_cell_field",
+        &fields,
+        &[row(1, "name", "New"), row(1, "symbol", "NEW")],
+        Some(conflict_fields),
+        Some(&["field_value"]),
+        &mut tx,
+    )
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(
+        vec![
+            ("decimals".to_string(), "8".to_string()),
+            ("name".to_string(), "New".to_string()),
+            ("symbol".to_string(), "NEW".to_string()),
+        ],
+        field_values().await
+    );
+
+    // updating needs a conflict target
+    let mut tx = storage.transaction().await.unwrap();
+    assert!(bulk_insert(
+        "custom_cell_field",
+        &fields,
+        &[row(1, "name", "Newer")],
+        None,
+        Some(&["field_value"]),
+        &mut tx,
+    )
+    .await
+    .is_err());
+}
+
+#[test]
+async fn bulk_insert_sql_of_backends() {
+    let fields = ["output_id", "field_name"];
+    let row = || vec![FieldValue::BigInt(1), FieldValue::Text("name".to_string())];
+    let rows = [row(), row()];
+    assert_eq!(
+        "INSERT INTO custom_cell_field (output_id, field_name) VALUES ($1, $2), ($3, $4)",
+        build_bulk_insert_sql(DBDriver::Postgres, "custom_cell_field", &fields, &rows).unwrap()
+    );
+    assert_eq!(
+        "INSERT INTO custom_cell_field (output_id, field_name) VALUES (?, ?), (?, ?)",
+        build_bulk_insert_sql(DBDriver::MySQL, "custom_cell_field", &fields, &rows).unwrap()
+    );
+
+    let conflict_fields: &[&str] = &["output_id", "field_name"];
+    assert_eq!(
+        " ON CONFLICT (output_id, field_name) DO NOTHING",
+        build_on_conflict_clause(DBDriver::Sqlite, Some(conflict_fields), None).unwrap()
+    );
+    assert_eq!(
+        " ON DUPLICATE KEY UPDATE output_id = output_id",
+        build_on_conflict_clause(DBDriver::MySQL, Some(conflict_fields), None).unwrap()
+    );
+    assert_eq!(
+        " ON DUPLICATE KEY UPDATE field_value = VALUES(field_value)",
+        build_on_conflict_clause(
+            DBDriver::MySQL,
+            Some(conflict_fields),
+            Some(&["field_value"])
+        )
+        .unwrap()
+    );
+    assert!(build_on_conflict_clause(DBDriver::MySQL, None, Some(&["field_value"])).is_err());
+}
+
+#[test]
+async fn append_block_with_many_scripts() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    // distinct lock scripts, and a type script on every other output, spanning several
+    // lookup chunks
+    let output_count = 2_000;
+    assert!(output_count > BATCH_SIZE_THRESHOLD);
+    let script = |code_hash: u8, args: u32| {
+        ScriptBuilder::default()
+            .code_hash(H256([code_hash; 32]).pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(args.to_le_bytes().to_vec()).pack())
+            .build()
+    };
+    let outputs: Vec<_> = (0..output_count as u32)
+        .map(|i| {
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(script(1, i))
+                .type_((i % 2 == 0).then(|| script(2, i / 2)).pack())
+                .build()
+        })
+        .collect();
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs(outputs.clone())
+        .outputs_data((0..output_count).map(|_| Bytes::new().pack()))
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase)
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let rows = storage
+        .fetch_all(SQLXPool::new_query(
+            r#"
+            SELECT
+                output.output_index,
+                lock_script.script_hash AS lock_script_hash,
+                type_script.script_hash AS type_script_hash
+            FROM output
+            JOIN script AS lock_script ON output.lock_script_id = lock_script.id
+            LEFT JOIN script AS type_script ON output.type_script_id = type_script.id
+            ORDER BY output.output_index
+            "#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(output_count, rows.len());
+    for (row, output) in rows.iter().zip(outputs) {
+        assert_eq!(
+            output.lock().calc_script_hash().raw_data().to_vec(),
+            row.get::<Vec<u8>, _>("lock_script_hash")
+        );
+        assert_eq!(
+            output
+                .type_()
+                .to_opt()
+                .map(|script| script.calc_script_hash().raw_data().to_vec()),
+            row.get::<Option<Vec<u8>>, _>("type_script_hash")
+        );
+    }
+    assert_eq!(
+        (output_count + output_count / 2) as u64,
+        storage.fetch_count("script").await.unwrap()
+    );
+}
+
+#[test]
+async fn input_since_fields() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .build();
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .outputs(vec![cell_output.clone(); 3])
+            .outputs_data(vec![Bytes::new().pack(); 3])
+            .build()
+    };
+    let cellbase0 = cellbase(0);
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    // no lock, an absolute block number and a relative epoch
+    let epoch = EpochNumberWithFraction::new(6, 1, 2);
+    let sinces = [0, 1_000, 0xa000_0000_0000_0000 | epoch.full_value()];
+    let tx = TransactionBuilder::default()
+        .inputs(sinces.iter().enumerate().map(|(index, since)| {
+            CellInput::new(OutPoint::new(cellbase0.hash(), index as u32), *since)
+        }))
+        .output(cell_output.clone())
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase(1), tx])
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let since_fields = || async {
+        storage
+            .fetch_all(SQLXPool::new_query(
+                r#"
+                SELECT since, since_flag, since_type, since_value
+                FROM input
+                ORDER BY input_index
+                "#,
+            ))
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<Vec<u8>, _>("since"),
+                    row.get::<Option<i16>, _>("since_flag"),
+                    row.get::<Option<i16>, _>("since_type"),
+                    row.get::<Option<i64>, _>("since_value"),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let expected: Vec<_> = sinces
+        .iter()
+        .map(|since| since.to_be_bytes().to_vec())
+        .zip([
+            (None, None, None),
+            (Some(0), Some(0), Some(1_000)),
+            (Some(1), Some(1), Some(epoch.full_value() as i64)),
+        ])
+        .map(|(since, (flag, since_type, value))| (since, flag, since_type, value))
+        .collect();
+    assert_eq!(expected, since_fields().await);
+
+    // inputs indexed before the since fields were introduced are filled in
+    let mut tx = storage.transaction().await.unwrap();
+    SQLXPool::new_query(
+        "UPDATE input SET since_flag = NULL, since_type = NULL, since_value = NULL",
+    )
+    .execute(tx.as_mut())
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    run_pending_backfills(&storage).await.unwrap();
+    assert_eq!(expected, since_fields().await);
+
+    // the input without a since lock keeps them NULL, and is not scanned again
+    assert!(!pending_backfills(&storage)
+        .await
+        .unwrap()
+        .contains(&Backfill::SinceFields));
+}
@@ -1,6 +1,9 @@
 mod insert;
+#[cfg(feature = "mysql")]
+mod mysql;
 mod query;
 mod rollback;
+mod sync;
 
 use crate::store::SQLXPool;
 use crate::{AsyncRichIndexer, AsyncRichIndexerHandle};
@@ -29,7 +32,12 @@ async fn connect_sqlite(store_path: &str) -> SQLXPool {
 
 async fn insert_blocks(store: SQLXPool) {
     let data_path = String::from(BLOCK_DIR);
-    let indexer = AsyncRichIndexer::new(store, None, CustomFilters::new(None, None));
+    let indexer = AsyncRichIndexer::new(
+        store,
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
     for i in 0..10 {
         indexer
             .append(&read_block_view(i, data_path.clone()).into())
@@ -1,17 +1,27 @@
 use super::*;
 
+use crate::indexer::{
+    backfill_on_startup, fill_data_prefixes, fill_missing_data_hashes, fill_missing_script_hashes,
+    pending_backfills, run_pending_backfills, Backfill,
+};
+use crate::ExportFormat;
+
 use ckb_indexer_sync::{CustomFilters, Pool};
-use ckb_jsonrpc_types::{IndexerRange, IndexerSearchKeyFilter, IndexerTx};
+use ckb_jsonrpc_types::{
+    IndexerCellType, IndexerCellsOrderBy, IndexerPagination, IndexerRange, IndexerSearchKeyFilter,
+    IndexerTx, IndexerTxInfo,
+};
 use ckb_types::{
     bytes::Bytes,
     core::{
-        capacity_bytes, BlockBuilder, Capacity, EpochNumberWithFraction, HeaderBuilder,
+        capacity_bytes, BlockBuilder, Capacity, DepType, EpochNumberWithFraction, HeaderBuilder,
         ScriptHashType, TransactionBuilder,
     },
     packed::{self, CellInput, CellOutputBuilder, OutPoint, Script, ScriptBuilder},
     H256,
 };
 
+use sqlx::Row;
 use std::{
     sync::{Arc, RwLock},
     usize,
@@ -34,6 +44,28 @@ async fn test_query_tip() {
     );
 }
 
+#[test]
+async fn get_sync_status() {
+    let pool = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexerHandle::new(pool.clone(), None, usize::MAX);
+
+    let status = indexer.get_sync_status(5u64.into()).await.unwrap();
+    assert!(status.indexer_tip.is_none());
+    assert_eq!(6, status.lag.value());
+
+    insert_blocks(pool.clone()).await;
+    let status = indexer.get_sync_status(15u64.into()).await.unwrap();
+    assert_eq!(9, status.indexer_tip.unwrap().block_number.value());
+    assert_eq!(6, status.lag.value());
+
+    let status = indexer.get_sync_status(9u64.into()).await.unwrap();
+    assert_eq!(0, status.lag.value());
+
+    // the node tip may fall behind the indexer tip during a reorg
+    let status = indexer.get_sync_status(5u64.into()).await.unwrap();
+    assert_eq!(0, status.lag.value());
+}
+
 #[test]
 async fn get_cells() {
     let pool = connect_sqlite(MEMORY_DB).await;
@@ -559,7 +591,12 @@ async fn get_cells_capacity() {
 async fn rpc() {
     let store = connect_sqlite(MEMORY_DB).await;
     let pool = Arc::new(RwLock::new(Pool::default()));
-    let indexer = AsyncRichIndexer::new(store.clone(), None, CustomFilters::new(None, None));
+    let indexer = AsyncRichIndexer::new(
+        store.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
     let rpc = AsyncRichIndexerHandle::new(store, Some(Arc::clone(&pool)), usize::MAX);
 
     // setup test data
@@ -1167,7 +1204,12 @@ async fn rpc() {
 #[test]
 async fn script_search_mode_rpc() {
     let pool = connect_sqlite(MEMORY_DB).await;
-    let indexer = AsyncRichIndexer::new(pool.clone(), None, CustomFilters::new(None, None));
+    let indexer = AsyncRichIndexer::new(
+        pool.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
     let rpc = AsyncRichIndexerHandle::new(pool, None, usize::MAX);
 
     // setup test data
@@ -1416,7 +1458,12 @@ async fn script_search_mode_rpc() {
 #[test]
 async fn output_data_filter_mode_rpc() {
     let pool = connect_sqlite(MEMORY_DB).await;
-    let indexer = AsyncRichIndexer::new(pool.clone(), None, CustomFilters::new(None, None));
+    let indexer = AsyncRichIndexer::new(
+        pool.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
     let rpc = AsyncRichIndexerHandle::new(pool, None, usize::MAX);
 
     // setup test data
@@ -1620,6 +1667,2480 @@ async fn output_data_filter_mode_rpc() {
     assert_eq!(200000000000, capacity);
 }
 
+#[test]
+async fn get_block_range_cell_events() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage, None, usize::MAX);
+
+    let lock_script1 = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(b"lock_script1".to_vec()).pack())
+        .build();
+    let lock_script2 = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(b"lock_script2".to_vec()).pack())
+        .build();
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(lock_script1.clone())
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+    };
+
+    // block 0 creates two cells in tx01
+    let tx01 = TransactionBuilder::default()
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script1.clone())
+                .build(),
+        )
+        .output_data(Default::default())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(2000).pack())
+                .lock(lock_script2.clone())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase(0))
+        .transaction(tx01.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    // block 1 spends the first cell of tx01
+    let tx11 = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(tx01.hash(), 0), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script2.clone())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase(1))
+        .transaction(tx11.clone())
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    // block 2 spends the second cell of tx01 and the cell created in block 1
+    let tx21 = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(tx01.hash(), 1), 0))
+        .input(CellInput::new(OutPoint::new(tx11.hash(), 0), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(3000).pack())
+                .lock(lock_script1.clone())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block2 = BlockBuilder::default()
+        .transaction(cellbase(2))
+        .transaction(tx21.clone())
+        .header(
+            HeaderBuilder::default()
+                .number(2.pack())
+                .parent_hash(block1.hash())
+                .epoch(EpochNumberWithFraction::new(2, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block2).await.unwrap();
+
+    let events = rpc
+        .get_block_range_cell_events(0u64.into(), 3u64.into())
+        .await
+        .unwrap();
+    let events = events
+        .into_iter()
+        .map(|event| {
+            (
+                event.block_number.value(),
+                event.tx_index.value(),
+                matches!(event.io_type, IndexerCellType::Input),
+                event.io_index.value(),
+                event.out_point,
+            )
+        })
+        .collect::<Vec<_>>();
+    let out_point = |tx_hash: packed::Byte32, index: u32| OutPoint::new(tx_hash, index).into();
+    assert_eq!(
+        vec![
+            (0, 0, false, 0, out_point(block0.tx_hashes()[0].clone(), 0)),
+            (0, 1, false, 0, out_point(tx01.hash(), 0)),
+            (0, 1, false, 1, out_point(tx01.hash(), 1)),
+            (1, 0, false, 0, out_point(block1.tx_hashes()[0].clone(), 0)),
+            (1, 1, true, 0, out_point(tx01.hash(), 0)),
+            (1, 1, false, 0, out_point(tx11.hash(), 0)),
+            (2, 0, false, 0, out_point(block2.tx_hashes()[0].clone(), 0)),
+            (2, 1, true, 0, out_point(tx01.hash(), 1)),
+            (2, 1, true, 1, out_point(tx11.hash(), 0)),
+            (2, 1, false, 0, out_point(tx21.hash(), 0)),
+        ],
+        events
+    );
+
+    // the range is half-open
+    let events = rpc
+        .get_block_range_cell_events(1u64.into(), 2u64.into())
+        .await
+        .unwrap();
+    assert_eq!(3, events.len());
+
+    // the range size is capped
+    assert!(rpc
+        .get_block_range_cell_events(0u64.into(), 1_001u64.into())
+        .await
+        .is_err());
+}
+
+#[test]
+async fn get_lock_usage_stats() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage, None, usize::MAX);
+
+    let lock_scripts: Vec<Script> = (0..3)
+        .map(|_| {
+            ScriptBuilder::default()
+                .code_hash(H256(rand::random()).pack())
+                .hash_type(ScriptHashType::Type.into())
+                .args(Bytes::from(rand::random::<[u8; 20]>().to_vec()).pack())
+                .build()
+        })
+        .collect();
+
+    // block 0 creates 4, 2 and 1 cells under the three lock code hashes
+    let mut cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness());
+    for (lock_script, count) in lock_scripts.iter().zip([4, 2, 1]) {
+        for _ in 0..count {
+            cellbase = cellbase
+                .output(
+                    CellOutputBuilder::default()
+                        .capacity(capacity_bytes!(1000).pack())
+                        .lock(lock_script.clone())
+                        .build(),
+                )
+                .output_data(Default::default());
+        }
+    }
+    let cellbase = cellbase.build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    // block 1 spends two cells of the first lock code hash
+    let tx = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase.hash(), 0), 0))
+        .input(CellInput::new(OutPoint::new(cellbase.hash(), 1), 0))
+        .build();
+    let block1 = BlockBuilder::default()
+        .transaction(
+            TransactionBuilder::default()
+                .input(CellInput::new_cellbase_input(1))
+                .witness(Script::default().into_witness())
+                .build(),
+        )
+        .transaction(tx)
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let stats = rpc.get_lock_usage_stats(10.into()).await.unwrap();
+    let mut expected = vec![
+        (lock_scripts[0].code_hash().unpack(), 2),
+        (lock_scripts[1].code_hash().unpack(), 2),
+        (lock_scripts[2].code_hash().unpack(), 1),
+    ];
+    expected[..2].sort();
+    assert_eq!(
+        expected,
+        stats
+            .into_iter()
+            .map(|stat| (stat.code_hash, stat.live_cell_count.value()))
+            .collect::<Vec<(H256, u64)>>()
+    );
+
+    let stats = rpc.get_lock_usage_stats(1.into()).await.unwrap();
+    assert_eq!(1, stats.len());
+    assert_eq!(2, stats[0].live_cell_count.value());
+
+    assert!(rpc.get_lock_usage_stats(0.into()).await.is_err());
+}
+
+#[test]
+async fn get_cells_and_transactions_large_result_set() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage, None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(b"lock_script".to_vec()).pack())
+        .build();
+    let cells_count = 600;
+    let mut cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness());
+    for i in 0..cells_count {
+        cellbase = cellbase
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(lock_script.clone())
+                    .build(),
+            )
+            .output_data(Bytes::from(vec![i as u8; 32]).pack());
+    }
+    let cellbase = cellbase.build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let search_key = || IndexerSearchKey {
+        script: lock_script.clone().into(),
+        script_type: IndexerScriptType::Lock,
+        script_search_mode: Some(IndexerSearchMode::Exact),
+        filter: None,
+        with_data: Some(true),
+        group_by_transaction: None,
+    };
+
+    // fetch all cells at once
+    let cells = rpc
+        .get_cells(search_key(), IndexerOrder::Asc, 1000u32.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(cells_count, cells.objects.len());
+    for (i, cell) in cells.objects.iter().enumerate() {
+        assert_eq!(
+            OutPoint::new(cellbase.hash(), i as u32),
+            cell.out_point.clone().into()
+        );
+        assert_eq!(
+            Some(JsonBytes::from_vec(vec![i as u8; 32])),
+            cell.output_data
+        );
+    }
+
+    // fetch the same cells page by page
+    let mut paged_out_points = vec![];
+    let mut after = None;
+    loop {
+        let page = rpc
+            .get_cells(search_key(), IndexerOrder::Asc, 250u32.into(), after)
+            .await
+            .unwrap();
+        if page.objects.is_empty() {
+            break;
+        }
+        paged_out_points.extend(page.objects.into_iter().map(|cell| cell.out_point));
+        after = Some(page.last_cursor);
+    }
+    assert_eq!(
+        cells
+            .objects
+            .into_iter()
+            .map(|cell| cell.out_point)
+            .collect::<Vec<_>>(),
+        paged_out_points
+    );
+
+    let txs = rpc
+        .get_transactions(search_key(), IndexerOrder::Asc, 1000u32.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(cells_count, txs.objects.len());
+    for (i, tx) in txs.objects.iter().enumerate() {
+        match tx {
+            IndexerTx::Ungrouped(tx_with_cell) => {
+                assert_eq!(i as u32, tx_with_cell.io_index.value());
+            }
+            IndexerTx::Grouped(_) => panic!("unexpected grouped transaction"),
+        }
+    }
+}
+
+#[test]
+async fn live_cells_by_script_use_composite_index() {
+    let pool = connect_sqlite(MEMORY_DB).await;
+    insert_blocks(pool.clone()).await;
+
+    for (column, index) in [
+        ("lock_script_id", "idx_output_table_lock_script_id_is_spent"),
+        ("type_script_id", "idx_output_table_type_script_id_is_spent"),
+    ] {
+        let sql = format!(
+            "EXPLAIN QUERY PLAN SELECT id FROM output WHERE {} = 1 AND is_spent = 0",
+            column
+        );
+        let plan = pool
+            .fetch_all(SQLXPool::new_query(&sql))
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get::<String, _>("detail"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(plan.contains(index), "{}", plan);
+    }
+}
+
+#[test]
+async fn get_cells_in_creation_order() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage, None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(b"lock_script".to_vec()).pack())
+        .build();
+    let output = || {
+        CellOutputBuilder::default()
+            .capacity(capacity_bytes!(1000).pack())
+            .lock(lock_script.clone())
+            .build()
+    };
+
+    // each block has a cellbase with one output and a transaction with two outputs
+    let mut parent: Option<packed::Header> = None;
+    for number in 0..3u64 {
+        let cellbase = TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(output())
+            .output_data(Default::default())
+            .build();
+        let tx = TransactionBuilder::default()
+            .output(output())
+            .output_data(Default::default())
+            .output(output())
+            .output_data(Default::default())
+            .build();
+        let mut header = HeaderBuilder::default().number(number.pack());
+        if let Some(parent) = parent {
+            header = header
+                .parent_hash(parent.calc_header_hash())
+                .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack());
+        }
+        let block = BlockBuilder::default()
+            .transaction(cellbase)
+            .transaction(tx)
+            .header(header.build())
+            .build();
+        indexer.append(&block).await.unwrap();
+        parent = Some(block.header().data());
+    }
+
+    let search_key = || IndexerSearchKey {
+        script: lock_script.clone().into(),
+        script_type: IndexerScriptType::Lock,
+        script_search_mode: Some(IndexerSearchMode::Exact),
+        filter: None,
+        with_data: Some(false),
+        group_by_transaction: None,
+    };
+    let mut expected = vec![];
+    for number in 0..3u64 {
+        expected.push((number, 0, 0));
+        expected.push((number, 1, 0));
+        expected.push((number, 1, 1));
+    }
+
+    for desc in [false, true] {
+        // fetch page by page with the creation cursor
+        let mut cells = vec![];
+        let mut after = None;
+        loop {
+            let page = rpc
+                .get_cells_with_order_by(
+                    search_key(),
+                    if desc {
+                        IndexerOrder::Desc
+                    } else {
+                        IndexerOrder::Asc
+                    },
+                    IndexerCellsOrderBy::Creation,
+                    2u32.into(),
+                    after,
+                )
+                .await
+                .unwrap();
+            if page.objects.is_empty() {
+                break;
+            }
+            cells.extend(page.objects.into_iter().map(|cell| {
+                (
+                    cell.block_number.value(),
+                    cell.tx_index.value(),
+                    cell.out_point.index.value(),
+                )
+            }));
+            after = Some(page.last_cursor);
+        }
+        let mut expected = expected.clone();
+        if desc {
+            expected.reverse();
+        }
+        assert_eq!(expected, cells);
+    }
+
+    // a cursor of the id order can't be used with the creation order
+    assert!(rpc
+        .get_cells_with_order_by(
+            search_key(),
+            IndexerOrder::Asc,
+            IndexerCellsOrderBy::Creation,
+            2u32.into(),
+            Some(JsonBytes::from_vec(vec![1u8, 0, 0, 0, 0, 0, 0, 0])),
+        )
+        .await
+        .is_err());
+}
+
+#[test]
+async fn export_live_cells() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage, None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(b"lock_script".to_vec()).pack())
+        .build();
+    let udt_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(b"owner".to_vec()).pack())
+        .build();
+    let other_type_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .build();
+    let output = |type_script: &Script| {
+        CellOutputBuilder::default()
+            .capacity(capacity_bytes!(142).pack())
+            .lock(lock_script.clone())
+            .type_(Some(type_script.clone()).pack())
+            .build()
+    };
+    let amount = |amount: u128| Bytes::from(amount.to_le_bytes().to_vec()).pack();
+
+    // block 0 creates three udt cells and a cell of another type
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(output(&udt_script))
+        .output_data(amount(100))
+        .output(output(&udt_script))
+        .output_data(amount(200))
+        .output(output(&udt_script))
+        .output_data(amount(300))
+        .output(output(&other_type_script))
+        .output_data(amount(400))
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    // block 1 spends the first udt cell
+    let tx = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase.hash(), 0), 0))
+        .build();
+    let block1 = BlockBuilder::default()
+        .transaction(
+            TransactionBuilder::default()
+                .input(CellInput::new_cellbase_input(1))
+                .witness(Script::default().into_witness())
+                .build(),
+        )
+        .transaction(tx)
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let mut buffer = Vec::new();
+    let count = rpc
+        .export_live_cells(
+            udt_script.clone().into(),
+            ExportFormat::JsonLines,
+            &mut buffer,
+        )
+        .await
+        .unwrap();
+    assert_eq!(2, count);
+    let lines = String::from_utf8(buffer).unwrap();
+    let cells = lines
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(2, cells.len());
+    for (cell, (index, amount)) in cells.iter().zip([(1u32, 200u128), (2, 300)]) {
+        assert_eq!(
+            format!("{:#x}", cellbase.hash()),
+            cell["out_point"]["tx_hash"]
+        );
+        assert_eq!(format!("{:#x}", index), cell["out_point"]["index"]);
+        assert_eq!(format!("{:#x}", amount), cell["amount"]);
+        assert_eq!(
+            format!("{:#x}", capacity_bytes!(142).as_u64()),
+            cell["capacity"]
+        );
+        assert_eq!("0x6c6f636b5f736372697074", cell["lock"]["args"]);
+    }
+
+    let mut buffer = Vec::new();
+    rpc.export_live_cells(udt_script.into(), ExportFormat::Csv, &mut buffer)
+        .await
+        .unwrap();
+    let lines = String::from_utf8(buffer).unwrap();
+    let lines = lines.lines().collect::<Vec<_>>();
+    assert_eq!(3, lines.len());
+    assert!(lines[0].starts_with("tx_hash,index,"));
+    let fields = lines[2].split(',').collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            format!("{:#x}", cellbase.hash()),
+            "2".to_string(),
+            format!("{:#x}", lock_script.code_hash()),
+            "1".to_string(),
+            "0x6c6f636b5f736372697074".to_string(),
+            capacity_bytes!(142).as_u64().to_string(),
+            "300".to_string(),
+        ],
+        fields
+    );
+}
+
+#[test]
+async fn get_latest_transactions() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256(rand::random()).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(lock_script.clone())
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+    };
+
+    let cellbase0 = cellbase(0);
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    let cellbase1 = cellbase(1);
+    let tx = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(900).pack())
+                .lock(lock_script.clone())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase1.clone())
+        .transaction(tx.clone())
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let summary = |txs: &IndexerPagination<IndexerTxInfo>| {
+        txs.objects
+            .iter()
+            .map(|tx| {
+                (
+                    tx.tx_hash.clone(),
+                    tx.block_number.value(),
+                    tx.tx_index.value(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // newest first, across blocks
+    let txs = rpc
+        .get_latest_transactions(2u32.into(), None, true)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            (tx.hash().unpack(), 1, 1),
+            (cellbase1.hash().unpack(), 1, 0)
+        ],
+        summary(&txs)
+    );
+    let txs = rpc
+        .get_latest_transactions(2u32.into(), Some(txs.last_cursor), true)
+        .await
+        .unwrap();
+    assert_eq!(vec![(cellbase0.hash().unpack(), 0, 0)], summary(&txs));
+
+    // cellbase transactions are excluded
+    let txs = rpc
+        .get_latest_transactions(10u32.into(), None, false)
+        .await
+        .unwrap();
+    assert_eq!(vec![(tx.hash().unpack(), 1, 1)], summary(&txs));
+}
+
+#[test]
+async fn get_address_activity() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock = |args: u8| {
+        ScriptBuilder::default()
+            .code_hash(H256([1; 32]).pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![args]).pack())
+            .build()
+    };
+    let cellbase = |number: u64, lock_script: Script| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(lock_script)
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+    };
+
+    // lock 1 receives a cell in block 0 which is spent in block 2,
+    // lock 2 receives cells in blocks 1 to 3
+    let cellbase0 = cellbase(0, lock(1));
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(900).pack())
+                .lock(lock(2))
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let mut parent_hash = packed::Byte32::zero();
+    for (number, txs) in [
+        (0u64, vec![cellbase0]),
+        (1, vec![cellbase(1, lock(2))]),
+        (2, vec![cellbase(2, lock(2)), spend]),
+        (3, vec![cellbase(3, lock(2))]),
+    ] {
+        let block = BlockBuilder::default()
+            .transactions(txs)
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+                    .build(),
+            )
+            .build();
+        indexer.append(&block).await.unwrap();
+        parent_hash = block.hash();
+    }
+
+    let activity = |args: u8| {
+        let rpc = rpc.clone();
+        async move {
+            rpc.get_address_activity(lock(args).into())
+                .await
+                .unwrap()
+                .map(|activity| (activity.first_block.value(), activity.last_block.value()))
+        }
+    };
+    assert_eq!(Some((0, 2)), activity(1).await);
+    assert_eq!(Some((1, 3)), activity(2).await);
+    assert_eq!(None, activity(3).await);
+}
+
+#[test]
+async fn get_scripts_by_hashes() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256([1; 32]).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(vec![1]).pack())
+        .build();
+    let type_script = ScriptBuilder::default()
+        .code_hash(H256([2; 32]).pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .build();
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script.clone())
+                .type_(Some(type_script.clone()).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase)
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let lock_hash: H256 = lock_script.calc_script_hash().unpack();
+    let type_hash: H256 = type_script.calc_script_hash().unpack();
+    let unknown_hash = H256([3; 32]);
+    let resolve = || {
+        rpc.get_scripts_by_hashes(vec![
+            lock_hash.clone(),
+            type_hash.clone(),
+            unknown_hash.clone(),
+        ])
+    };
+
+    let scripts = resolve().await.unwrap();
+    assert_eq!(2, scripts.len());
+    assert_eq!(Some(&lock_script.clone().into()), scripts.get(&lock_hash));
+    assert_eq!(Some(&type_script.clone().into()), scripts.get(&type_hash));
+    assert!(!scripts.contains_key(&unknown_hash));
+
+    // scripts indexed before the hash column existed are resolvable once filled in
+    sqlx::query("UPDATE script SET script_hash = NULL")
+        .execute(storage.get_pool().unwrap())
+        .await
+        .unwrap();
+    assert!(resolve().await.unwrap().is_empty());
+    fill_missing_script_hashes(&storage).await.unwrap();
+    assert_eq!(2, resolve().await.unwrap().len());
+}
+
+#[test]
+async fn filter_live_out_points() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let cell_output = |capacity: Capacity| {
+        CellOutputBuilder::default()
+            .capacity(capacity.pack())
+            .lock(
+                ScriptBuilder::default()
+                    .code_hash(H256([1; 32]).pack())
+                    .hash_type(ScriptHashType::Type.into())
+                    .build(),
+            )
+            .build()
+    };
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(cell_output(capacity_bytes!(1000)))
+            .output_data(Default::default())
+            .build()
+    };
+
+    // the cellbase of block 0 is spent in block 1
+    let cellbase0 = cellbase(0);
+    let cellbase1 = cellbase(1);
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .output(cell_output(capacity_bytes!(900)))
+        .output_data(Default::default())
+        .build();
+    let mut parent_hash = packed::Byte32::zero();
+    for (number, txs) in [
+        (0u64, vec![cellbase0.clone()]),
+        (1, vec![cellbase1.clone(), spend.clone()]),
+    ] {
+        let block = BlockBuilder::default()
+            .transactions(txs)
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+                    .build(),
+            )
+            .build();
+        indexer.append(&block).await.unwrap();
+        parent_hash = block.hash();
+    }
+
+    let out_points = [
+        OutPoint::new(spend.hash(), 0),
+        OutPoint::new(cellbase0.hash(), 0),
+        OutPoint::new(packed::Byte32::zero(), 0),
+        OutPoint::new(cellbase1.hash(), 0),
+        OutPoint::new(cellbase1.hash(), 1),
+    ];
+    let live = rpc
+        .filter_live_out_points(out_points.iter().cloned().map(Into::into).collect())
+        .await
+        .unwrap();
+    let expected: Vec<ckb_jsonrpc_types::OutPoint> =
+        vec![out_points[0].clone().into(), out_points[3].clone().into()];
+    assert_eq!(expected, live);
+}
+
+#[test]
+async fn get_capacity_multi_lock() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock = |args: u8| {
+        ScriptBuilder::default()
+            .code_hash(H256([1; 32]).pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![args]).pack())
+            .build()
+    };
+    let cell_output = |args: u8, capacity: Capacity| {
+        CellOutputBuilder::default()
+            .capacity(capacity.pack())
+            .lock(lock(args))
+            .build()
+    };
+
+    // lock 1 holds two cells, the cell of lock 3 is spent to a smaller one in block 1
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs(vec![
+            cell_output(1, capacity_bytes!(100)),
+            cell_output(1, capacity_bytes!(200)),
+            cell_output(2, capacity_bytes!(300)),
+            cell_output(3, capacity_bytes!(400)),
+        ])
+        .outputs_data(vec![Default::default(); 4])
+        .build();
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase.hash(), 3), 0))
+        .output(cell_output(3, capacity_bytes!(350)))
+        .output_data(Default::default())
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase)
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+    let cellbase1 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .witness(Script::default().into_witness())
+        .output(cell_output(5, capacity_bytes!(1000)))
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase1, spend])
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let capacities = rpc
+        .get_capacity_multi_lock((1..=4).map(|args| lock(args).into()).collect())
+        .await
+        .unwrap();
+    let capacity = |args: u8| {
+        let lock_hash: H256 = lock(args).calc_script_hash().unpack();
+        capacities[&lock_hash].value()
+    };
+    assert_eq!(4, capacities.len());
+    assert_eq!(capacity_bytes!(300).as_u64(), capacity(1));
+    assert_eq!(capacity_bytes!(300).as_u64(), capacity(2));
+    assert_eq!(capacity_bytes!(350).as_u64(), capacity(3));
+    assert_eq!(0, capacity(4));
+}
+
+#[test]
+async fn get_outputs_spending_status() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .lock(
+            ScriptBuilder::default()
+                .code_hash(H256([1; 32]).pack())
+                .hash_type(ScriptHashType::Type.into())
+                .build(),
+        )
+        .build();
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .outputs(vec![cell_output.clone(); 2])
+            .outputs_data(vec![Default::default(); 2])
+            .build()
+    };
+
+    // the second output of the cellbase of block 0 is spent in block 1
+    let cellbase0 = cellbase(0);
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 1), 0))
+        .output(cell_output.clone())
+        .output_data(Default::default())
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase(1), spend.clone()])
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let status = rpc
+        .get_outputs_spending_status(cellbase0.hash().unpack())
+        .await
+        .unwrap();
+    assert_eq!(2, status.len());
+    assert_eq!(0, status[0].output_index.value());
+    assert!(!status[0].spent);
+    assert_eq!(None, status[0].spent_by_tx);
+    assert_eq!(1, status[1].output_index.value());
+    assert!(status[1].spent);
+    assert_eq!(Some(spend.hash().unpack()), status[1].spent_by_tx);
+
+    assert!(rpc
+        .get_outputs_spending_status(H256([2; 32]))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+async fn get_active_address_count() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock = |args: u8| {
+        ScriptBuilder::default()
+            .code_hash(H256([1; 32]).pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![args]).pack())
+            .build()
+    };
+    let cellbase = |number: u64, args: u8| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(lock(args))
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+    };
+
+    // lock 1 receives a cell in block 0 which is spent to lock 2 in block 2,
+    // lock 3 and lock 4 receive the cellbases of blocks 1 to 3
+    let cellbase0 = cellbase(0, 1);
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(900).pack())
+                .lock(lock(2))
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let mut parent_hash = packed::Byte32::zero();
+    for (number, txs) in [
+        (0u64, vec![cellbase0]),
+        (1, vec![cellbase(1, 3)]),
+        (2, vec![cellbase(2, 3), spend]),
+        (3, vec![cellbase(3, 4)]),
+    ] {
+        let block = BlockBuilder::default()
+            .transactions(txs)
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+                    .build(),
+            )
+            .build();
+        indexer.append(&block).await.unwrap();
+        parent_hash = block.hash();
+    }
+
+    let count = |from: u64, to: u64| {
+        let rpc = rpc.clone();
+        async move {
+            rpc.get_active_address_count(from.into(), to.into())
+                .await
+                .unwrap()
+                .value()
+        }
+    };
+    assert_eq!(4, count(0, 4).await);
+    assert_eq!(1, count(0, 1).await);
+    assert_eq!(1, count(1, 2).await);
+    // lock 1 is active in block 2 by the spent cell
+    assert_eq!(3, count(2, 3).await);
+    assert_eq!(3, count(1, 3).await);
+    assert_eq!(0, count(4, 10).await);
+    assert!(rpc
+        .get_active_address_count(2.into(), 2.into())
+        .await
+        .is_err());
+}
+
+#[test]
+async fn data_prefix_filters() {
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256([1; 32]).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let data = [
+        vec![],
+        vec![1],
+        vec![1, 2],
+        vec![1, 2, 3],
+        vec![1, 2, 3, 4],
+        vec![1, 2, 3, 4, 5],
+        vec![1, 2, 4, 0],
+        vec![1, 255, 255, 255],
+        vec![2, 0, 0, 0],
+        vec![255, 255, 255, 255],
+    ];
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs(data.iter().map(|_| {
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script.clone())
+                .build()
+        }))
+        .outputs_data(data.iter().map(|data| data.pack()))
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase)
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+
+    // index the same block with and without the data prefix column
+    let mut stores = Vec::new();
+    for data_prefix_len in [None, Some(3)] {
+        let config = RichIndexerConfig {
+            store: MEMORY_DB.into(),
+            data_prefix_len,
+            ..Default::default()
+        };
+        let mut store = SQLXPool::default();
+        store.connect(&config).await.unwrap();
+        backfill_on_startup(&store, true).await.unwrap();
+        let indexer =
+            AsyncRichIndexer::new(store.clone(), None, CustomFilters::new(None, None), config);
+        indexer.append(&block).await.unwrap();
+        stores.push(store);
+    }
+    let plain = AsyncRichIndexerHandle::new(stores[0].clone(), None, usize::MAX);
+    let prefixed = AsyncRichIndexerHandle::new(stores[1].clone(), None, usize::MAX);
+
+    let filter_values = [
+        vec![],
+        vec![1],
+        vec![1, 2],
+        vec![1, 2, 3],
+        vec![1, 2, 3, 4],
+        vec![1, 2, 4],
+        vec![1, 255],
+        vec![255, 255, 255, 255],
+    ];
+    for value in filter_values {
+        for mode in [IndexerSearchMode::Prefix, IndexerSearchMode::Exact] {
+            let search_key = || IndexerSearchKey {
+                script: lock_script.clone().into(),
+                script_type: IndexerScriptType::Lock,
+                script_search_mode: Some(IndexerSearchMode::Exact),
+                filter: Some(IndexerSearchKeyFilter {
+                    output_data: Some(JsonBytes::from_vec(value.clone())),
+                    output_data_filter_mode: Some(mode),
+                    ..Default::default()
+                }),
+                with_data: Some(false),
+                group_by_transaction: None,
+            };
+            let mut out_points = Vec::new();
+            let mut capacities = Vec::new();
+            let mut tx_counts = Vec::new();
+            for handle in [&plain, &prefixed] {
+                let cells = handle
+                    .get_cells(search_key(), IndexerOrder::Asc, 100u32.into(), None)
+                    .await
+                    .unwrap();
+                out_points.push(
+                    cells
+                        .objects
+                        .into_iter()
+                        .map(|cell| cell.out_point)
+                        .collect::<Vec<ckb_jsonrpc_types::OutPoint>>(),
+                );
+                capacities.push(
+                    handle
+                        .get_cells_capacity(search_key())
+                        .await
+                        .unwrap()
+                        .map(|capacity| capacity.capacity.value()),
+                );
+                let txs = handle
+                    .get_transactions(search_key(), IndexerOrder::Asc, 100u32.into(), None)
+                    .await
+                    .unwrap();
+                tx_counts.push(txs.objects.len());
+            }
+            assert_eq!(out_points[0], out_points[1], "{:?}", value);
+            assert_eq!(capacities[0], capacities[1], "{:?}", value);
+            assert_eq!(tx_counts[0], tx_counts[1], "{:?}", value);
+        }
+    }
+
+    // the prefix column is filled in once it is enabled on an existing database
+    let mut store = stores[0].clone();
+    store.data_prefix_len = Some(3);
+    fill_data_prefixes(&store).await.unwrap();
+    for store in &stores {
+        let prefixes: Vec<Vec<u8>> = store
+            .fetch_all(SQLXPool::new_query(
+                "SELECT data_prefix FROM output ORDER BY output_index",
+            ))
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| {
+                row.get::<Option<Vec<u8>>, _>("data_prefix")
+                    .unwrap_or_default()
+            })
+            .collect();
+        let expected: Vec<Vec<u8>> = data
+            .iter()
+            .map(|data| data[..data.len().min(3)].to_vec())
+            .collect();
+        assert_eq!(expected, prefixes);
+    }
+}
+
+#[test]
+async fn data_prefix_of_compressed_data() {
+    let config = RichIndexerConfig {
+        store: MEMORY_DB.into(),
+        data_prefix_len: Some(4),
+        data_compression_threshold: Some(1024),
+        ..Default::default()
+    };
+    let mut store = SQLXPool::default();
+    store.connect(&config).await.unwrap();
+    backfill_on_startup(&store, true).await.unwrap();
+    let indexer = AsyncRichIndexer::new(
+        store.clone(),
+        None,
+        CustomFilters::new(None, None),
+        config.clone(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(store.clone(), None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256([1; 32]).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let data = [&[1, 2, 3, 4][..], &b"compressible ".repeat(200)].concat();
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock_script.clone())
+                .build(),
+        )
+        .output_data(Bytes::from(data.clone()).pack())
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase)
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let reader = store.clone();
+    let query_prefix = || async {
+        let row = reader
+            .fetch_one(SQLXPool::new_query(
+                "SELECT data_prefix, data_compressed FROM output",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(1, row.get::<i32, _>("data_compressed"));
+        row.get::<Option<Vec<u8>>, _>("data_prefix")
+    };
+    let search_key = || IndexerSearchKey {
+        script: lock_script.clone().into(),
+        filter: Some(IndexerSearchKeyFilter {
+            output_data: Some(JsonBytes::from_vec(vec![1, 2, 3])),
+            output_data_filter_mode: Some(IndexerSearchMode::Prefix),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let count_cells = || async {
+        rpc.get_cells(search_key(), IndexerOrder::Asc, 10u32.into(), None)
+            .await
+            .unwrap()
+            .objects
+            .len()
+    };
+    assert_eq!(Some(vec![1, 2, 3, 4]), query_prefix().await);
+    assert_eq!(Some(4), store.queryable_data_prefix_len());
+    assert_eq!(1, count_cells().await);
+
+    // prefixes written from the compressed bytes are replaced once, by the backfill, and
+    // queries don't rely on them until it has run
+    sqlx::query("UPDATE output SET data_prefix = substr(data, 1, 4)")
+        .execute(store.get_pool().unwrap())
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM backfill WHERE name LIKE 'data_prefix_%'")
+        .execute(store.get_pool().unwrap())
+        .await
+        .unwrap();
+    assert_ne!(Some(vec![1, 2, 3, 4]), query_prefix().await);
+    backfill_on_startup(&store, false).await.unwrap();
+    assert_eq!(
+        vec![Backfill::DataPrefix(4)],
+        pending_backfills(&store).await.unwrap()
+    );
+    assert_eq!(None, store.queryable_data_prefix_len());
+    assert_eq!(1, count_cells().await);
+    run_pending_backfills(&store).await.unwrap();
+    assert_eq!(Some(vec![1, 2, 3, 4]), query_prefix().await);
+    assert!(pending_backfills(&store).await.unwrap().is_empty());
+    assert_eq!(Some(4), store.queryable_data_prefix_len());
+    assert_eq!(1, count_cells().await);
+
+    // outputs indexed while the prefix is disabled lack it, so it is filled in again once it is
+    // enabled again
+    store.data_prefix_len = None;
+    backfill_on_startup(&store, false).await.unwrap();
+    sqlx::query("UPDATE output SET data_prefix = NULL")
+        .execute(store.get_pool().unwrap())
+        .await
+        .unwrap();
+    store.data_prefix_len = Some(4);
+    backfill_on_startup(&store, false).await.unwrap();
+    assert_eq!(
+        vec![Backfill::DataPrefix(4)],
+        pending_backfills(&store).await.unwrap()
+    );
+    assert_eq!(1, count_cells().await);
+    run_pending_backfills(&store).await.unwrap();
+    assert_eq!(Some(vec![1, 2, 3, 4]), query_prefix().await);
+
+    // as are the prefixes of a new length
+    store.data_prefix_len = Some(2);
+    assert_eq!(None, store.queryable_data_prefix_len());
+    assert_eq!(
+        vec![Backfill::DataPrefix(2)],
+        pending_backfills(&store).await.unwrap()
+    );
+    run_pending_backfills(&store).await.unwrap();
+    assert_eq!(Some(vec![1, 2]), query_prefix().await);
+    let names: Vec<String> = store
+        .fetch_all(SQLXPool::new_query(
+            "SELECT name FROM backfill WHERE name LIKE 'data_prefix_%'",
+        ))
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get::<String, _>("name"))
+        .collect();
+    assert_eq!(vec!["data_prefix_2".to_string()], names);
+}
+
+#[test]
+async fn find_block_gaps() {
+    let pool = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexerHandle::new(pool.clone(), None, usize::MAX);
+    insert_blocks(pool.clone()).await;
+    assert!(indexer.find_block_gaps().await.unwrap().is_empty());
+
+    for block_number in [4i64, 6, 7] {
+        sqlx::query("DELETE FROM block WHERE block_number = $1")
+            .bind(block_number)
+            .execute(pool.get_pool().unwrap())
+            .await
+            .unwrap();
+    }
+
+    let gaps: Vec<(u64, u64)> = indexer
+        .find_block_gaps()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(from, to)| (from.into(), to.into()))
+        .collect();
+    assert_eq!(vec![(4, 5), (6, 8)], gaps);
+}
+
+#[test]
+async fn read_only_handle() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = RichIndexerConfig {
+        store: dir.path().join("indexer.db"),
+        ..Default::default()
+    };
+    let mut storage = SQLXPool::default();
+    storage.connect(&config).await.unwrap();
+    insert_blocks(storage.clone()).await;
+    let block_count = storage.fetch_count("block").await.unwrap();
+
+    let rpc = AsyncRichIndexerHandle::new_read_only(&config, None, usize::MAX)
+        .await
+        .unwrap();
+    let tip = rpc.get_indexer_tip().await.unwrap().unwrap();
+    assert_eq!(9, tip.block_number.value());
+
+    let mut read_only = SQLXPool::default();
+    read_only.connect_read_only(&config).await.unwrap();
+    let rpc = AsyncRichIndexerHandle::new(read_only.clone(), None, usize::MAX);
+    assert!(sqlx::query("DELETE FROM block")
+        .execute(read_only.get_pool().unwrap())
+        .await
+        .is_err());
+    let mut tx = read_only.transaction().await.unwrap();
+    assert!(sqlx::query("DELETE FROM output")
+        .execute(tx.as_mut())
+        .await
+        .is_err());
+    drop(tx);
+    let tip = rpc.get_indexer_tip().await.unwrap().unwrap();
+    assert_eq!(9, tip.block_number.value());
+    assert_eq!(block_count, storage.fetch_count("block").await.unwrap());
+}
+
+#[test]
+async fn get_cells_by_lock_and_data_prefix() {
+    let lock = |args: u8| -> Script {
+        ScriptBuilder::default()
+            .code_hash(H256([1; 32]).pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![args]).pack())
+            .build()
+    };
+    let magic = vec![0x4e, 0x46, 0x54, 0x01];
+    let cells = [
+        (lock(1), [magic.clone(), vec![1]].concat()),
+        (lock(1), vec![0x4e, 0x46, 0x54, 0x02, 1]),
+        (lock(1), [magic.clone(), vec![2, 3]].concat()),
+        (lock(2), [magic.clone(), vec![1]].concat()),
+    ];
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs(cells.iter().map(|(lock, _)| {
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .lock(lock.clone())
+                .build()
+        }))
+        .outputs_data(cells.iter().map(|(_, data)| data.pack()))
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+
+    for data_prefix_len in [None, Some(3)] {
+        let config = RichIndexerConfig {
+            store: MEMORY_DB.into(),
+            data_prefix_len,
+            ..Default::default()
+        };
+        let mut store = SQLXPool::default();
+        store.connect(&config).await.unwrap();
+        AsyncRichIndexer::new(store.clone(), None, CustomFilters::new(None, None), config)
+            .append(&block)
+            .await
+            .unwrap();
+        let rpc = AsyncRichIndexerHandle::new(store, None, usize::MAX);
+
+        let search_key = IndexerSearchKey {
+            script: lock(1).into(),
+            script_type: IndexerScriptType::Lock,
+            script_search_mode: Some(IndexerSearchMode::Exact),
+            filter: Some(IndexerSearchKeyFilter {
+                output_data: Some(JsonBytes::from_vec(magic.clone())),
+                output_data_filter_mode: Some(IndexerSearchMode::Prefix),
+                ..Default::default()
+            }),
+            with_data: Some(true),
+            group_by_transaction: None,
+        };
+        let cells = rpc
+            .get_cells(search_key, IndexerOrder::Asc, 100u32.into(), None)
+            .await
+            .unwrap();
+        let out_points: Vec<ckb_jsonrpc_types::OutPoint> = cells
+            .objects
+            .into_iter()
+            .map(|cell| cell.out_point)
+            .collect();
+        let expected: Vec<ckb_jsonrpc_types::OutPoint> = [0u32, 2]
+            .into_iter()
+            .map(|index| OutPoint::new(cellbase.hash(), index).into())
+            .collect();
+        assert_eq!(expected, out_points);
+    }
+}
+
+#[test]
+async fn get_asset_types_for_lock() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let script = |code_hash: u8, args: u8| {
+        ScriptBuilder::default()
+            .code_hash(H256([code_hash; 32]).pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![args]).pack())
+            .build()
+    };
+    let wallet = script(1, 1);
+    let sudt = H256([2; 32]);
+    let spore = H256([3; 32]);
+    let cell = |lock: &Script, type_: Option<Script>| {
+        CellOutputBuilder::default()
+            .capacity(capacity_bytes!(1000).pack())
+            .lock(lock.clone())
+            .type_(type_.pack())
+            .build()
+    };
+    let outputs = vec![
+        cell(&wallet, None),
+        cell(&wallet, None),
+        cell(&wallet, Some(script(2, 1))),
+        cell(&wallet, Some(script(2, 2))),
+        cell(&wallet, Some(script(3, 1))),
+        cell(&script(1, 2), Some(script(3, 2))),
+    ];
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs_data(outputs.iter().map(|_| Bytes::new().pack()))
+        .outputs(outputs)
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    // the spore leaves the wallet
+    let transfer = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase.hash(), 4), 0))
+        .output(cell(&script(1, 2), Some(script(3, 1))))
+        .output_data(Default::default())
+        .build();
+    let cellbase1 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .witness(Script::default().into_witness())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase1, transfer])
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+
+    let counts = |asset_types: Vec<ckb_jsonrpc_types::IndexerAssetType>| {
+        asset_types
+            .into_iter()
+            .map(|asset_type| (asset_type.type_code_hash, asset_type.cell_count.value()))
+            .collect::<Vec<_>>()
+    };
+    let asset_types = rpc
+        .get_asset_types_for_lock(wallet.clone().into())
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![(None, 2), (Some(sudt.clone()), 2), (Some(spore), 1)],
+        counts(asset_types)
+    );
+
+    indexer.append(&block1).await.unwrap();
+    let asset_types = rpc.get_asset_types_for_lock(wallet.into()).await.unwrap();
+    assert_eq!(vec![(None, 2), (Some(sudt), 2)], counts(asset_types));
+
+    let asset_types = rpc
+        .get_asset_types_for_lock(script(9, 9).into())
+        .await
+        .unwrap();
+    assert!(asset_types.is_empty());
+}
+
+#[test]
+async fn get_tx_index_in_block() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let cell_output = || {
+        CellOutputBuilder::default()
+            .capacity(capacity_bytes!(1000).pack())
+            .build()
+    };
+    let cellbase0 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs(vec![cell_output(), cell_output()])
+        .outputs_data(vec![Bytes::new().pack(); 2])
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    let cellbase1 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .witness(Script::default().into_witness())
+        .build();
+    let spends: Vec<_> = (0..2)
+        .map(|index| {
+            TransactionBuilder::default()
+                .input(CellInput::new(OutPoint::new(cellbase0.hash(), index), 0))
+                .output(cell_output())
+                .output_data(Default::default())
+                .build()
+        })
+        .collect();
+    let block1 = BlockBuilder::default()
+        .transaction(cellbase1)
+        .transactions(spends.clone())
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block0).await.unwrap();
+    indexer.append(&block1).await.unwrap();
+
+    let position = rpc
+        .get_tx_index_in_block(spends[1].hash().unpack())
+        .await
+        .unwrap()
+        .unwrap();
+    let block1_hash: H256 = block1.hash().unpack();
+    assert_eq!(block1_hash, position.block_hash);
+    assert_eq!(2, position.tx_index.value());
+    assert_eq!(Some(3), position.tx_count.map(|count| count.value()));
+
+    let position = rpc
+        .get_tx_index_in_block(cellbase0.hash().unpack())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(0, position.tx_index.value());
+    assert_eq!(Some(1), position.tx_count.map(|count| count.value()));
+
+    assert!(rpc
+        .get_tx_index_in_block(H256([9; 32]))
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+async fn get_cell() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .lock(
+            ScriptBuilder::default()
+                .code_hash(H256([1; 32]).pack())
+                .hash_type(ScriptHashType::Type.into())
+                .args(Bytes::from(vec![1, 2, 3]).pack())
+                .build(),
+        )
+        .build();
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .outputs(vec![cell_output.clone(); 2])
+            .outputs_data(vec![Bytes::from(vec![number as u8]).pack(); 2])
+            .build()
+    };
+
+    // the second output of the cellbase of block 0 is spent in block 1
+    let cellbase0 = cellbase(0);
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 1), 0))
+        .output(cell_output.clone())
+        .output_data(Default::default())
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase(1), spend.clone()])
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let live = rpc
+        .get_cell(cellbase0.hash().unpack(), 0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!live.spent);
+    assert_eq!(None, live.spent_by_tx);
+    assert_eq!(cell_output, live.cell.output.into());
+    assert_eq!(Some(JsonBytes::from_vec(vec![0])), live.cell.output_data);
+    assert_eq!(
+        OutPoint::new(cellbase0.hash(), 0),
+        live.cell.out_point.into()
+    );
+    assert_eq!(0, live.cell.block_number.value());
+    assert_eq!(0, live.cell.tx_index.value());
+
+    let spent = rpc
+        .get_cell(cellbase0.hash().unpack(), 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(spent.spent);
+    assert_eq!(Some(spend.hash().unpack()), spent.spent_by_tx);
+    assert_eq!(
+        OutPoint::new(cellbase0.hash(), 1),
+        spent.cell.out_point.into()
+    );
+
+    let created = rpc
+        .get_cell(spend.hash().unpack(), 0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!created.spent);
+    assert_eq!(1, created.cell.block_number.value());
+    assert_eq!(1, created.cell.tx_index.value());
+
+    // nonexistent output index and transaction
+    assert!(rpc
+        .get_cell(cellbase0.hash().unpack(), 2)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(rpc.get_cell(H256([9; 32]), 0).await.unwrap().is_none());
+}
+
+#[test]
+async fn get_cells_by_data_hash() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    // the shared data is stored compressed, its hash is the one of the original data
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig {
+            data_compression_threshold: Some(1024),
+            ..Default::default()
+        },
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(100000).pack())
+        .lock(
+            ScriptBuilder::default()
+                .code_hash(H256([1; 32]).pack())
+                .hash_type(ScriptHashType::Type.into())
+                .build(),
+        )
+        .build();
+    let shared_data = Bytes::from(vec![7; 64 * 1024]);
+    let outputs_data = [
+        shared_data.clone(),
+        Bytes::from(vec![8; 64 * 1024]),
+        Bytes::new(),
+        shared_data.clone(),
+    ];
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs(vec![cell_output; outputs_data.len()])
+        .outputs_data(outputs_data.iter().map(|data| data.pack()))
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let shared_hash: H256 = packed::CellOutput::calc_data_hash(&shared_data).unpack();
+    let cells = rpc
+        .get_cells_by_data_hash(shared_hash.clone(), IndexerOrder::Asc, 10.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            OutPoint::new(cellbase.hash(), 0),
+            OutPoint::new(cellbase.hash(), 3)
+        ],
+        cells
+            .objects
+            .into_iter()
+            .map(|cell| cell.out_point.into())
+            .collect::<Vec<OutPoint>>()
+    );
+
+    // paged in descending order
+    let first_page = rpc
+        .get_cells_by_data_hash(shared_hash.clone(), IndexerOrder::Desc, 1.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(3, first_page.objects[0].out_point.index.value());
+    assert_eq!(
+        Some(JsonBytes::from_vec(shared_data.to_vec())),
+        first_page.objects[0].output_data
+    );
+    let second_page = rpc
+        .get_cells_by_data_hash(
+            shared_hash.clone(),
+            IndexerOrder::Desc,
+            1.into(),
+            Some(first_page.last_cursor),
+        )
+        .await
+        .unwrap();
+    assert_eq!(0, second_page.objects[0].out_point.index.value());
+
+    // empty data has the zero hash
+    let cells = rpc
+        .get_cells_by_data_hash(H256::default(), IndexerOrder::Asc, 10.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(1, cells.objects.len());
+    assert_eq!(2, cells.objects[0].out_point.index.value());
+
+    // outputs indexed before the column existed are filled in
+    sqlx::query("UPDATE output SET data_hash = NULL")
+        .execute(storage.get_pool().unwrap())
+        .await
+        .unwrap();
+    fill_missing_data_hashes(&storage).await.unwrap();
+    let cells = rpc
+        .get_cells_by_data_hash(shared_hash, IndexerOrder::Asc, 10.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(2, cells.objects.len());
+}
+
+#[test]
+async fn filter_compressed_cells_by_data() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig {
+            data_compression_threshold: Some(1024),
+            ..Default::default()
+        },
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256([1; 32]).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .lock(lock_script.clone())
+        .build();
+    // the first two cells are stored compressed
+    let large_data = [&[0x62, 0xe9][..], &b"compressible ".repeat(200)].concat();
+    let outputs_data = [
+        Bytes::from(large_data.clone()),
+        Bytes::from(b"other data ".repeat(200)),
+        Bytes::from(vec![0x62, 0xe9, 0x01]),
+    ];
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .outputs(vec![cell_output; outputs_data.len()])
+        .outputs_data(outputs_data.iter().map(|data| data.pack()))
+        .build();
+    let block = BlockBuilder::default()
+        .transaction(cellbase.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block).await.unwrap();
+
+    let compressed: i64 = sqlx::query("SELECT COUNT(*) FROM output WHERE data_compressed = 1")
+        .fetch_one(storage.get_pool().unwrap())
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(2, compressed);
+
+    let search_key = |filter: IndexerSearchKeyFilter| IndexerSearchKey {
+        script: lock_script.clone().into(),
+        filter: Some(filter),
+        ..Default::default()
+    };
+    let data_filter = |data: &[u8], mode: IndexerSearchMode| IndexerSearchKeyFilter {
+        output_data: Some(JsonBytes::from_vec(data.to_vec())),
+        output_data_filter_mode: Some(mode),
+        ..Default::default()
+    };
+    let cell_indexes = |filter: IndexerSearchKeyFilter| {
+        let rpc = rpc.clone();
+        let search_key = search_key(filter);
+        async move {
+            rpc.get_cells(search_key, IndexerOrder::Asc, 100.into(), None)
+                .await
+                .unwrap()
+                .objects
+                .into_iter()
+                .map(|cell| cell.out_point.index.value())
+                .collect::<Vec<u32>>()
+        }
+    };
+
+    assert_eq!(
+        vec![0, 2],
+        cell_indexes(data_filter(&[0x62, 0xe9], IndexerSearchMode::Prefix)).await
+    );
+    assert_eq!(
+        vec![0],
+        cell_indexes(data_filter(&large_data, IndexerSearchMode::Exact)).await
+    );
+    assert_eq!(
+        vec![0],
+        cell_indexes(data_filter(b"ible comp", IndexerSearchMode::Partial)).await
+    );
+    assert_eq!(
+        vec![0, 1, 2],
+        cell_indexes(data_filter(b"", IndexerSearchMode::Partial)).await
+    );
+    assert_eq!(
+        vec![0, 1],
+        cell_indexes(IndexerSearchKeyFilter {
+            output_data_len_range: Some(IndexerRange::new(1024u64, 4096u64)),
+            ..Default::default()
+        })
+        .await
+    );
+
+    let capacity = rpc
+        .get_cells_capacity(search_key(data_filter(
+            &[0x62, 0xe9],
+            IndexerSearchMode::Prefix,
+        )))
+        .await
+        .unwrap()
+        .unwrap()
+        .capacity;
+    assert_eq!(capacity_bytes!(2000).as_u64(), capacity.value());
+
+    let txs = rpc
+        .get_transactions(
+            search_key(data_filter(b"data other", IndexerSearchMode::Partial)),
+            IndexerOrder::Asc,
+            100.into(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(1, txs.objects.len());
+    match &txs.objects[0] {
+        IndexerTx::Ungrouped(tx_with_cell) => {
+            assert_eq!(1, tx_with_cell.io_index.value());
+        }
+        IndexerTx::Grouped(_) => panic!("unexpected grouped transaction"),
+    }
+}
+
+#[test]
+async fn page_compressed_cells_by_data() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig {
+            data_compression_threshold: Some(1024),
+            ..Default::default()
+        },
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock_script = ScriptBuilder::default()
+        .code_hash(H256([1; 32]).pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .lock(lock_script.clone())
+        .build();
+    // five compressed cells and an uncompressed one match the filter, each in a transaction
+    // of its own, the first one is spent
+    let large_data = [&[0x62, 0xe9][..], &b"compressible ".repeat(200)].concat();
+    let txs: Vec<_> = (0..5)
+        .map(|_| Bytes::from(large_data.clone()))
+        .chain([Bytes::from(vec![0x62, 0xe9, 0x01])])
+        .enumerate()
+        .map(|(i, data)| {
+            TransactionBuilder::default()
+                .input(CellInput::new(
+                    OutPoint::new(H256([2; 32]).pack(), i as u32),
+                    0,
+                ))
+                .output(cell_output.clone())
+                .output_data(data.pack())
+                .build()
+        })
+        .collect();
+    let cellbase0 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(0))
+        .witness(Script::default().into_witness())
+        .build();
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0)
+        .transactions(txs.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+    let cellbase1 = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .witness(Script::default().into_witness())
+        .build();
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(txs[0].hash(), 0), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(1000).pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase1, spend])
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    let search_key = || IndexerSearchKey {
+        script: lock_script.clone().into(),
+        filter: Some(IndexerSearchKeyFilter {
+            output_data: Some(JsonBytes::from_vec(vec![0x62, 0xe9])),
+            output_data_filter_mode: Some(IndexerSearchMode::Prefix),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    // the pages of the live cells hold every match once, in order
+    let order = |desc: bool| {
+        if desc {
+            IndexerOrder::Desc
+        } else {
+            IndexerOrder::Asc
+        }
+    };
+    for (desc, order_by) in [
+        (false, IndexerCellsOrderBy::Id),
+        (true, IndexerCellsOrderBy::Id),
+        (false, IndexerCellsOrderBy::Creation),
+        (true, IndexerCellsOrderBy::Creation),
+    ] {
+        let mut indexes = Vec::new();
+        let mut after = None;
+        loop {
+            let page = rpc
+                .get_cells_with_order_by(search_key(), order(desc), order_by, 2.into(), after)
+                .await
+                .unwrap();
+            if page.objects.is_empty() {
+                break;
+            }
+            indexes.extend(page.objects.iter().map(|cell| {
+                let tx_hash = cell.out_point.tx_hash.pack();
+                txs.iter().position(|tx| tx.hash() == tx_hash).unwrap()
+            }));
+            after = Some(page.last_cursor);
+        }
+        let mut expected = vec![1, 2, 3, 4, 5];
+        if desc {
+            expected.reverse();
+        }
+        assert_eq!(expected, indexes, "{} {:?}", desc, order_by);
+    }
+
+    // the spent cell is found by its creation and by the transaction consuming it
+    for desc in [false, true] {
+        let mut rows = Vec::new();
+        let mut after = None;
+        loop {
+            let page = rpc
+                .get_transactions(search_key(), order(desc), 2.into(), after)
+                .await
+                .unwrap();
+            if page.objects.is_empty() {
+                break;
+            }
+            rows.extend(page.objects.into_iter().map(|tx| match tx {
+                IndexerTx::Ungrouped(tx) => (
+                    matches!(tx.io_type, IndexerCellType::Input),
+                    tx.io_index.value(),
+                ),
+                IndexerTx::Grouped(_) => panic!("unexpected grouped transaction"),
+            }));
+            after = Some(page.last_cursor);
+        }
+        assert_eq!(7, rows.len(), "{}", desc);
+        assert_eq!(1, rows.iter().filter(|(is_input, _)| *is_input).count());
+    }
+    let grouped_search_key = || IndexerSearchKey {
+        group_by_transaction: Some(true),
+        ..search_key()
+    };
+    let mut after = None;
+    let mut tx_count = 0;
+    loop {
+        let page = rpc
+            .get_transactions(grouped_search_key(), IndexerOrder::Asc, 1.into(), after)
+            .await
+            .unwrap();
+        if page.objects.is_empty() {
+            break;
+        }
+        tx_count += page.objects.len();
+        after = Some(page.last_cursor);
+    }
+    assert_eq!(7, tx_count);
+}
+
+#[test]
+async fn get_transactions_using_dep() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let cell_output = CellOutputBuilder::default()
+        .capacity(capacity_bytes!(1000).pack())
+        .build();
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .outputs(vec![cell_output.clone(); 2])
+            .outputs_data(vec![Bytes::new().pack(); 2])
+            .build()
+    };
+    let cell_dep = |out_point: OutPoint, dep_type: DepType| {
+        packed::CellDep::new_builder()
+            .out_point(out_point)
+            .dep_type(dep_type.into())
+            .build()
+    };
+
+    // the outputs of the cellbase of block 0 are the deps of the transactions of block 1
+    let cellbase0 = cellbase(0);
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    indexer.append(&block0).await.unwrap();
+
+    let code = OutPoint::new(cellbase0.hash(), 0);
+    let dep_group = OutPoint::new(cellbase0.hash(), 1);
+    let tx1 = TransactionBuilder::default()
+        .cell_dep(cell_dep(code.clone(), DepType::Code))
+        .cell_dep(cell_dep(dep_group.clone(), DepType::DepGroup))
+        .header_dep(block0.hash())
+        .output(cell_output.clone())
+        .output_data(Default::default())
+        .build();
+    let tx2 = TransactionBuilder::default()
+        .cell_dep(cell_dep(code.clone(), DepType::Code))
+        .output(cell_output.clone())
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase(1), tx1.clone(), tx2.clone()])
+        .header(
+            HeaderBuilder::default()
+                .number(1.pack())
+                .parent_hash(block0.hash())
+                .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                .build(),
+        )
+        .build();
+    indexer.append(&block1).await.unwrap();
+
+    assert_eq!(
+        3,
+        storage
+            .fetch_count("tx_association_cell_dep")
+            .await
+            .unwrap()
+    );
+    let header_dep = storage
+        .fetch_one(SQLXPool::new_query(
+            r#"
+            SELECT ckb_transaction.tx_hash, block.block_hash
+            FROM tx_association_header_dep
+            JOIN ckb_transaction ON tx_association_header_dep.tx_id = ckb_transaction.id
+            JOIN block ON tx_association_header_dep.block_id = block.id
+            "#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        tx1.hash().raw_data().to_vec(),
+        header_dep.get::<Vec<u8>, _>("tx_hash")
+    );
+    assert_eq!(
+        block0.hash().raw_data().to_vec(),
+        header_dep.get::<Vec<u8>, _>("block_hash")
+    );
+
+    let tx_hashes = |txs: &IndexerPagination<IndexerTxInfo>| {
+        txs.objects
+            .iter()
+            .map(|tx| tx.tx_hash.pack())
+            .collect::<Vec<_>>()
+    };
+    let users = rpc
+        .get_transactions_using_dep(code.clone().into(), IndexerOrder::Asc, 10u32.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(vec![tx1.hash(), tx2.hash()], tx_hashes(&users));
+    assert!(users.objects.iter().all(|tx| tx.block_number.value() == 1));
+
+    let users = rpc
+        .get_transactions_using_dep(dep_group.into(), IndexerOrder::Asc, 10u32.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(vec![tx1.hash()], tx_hashes(&users));
+
+    // paging, newest first
+    let first_page = rpc
+        .get_transactions_using_dep(code.clone().into(), IndexerOrder::Desc, 1u32.into(), None)
+        .await
+        .unwrap();
+    assert_eq!(vec![tx2.hash()], tx_hashes(&first_page));
+    let second_page = rpc
+        .get_transactions_using_dep(
+            code.into(),
+            IndexerOrder::Desc,
+            1u32.into(),
+            Some(first_page.last_cursor),
+        )
+        .await
+        .unwrap();
+    assert_eq!(vec![tx1.hash()], tx_hashes(&second_page));
+
+    let unknown = rpc
+        .get_transactions_using_dep(
+            OutPoint::new(cellbase0.hash(), 2).into(),
+            IndexerOrder::Asc,
+            10u32.into(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(unknown.objects.is_empty());
+}
+
+#[test]
+async fn get_transaction_witnesses() {
+    for index_witnesses in [true, false] {
+        let storage = connect_sqlite(MEMORY_DB).await;
+        let indexer = AsyncRichIndexer::new(
+            storage.clone(),
+            None,
+            CustomFilters::new(None, None),
+            RichIndexerConfig {
+                index_witnesses,
+                ..Default::default()
+            },
+        );
+        let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+        let cell_output = CellOutputBuilder::default()
+            .capacity(capacity_bytes!(1000).pack())
+            .build();
+        let cellbase0 = TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(0))
+            .witness(Script::default().into_witness())
+            .outputs(vec![cell_output.clone(); 2])
+            .outputs_data(vec![Bytes::new().pack(); 2])
+            .build();
+        let block0 = BlockBuilder::default()
+            .transaction(cellbase0.clone())
+            .header(HeaderBuilder::default().number(0.pack()).build())
+            .build();
+        indexer.append(&block0).await.unwrap();
+
+        let witnesses = [Bytes::from(vec![1; 65]), Bytes::from(vec![2, 3])];
+        let tx = TransactionBuilder::default()
+            .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+            .input(CellInput::new(OutPoint::new(cellbase0.hash(), 1), 0))
+            .witnesses(witnesses.iter().map(|witness| witness.pack()))
+            .output(cell_output.clone())
+            .output_data(Default::default())
+            .build();
+        let cellbase1 = TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(1))
+            .witness(Script::default().into_witness())
+            .output(cell_output.clone())
+            .output_data(Default::default())
+            .build();
+        let block1 = BlockBuilder::default()
+            .transactions(vec![cellbase1, tx.clone()])
+            .header(
+                HeaderBuilder::default()
+                    .number(1.pack())
+                    .parent_hash(block0.hash())
+                    .epoch(EpochNumberWithFraction::new(1, 0, 1000).pack())
+                    .build(),
+            )
+            .build();
+        indexer.append(&block1).await.unwrap();
+
+        let stored = rpc
+            .get_transaction_witnesses(tx.hash().unpack())
+            .await
+            .unwrap();
+        if index_witnesses {
+            let expected: Vec<JsonBytes> = witnesses
+                .iter()
+                .map(|witness| JsonBytes::from_bytes(witness.clone()))
+                .collect();
+            assert_eq!(Some(expected), stored);
+        } else {
+            assert_eq!(None, stored);
+        }
+
+        // the witness metrics are recorded either way
+        let row = storage
+            .fetch_one(
+                SQLXPool::new_query("SELECT witness_count FROM ckb_transaction WHERE tx_hash = $1")
+                    .bind(tx.hash().raw_data().to_vec()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(2, row.get::<i32, _>("witness_count"));
+
+        assert_eq!(
+            None,
+            rpc.get_transaction_witnesses(H256([9; 32])).await.unwrap()
+        );
+    }
+}
+
 /// helper fn extracts script fields raw data
 fn extract_raw_data(script: &Script) -> Vec<u8> {
     [
@@ -1,5 +1,18 @@
 use super::*;
 
+use crate::indexer::delete_block;
+
+use ckb_indexer_sync::Error;
+use ckb_types::{
+    bytes::Bytes,
+    core::{
+        capacity_bytes, BlockBuilder, Capacity, EpochNumberWithFraction, HeaderBuilder,
+        ScriptHashType, TransactionBuilder,
+    },
+    packed::{Byte32, CellInput, CellOutputBuilder, OutPoint, Script, ScriptBuilder},
+    H256,
+};
+use sqlx::Row;
 use tokio::test;
 
 #[test]
@@ -12,6 +25,7 @@ async fn test_rollback_block_0() {
             Some("block.header.number.to_uint() >= \"0x0\".to_uint()"),
             None,
         ),
+        RichIndexerConfig::default(),
     );
 
     let data_path = String::from(BLOCK_DIR);
@@ -68,6 +82,7 @@ async fn test_rollback_block_9() {
             Some("block.header.number.to_uint() >= \"0x0\".to_uint()"),
             None,
         ),
+        RichIndexerConfig::default(),
     );
     insert_blocks(storage.clone()).await;
 
@@ -152,6 +167,7 @@ async fn test_block_filter_and_rollback_block() {
             Some("block.header.number.to_uint() >= \"0x1\".to_uint()"),
             None,
         ),
+        RichIndexerConfig::default(),
     );
 
     let data_path = String::from(BLOCK_DIR);
@@ -174,3 +190,202 @@ async fn test_block_filter_and_rollback_block() {
     assert_eq!(0, storage.fetch_count("input").await.unwrap());
     assert_eq!(0, storage.fetch_count("script").await.unwrap());
 }
+
+#[test]
+async fn competing_block_at_same_height() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+    let rpc = AsyncRichIndexerHandle::new(storage.clone(), None, usize::MAX);
+
+    let lock_script = |args: &[u8]| {
+        ScriptBuilder::default()
+            .code_hash(H256::default().pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(args.to_vec()).pack())
+            .build()
+    };
+    let block = |number: u64, parent_hash: Byte32, args: &[u8]| {
+        let cellbase = TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(lock_script(args))
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build();
+        let mut header = HeaderBuilder::default()
+            .number(number.pack())
+            .parent_hash(parent_hash);
+        if number > 0 {
+            header = header.epoch(EpochNumberWithFraction::new(number, 0, 1000).pack());
+        }
+        BlockBuilder::default()
+            .transaction(cellbase)
+            .header(header.build())
+            .build()
+    };
+    let count_cells = |args: &'static [u8]| {
+        let rpc = rpc.clone();
+        async move {
+            let search_key = IndexerSearchKey {
+                script: lock_script(args).into(),
+                script_type: IndexerScriptType::Lock,
+                script_search_mode: Some(IndexerSearchMode::Exact),
+                filter: None,
+                with_data: None,
+                group_by_transaction: None,
+            };
+            rpc.get_cells(search_key, IndexerOrder::Asc, 100u32.into(), None)
+                .await
+                .unwrap()
+                .objects
+                .len()
+        }
+    };
+
+    let block0 = block(0, Byte32::zero(), b"genesis");
+    let block1a = block(1, block0.hash(), b"a");
+    let block1b = block(1, block0.hash(), b"b");
+    indexer.append(&block0).await.unwrap();
+    indexer.append(&block1a).await.unwrap();
+    assert_eq!(1, count_cells(b"a").await);
+
+    // the competing block replaces the indexed one
+    indexer.append(&block1b).await.unwrap();
+    assert_eq!(0, count_cells(b"a").await);
+    assert_eq!(1, count_cells(b"b").await);
+    assert_eq!(1, count_cells(b"genesis").await);
+    assert_eq!(2, storage.fetch_count("block").await.unwrap());
+    let tip = rpc.get_indexer_tip().await.unwrap().unwrap();
+    assert_eq!(1, tip.block_number.value());
+    assert_eq!(Unpack::<H256>::unpack(&block1b.hash()), tip.block_hash);
+
+    // appending an already indexed block is a no-op
+    indexer.append(&block1b).await.unwrap();
+    assert_eq!(1, count_cells(b"b").await);
+    assert_eq!(2, storage.fetch_count("block").await.unwrap());
+
+    // stale blocks forking off below the tip are refused and leave the indexed chain alone,
+    // whether they are lower than the tip or at its height on top of another parent
+    let block2b = block(2, block1b.hash(), b"b2");
+    indexer.append(&block2b).await.unwrap();
+    let stale_blocks = [
+        block1a.clone(),
+        block(1, block0.hash(), b"c"),
+        block(2, block1a.hash(), b"a2"),
+    ];
+    for stale_block in stale_blocks {
+        let result = indexer.append(&stale_block).await;
+        assert!(matches!(result, Err(Error::Params(_))), "{:?}", result);
+        assert_eq!(3, storage.fetch_count("block").await.unwrap());
+        assert_eq!(1, count_cells(b"b").await);
+        assert_eq!(1, count_cells(b"b2").await);
+        assert_eq!(0, count_cells(b"a").await);
+        assert_eq!(0, count_cells(b"a2").await);
+        assert_eq!(0, count_cells(b"c").await);
+    }
+    let tip = rpc.get_indexer_tip().await.unwrap().unwrap();
+    assert_eq!(Unpack::<H256>::unpack(&block2b.hash()), tip.block_hash);
+}
+
+#[test]
+async fn delete_block_revives_spent_cells() {
+    let storage = connect_sqlite(MEMORY_DB).await;
+    let indexer = AsyncRichIndexer::new(
+        storage.clone(),
+        None,
+        CustomFilters::new(None, None),
+        RichIndexerConfig::default(),
+    );
+
+    let cell_output = || {
+        CellOutputBuilder::default()
+            .capacity(capacity_bytes!(1000).pack())
+            .lock(
+                ScriptBuilder::default()
+                    .code_hash(H256([1; 32]).pack())
+                    .hash_type(ScriptHashType::Type.into())
+                    .build(),
+            )
+            .build()
+    };
+    let cellbase = |number: u64| {
+        TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(cell_output())
+            .output_data(Default::default())
+            .build()
+    };
+    let header = |number: u64, parent_hash: Byte32| {
+        HeaderBuilder::default()
+            .number(number.pack())
+            .parent_hash(parent_hash)
+            .epoch(EpochNumberWithFraction::new(number, 0, 1000).pack())
+            .build()
+    };
+
+    let cellbase0 = cellbase(0);
+    let block0 = BlockBuilder::default()
+        .transaction(cellbase0.clone())
+        .header(HeaderBuilder::default().number(0.pack()).build())
+        .build();
+    let spend = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(cellbase0.hash(), 0), 0))
+        .output(cell_output())
+        .output_data(Default::default())
+        .build();
+    let block1 = BlockBuilder::default()
+        .transactions(vec![cellbase(1), spend])
+        .header(header(1, block0.hash()))
+        .build();
+    let block2 = BlockBuilder::default()
+        .transaction(cellbase(2))
+        .header(header(2, block1.hash()))
+        .build();
+    for block in [&block0, &block1, &block2] {
+        indexer.append(block).await.unwrap();
+    }
+    let is_spent = || async {
+        storage
+            .fetch_one(
+                SQLXPool::new_query(
+                    r#"
+                    SELECT output.is_spent FROM output
+                    JOIN ckb_transaction ON output.tx_id = ckb_transaction.id
+                    WHERE ckb_transaction.tx_hash = $1 AND output.output_index = 0
+                    "#,
+                )
+                .bind(cellbase0.hash().raw_data().to_vec()),
+            )
+            .await
+            .unwrap()
+            .get::<i32, _>("is_spent")
+    };
+    assert_eq!(1, is_spent().await);
+
+    // an unknown block is left alone
+    let mut tx = storage.transaction().await.unwrap();
+    delete_block(&[0; 32], &mut tx).await.unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(3, storage.fetch_count("block").await.unwrap());
+
+    // deleting block 1 takes block 2 with it and revives the spent cell
+    let mut tx = storage.transaction().await.unwrap();
+    delete_block(&block1.hash().raw_data(), &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(1, storage.fetch_count("block").await.unwrap());
+    assert_eq!(0, storage.fetch_count("input").await.unwrap());
+    assert_eq!(1, storage.fetch_count("output").await.unwrap());
+    assert_eq!(0, is_spent().await);
+}
@@ -0,0 +1,216 @@
+use super::*;
+
+use crate::indexer::RichIndexer;
+
+use ckb_app_config::{DBConfig, IndexerSyncConfig};
+use ckb_async_runtime::{new_background_runtime, Handle};
+use ckb_db::RocksDB;
+use ckb_db_schema::COLUMNS;
+use ckb_indexer_sync::{
+    new_secondary_db, Error, IndexerSync, IndexerSyncService, PoolService, SyncState,
+};
+use ckb_notify::NotifyService;
+use ckb_store::ChainDB;
+use ckb_types::{
+    core::{
+        capacity_bytes, BlockBuilder, BlockNumber, BlockView, Capacity, EpochNumberWithFraction,
+        HeaderBuilder, ScriptHashType, TransactionBuilder,
+    },
+    packed::{Byte32, CellInput, CellOutputBuilder, Script, ScriptBuilder},
+    H256,
+};
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Rich-indexer which records the sync lags reported by the sync loop.
+#[derive(Clone)]
+struct LagRecorder {
+    indexer: RichIndexer,
+    lags: Arc<Mutex<Vec<BlockNumber>>>,
+}
+
+impl IndexerSync for LagRecorder {
+    fn tip(&self) -> Result<Option<(BlockNumber, Byte32)>, Error> {
+        self.indexer.tip()
+    }
+
+    fn append(&self, block: &BlockView) -> Result<(), Error> {
+        self.indexer.append(block)
+    }
+
+    fn rollback(&self) -> Result<(), Error> {
+        self.indexer.rollback()
+    }
+
+    fn get_identity(&self) -> &str {
+        self.indexer.get_identity()
+    }
+
+    fn set_init_tip(&self, init_tip_number: u64, init_tip_hash: &H256) {
+        self.indexer.set_init_tip(init_tip_number, init_tip_hash)
+    }
+
+    fn report_sync_lag(&self, lag: BlockNumber) {
+        self.indexer.report_sync_lag(lag);
+        self.lags.lock().unwrap().push(lag);
+    }
+}
+
+/// Writes a chain of blocks to a node database, of which the blocks up to `attached` are on
+/// the main chain, while the last one is the tip header.
+fn write_node_db(path: &Path, len: u64, attached: BlockNumber) -> RocksDB {
+    let db = RocksDB::open_in(path, COLUMNS);
+    let chain_db = ChainDB::new(db.clone(), Default::default());
+    let mut parent_hash = Byte32::zero();
+    let mut tip_header = None;
+    for number in 0..len {
+        let cellbase = TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(number))
+            .witness(Script::default().into_witness())
+            .output(
+                CellOutputBuilder::default()
+                    .capacity(capacity_bytes!(1000).pack())
+                    .lock(
+                        ScriptBuilder::default()
+                            .code_hash(H256([1; 32]).pack())
+                            .hash_type(ScriptHashType::Type.into())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build();
+        let block = BlockBuilder::default()
+            .transaction(cellbase)
+            .header(
+                HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .epoch(EpochNumberWithFraction::new(number / 1000, number % 1000, 1000).pack())
+                    .build(),
+            )
+            .build();
+        let txn = chain_db.begin_transaction();
+        txn.insert_block(&block).unwrap();
+        if number <= attached {
+            txn.attach_block(&block).unwrap();
+        }
+        txn.commit().unwrap();
+        parent_hash = block.hash();
+        tip_header = Some(block.header());
+    }
+    let txn = chain_db.begin_transaction();
+    txn.insert_tip_header(&tip_header.unwrap()).unwrap();
+    txn.commit().unwrap();
+    db
+}
+
+/// Starts the sync loop on a rich-indexer store in `dir`, fed by the node database in `dir`.
+fn spawn_sync(dir: &Path, async_handle: &Handle) -> (IndexerSyncService, LagRecorder) {
+    let config = IndexerSyncConfig {
+        secondary_path: dir.join("secondary"),
+        poll_interval: 1,
+        index_tx_pool: false,
+        db_keep_log_file_num: None,
+        block_fetch_parallelism: 1,
+    };
+    let secondary_db = new_secondary_db(
+        &DBConfig {
+            path: dir.join("node"),
+            ..Default::default()
+        },
+        &config,
+    );
+    let sync = IndexerSyncService::new(
+        secondary_db,
+        PoolService::new(false, async_handle.clone()),
+        &config,
+        async_handle.clone(),
+        None,
+    );
+
+    let rich_indexer_config = RichIndexerConfig {
+        store: dir.join("indexer.db"),
+        ..Default::default()
+    };
+    let mut store = SQLXPool::default();
+    async_handle
+        .block_on(store.connect(&rich_indexer_config))
+        .unwrap();
+    let indexer = LagRecorder {
+        indexer: RichIndexer::new(
+            store,
+            None,
+            CustomFilters::new(None, None),
+            rich_indexer_config,
+            async_handle.clone(),
+            usize::MAX,
+        ),
+        lags: Default::default(),
+    };
+
+    let notify_controller = NotifyService::new(Default::default(), async_handle.clone()).start();
+    sync.spawn_poll(notify_controller, "Test".to_string(), indexer.clone());
+    (sync, indexer)
+}
+
+fn indexer_tip(indexer: &LagRecorder) -> Option<BlockNumber> {
+    indexer.tip().unwrap().map(|(number, _)| number)
+}
+
+fn wait_until<F: FnMut() -> bool>(mut condition: F) {
+    let started_at = Instant::now();
+    while !condition() {
+        assert!(
+            started_at.elapsed() < Duration::from_secs(30),
+            "timeout waiting for the sync loop"
+        );
+        sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn sync_loop_reports_lag() {
+    let dir = tempfile::tempdir().unwrap();
+    let async_handle = new_background_runtime();
+    // the node is 5 blocks ahead of its main chain which can be indexed
+    let _node_db = write_node_db(&dir.path().join("node"), 21, 15);
+    let (_sync, indexer) = spawn_sync(dir.path(), &async_handle);
+
+    wait_until(|| indexer_tip(&indexer) == Some(15));
+    wait_until(|| indexer.lags.lock().unwrap().len() == 16);
+    assert_eq!(
+        (5..=20).rev().collect::<Vec<BlockNumber>>(),
+        *indexer.lags.lock().unwrap()
+    );
+}
+
+#[test]
+fn pause_and_resume_sync_loop() {
+    const BLOCKS: u64 = 1_000;
+
+    let dir = tempfile::tempdir().unwrap();
+    let async_handle = new_background_runtime();
+    let _node_db = write_node_db(&dir.path().join("node"), BLOCKS, BLOCKS - 1);
+    let (sync, indexer) = spawn_sync(dir.path(), &async_handle);
+    let control = sync.control();
+
+    // pause once the first blocks are indexed
+    wait_until(|| indexer_tip(&indexer).is_some());
+    control.pause();
+    wait_until(|| control.state() == SyncState::Paused);
+    let paused_tip = indexer_tip(&indexer).unwrap();
+    assert!(paused_tip < BLOCKS - 1);
+
+    // no block is written while paused, including by the polls of the follow-up sync
+    sleep(Duration::from_millis(2_500));
+    assert_eq!(Some(paused_tip), indexer_tip(&indexer));
+    assert_eq!(SyncState::Paused, control.state());
+
+    control.resume();
+    assert_eq!(SyncState::Running, control.state());
+    wait_until(|| indexer_tip(&indexer) == Some(BLOCKS - 1));
+}